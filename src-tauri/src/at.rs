@@ -0,0 +1,297 @@
+//! AT 命令：发送一条命令并等待最终结果码（OK、ERROR、+CME ERROR: n 等），
+//! 把应答中的信息行与主动上报（URC）分开，命令与应答成对返回。
+//!
+//! 收发通过 SerialManager 的 send 与 read_available 完成，执行期间调用方应暂停其他 read_available，
+//! 期间的收发记录随应答一并返回。
+
+use crate::serial::{DataEntry, SerialManager};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 未在命令库中指定超时的命令使用的超时
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(2000);
+
+/// 命令库中的一条命令
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct AtCommandInfo {
+    pub command: &'static str,
+    pub description: &'static str,
+    pub timeout_ms: u64,
+}
+
+const fn cmd(command: &'static str, description: &'static str, timeout_ms: u64) -> AtCommandInfo {
+    AtCommandInfo { command, description, timeout_ms }
+}
+
+/// 常用 AT 命令，用于补全与超时设置
+pub const LIBRARY: &[AtCommandInfo] = &[
+    // 通用
+    cmd("AT", "测试连接", 1000),
+    cmd("ATI", "模块信息", 1000),
+    cmd("ATE0", "关闭回显", 1000),
+    cmd("ATE1", "打开回显", 1000),
+    cmd("AT&F", "恢复出厂设置", 2000),
+    cmd("AT&W", "保存设置", 2000),
+    cmd("AT+IPR?", "查询串口波特率", 1000),
+    cmd("AT+IPR=", "设置串口波特率", 1000),
+    // 蜂窝模块
+    cmd("AT+CGMI", "厂商", 1000),
+    cmd("AT+CGMM", "型号", 1000),
+    cmd("AT+CGMR", "固件版本", 1000),
+    cmd("AT+CGSN", "IMEI", 1000),
+    cmd("AT+CIMI", "IMSI", 1000),
+    cmd("AT+CCID", "SIM 卡 ICCID", 1000),
+    cmd("AT+CPIN?", "SIM 卡状态", 5000),
+    cmd("AT+CSQ", "信号质量", 1000),
+    cmd("AT+CREG?", "网络注册状态", 1000),
+    cmd("AT+CEREG?", "LTE 网络注册状态", 1000),
+    cmd("AT+CGREG?", "GPRS 注册状态", 1000),
+    cmd("AT+COPS?", "当前运营商", 3000),
+    cmd("AT+COPS=?", "搜索运营商", 180000),
+    cmd("AT+CFUN?", "功能模式", 1000),
+    cmd("AT+CFUN=", "设置功能模式", 15000),
+    cmd("AT+CGATT?", "附着状态", 1000),
+    cmd("AT+CGATT=", "附着/去附着", 75000),
+    cmd("AT+CGDCONT?", "PDP 上下文", 1000),
+    cmd("AT+CGDCONT=", "设置 PDP 上下文", 1000),
+    cmd("AT+CGPADDR", "PDP 地址", 1000),
+    cmd("AT+CMEE=2", "详细错误码", 1000),
+    cmd("AT+CMGF=", "短信格式 (0 PDU / 1 文本)", 1000),
+    cmd("AT+CMGS=", "发送短信，出现 > 后输入内容", 60000),
+    cmd("AT+CMGL=", "列出短信", 5000),
+    cmd("AT+CMGR=", "读取短信", 5000),
+    cmd("AT+CNMI=", "新短信提示方式", 1000),
+    cmd("ATD", "拨号", 60000),
+    cmd("ATA", "接听", 20000),
+    cmd("ATH", "挂断", 20000),
+    // Wi-Fi 模块 (ESP-AT)
+    cmd("AT+RST", "重启模块", 5000),
+    cmd("AT+GMR", "固件版本", 1000),
+    cmd("AT+CWMODE?", "Wi-Fi 模式", 1000),
+    cmd("AT+CWMODE=", "设置 Wi-Fi 模式 (1 STA / 2 AP / 3 STA+AP)", 1000),
+    cmd("AT+CWLAP", "扫描热点", 15000),
+    cmd("AT+CWJAP?", "当前连接的热点", 1000),
+    cmd("AT+CWJAP=", "连接热点", 20000),
+    cmd("AT+CWQAP", "断开热点", 2000),
+    cmd("AT+CIFSR", "本机 IP", 2000),
+    cmd("AT+CIPSTATUS", "连接状态", 2000),
+    cmd("AT+CIPSTART=", "建立 TCP/UDP 连接", 20000),
+    cmd("AT+CIPSEND=", "发送数据，出现 > 后输入内容", 10000),
+    cmd("AT+CIPCLOSE", "关闭连接", 5000),
+    cmd("AT+PING=", "Ping", 10000),
+    // BLE 模块
+    cmd("AT+NAME?", "蓝牙名称", 1000),
+    cmd("AT+NAME=", "设置蓝牙名称", 1000),
+    cmd("AT+ADDR?", "蓝牙地址", 1000),
+    cmd("AT+ROLE?", "主从角色", 1000),
+    cmd("AT+BAUD?", "波特率", 1000),
+    cmd("AT+BLEINIT=", "初始化 BLE (ESP-AT)", 2000),
+    cmd("AT+BLESCAN=", "扫描 BLE 设备", 15000),
+];
+
+/// 命令库中与 command 匹配的条目：优先完全相同，其次按 `=`、`?` 之前的部分匹配
+pub fn lookup(command: &str) -> Option<&'static AtCommandInfo> {
+    let command = command.trim().to_uppercase();
+    LIBRARY.iter().find(|c| c.command == command).or_else(|| {
+        let base = base_name(&command);
+        LIBRARY
+            .iter()
+            .find(|c| (c.command.ends_with('=') || c.command == "ATD") && command.starts_with(c.command))
+            .or_else(|| LIBRARY.iter().find(|c| base_name(c.command) == base && base.len() > 2))
+    })
+}
+
+// AT+CREG? → AT+CREG
+fn base_name(command: &str) -> &str {
+    command.split(['=', '?']).next().unwrap_or(command)
+}
+
+/// 命令的超时：命令库中的设置，或默认值
+pub fn timeout_for(command: &str) -> Duration {
+    lookup(command)
+        .map(|c| Duration::from_millis(c.timeout_ms))
+        .unwrap_or(DEFAULT_TIMEOUT)
+}
+
+/// 最终结果码
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "code", rename_all = "snake_case")]
+pub enum AtResult {
+    Ok,
+    Error,
+    CmeError(String),
+    CmsError(String),
+    /// 等待输入数据的 `>` 提示（AT+CMGS、AT+CIPSEND）
+    Prompt,
+    /// 其他结果码，如 NO CARRIER、BUSY、CONNECT、FAIL
+    Other(String),
+    Timeout,
+}
+
+impl AtResult {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, AtResult::Ok | AtResult::Prompt)
+    }
+
+    // 一行是否为最终结果码。CONNECT 只在拨号类命令中作为结果码，ESP-AT 建立连接时也会输出
+    fn parse(line: &str, command: &str) -> Option<Self> {
+        if let Some(code) = line.strip_prefix("+CME ERROR:") {
+            return Some(AtResult::CmeError(code.trim().to_string()));
+        }
+        if let Some(code) = line.strip_prefix("+CMS ERROR:") {
+            return Some(AtResult::CmsError(code.trim().to_string()));
+        }
+        match line {
+            "OK" | "SEND OK" => Some(AtResult::Ok),
+            "ERROR" => Some(AtResult::Error),
+            "NO CARRIER" | "BUSY" | "NO ANSWER" | "NO DIALTONE" | "FAIL" | "SEND FAIL" => {
+                Some(AtResult::Other(line.to_string()))
+            }
+            _ if (line == "CONNECT" || line.starts_with("CONNECT "))
+                && ["ATD", "ATA", "ATO"].iter().any(|p| command.to_uppercase().starts_with(p)) =>
+            {
+                Some(AtResult::Other(line.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AtResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtResult::Ok => write!(f, "OK"),
+            AtResult::Error => write!(f, "ERROR"),
+            AtResult::CmeError(code) => write!(f, "+CME ERROR: {}", code),
+            AtResult::CmsError(code) => write!(f, "+CMS ERROR: {}", code),
+            AtResult::Prompt => write!(f, ">"),
+            AtResult::Other(code) => write!(f, "{}", code),
+            AtResult::Timeout => write!(f, "超时"),
+        }
+    }
+}
+
+/// 一条命令及其应答
+#[derive(Debug, Clone, Serialize)]
+pub struct AtResponse {
+    pub command: String,
+    pub lines: Vec<String>, // 信息行，不含回显与结果码
+    pub result: AtResult,
+    pub urcs: Vec<String>, // 期间收到的主动上报
+    pub elapsed_ms: u64,
+    pub entries: Vec<DataEntry>, // 期间的收发记录
+}
+
+// 不以 + 开头的常见主动上报
+const PLAIN_URCS: &[&str] = &[
+    "RING",
+    "RDY",
+    "READY",
+    "ready",
+    "Call Ready",
+    "SMS Ready",
+    "NORMAL POWER DOWN",
+    "WIFI CONNECTED",
+    "WIFI GOT IP",
+    "WIFI DISCONNECT",
+];
+
+/// 判断一行是否为主动上报：在 URC 列表中，或以 + 开头但与命令的应答前缀不同
+pub fn is_urc(line: &str, command: &str) -> bool {
+    if PLAIN_URCS.contains(&line) {
+        return true;
+    }
+    if !line.starts_with('+') {
+        return false;
+    }
+    // AT+CSQ 的应答为 +CSQ: ...
+    let prefix = line.split(':').next().unwrap_or(line);
+    let expected = base_name(command.trim()).get(2..).unwrap_or("").to_uppercase();
+    prefix.to_uppercase() != expected
+}
+
+/// 把 data 中完整的行取出，不完整的部分留在 buffer 中
+pub fn take_lines(buffer: &mut String, data: &str) -> Vec<String> {
+    buffer.push_str(data);
+    let mut lines = Vec::new();
+    while let Some(end) = buffer.find(['\r', '\n']) {
+        let line = buffer[..end].trim().to_string();
+        buffer.drain(..=end);
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// 发送一条 AT 命令并等待最终结果码，超时返回 AtResult::Timeout。
+/// 发送前已在待取队列中的接收数据按主动上报处理
+pub fn execute(
+    manager: &Mutex<SerialManager>,
+    command: &str,
+    timeout: Duration,
+    cancel: &AtomicBool,
+) -> Result<AtResponse, String> {
+    let command = command.trim().to_string();
+    let mut response = AtResponse {
+        command: command.clone(),
+        lines: Vec::new(),
+        result: AtResult::Timeout,
+        urcs: Vec::new(),
+        elapsed_ms: 0,
+        entries: Vec::new(),
+    };
+
+    let mut buffer = String::new();
+    let before = manager.lock().read_available()?;
+    for entry in before.iter().filter(|e| e.direction == "rx") {
+        response.urcs.extend(take_lines(&mut buffer, &entry.data));
+    }
+    response.entries.extend(before);
+    buffer.clear();
+
+    manager.lock().send(&format!("{}\r\n", command), false)?;
+    let start = Instant::now();
+    let mut echoed = false;
+    let mut done = false;
+
+    while !done && start.elapsed() < timeout {
+        if cancel.load(Ordering::SeqCst) {
+            return Err("已取消".to_string());
+        }
+        let entries = manager.lock().read_available()?;
+        for entry in entries.iter().filter(|e| e.direction == "rx") {
+            for line in take_lines(&mut buffer, &entry.data) {
+                if done {
+                    response.urcs.push(line);
+                } else if !echoed && line.eq_ignore_ascii_case(&command) {
+                    echoed = true;
+                } else if let Some(result) = AtResult::parse(&line, &command) {
+                    response.result = result;
+                    done = true;
+                } else if is_urc(&line, &command) {
+                    response.urcs.push(line);
+                } else {
+                    response.lines.push(line);
+                }
+            }
+            // > 提示后没有换行
+            if !done && buffer.trim() == ">" {
+                buffer.clear();
+                response.result = AtResult::Prompt;
+                done = true;
+            }
+        }
+        response.entries.extend(entries);
+        if !done {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    response.elapsed_ms = start.elapsed().as_millis() as u64;
+    Ok(response)
+}
@@ -0,0 +1,147 @@
+//! JSON Lines 抓包格式：每行一个 JSON 对象，包含收发数据与连接、设置、控制线等事件
+//!
+//! ```text
+//! {"ts":"2024-05-01T10:00:00.123456+08:00","session":"3f2a9c1e","seq":0,"type":"connect","port":"COM3","baud_rate":115200,"data_bits":8,"stop_bits":1,"parity":"none"}
+//! {"ts":"2024-05-01T10:00:00.200001+08:00","session":"3f2a9c1e","seq":1,"type":"data","direction":"tx","hex":"41540D0A"}
+//! {"ts":"2024-05-01T10:00:00.215872+08:00","session":"3f2a9c1e","seq":2,"type":"control_line","line":"dtr","level":false}
+//! ```
+
+use crate::serial::{self, DataEntry};
+use crate::stats::SessionStats;
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// 串口参数，连接事件中记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortSettings {
+    pub port: String,
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub stop_bits: u8,
+    pub parity: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CaptureEvent {
+    Data {
+        direction: String, // "rx" or "tx"
+        hex: String,       // 原始字节，无分隔的十六进制
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        valid: Option<bool>,
+    },
+    Connect(PortSettings),
+    Disconnect,
+    Settings {
+        key: String,
+        value: String,
+    },
+    ControlLine {
+        line: String, // "dtr" or "rts"
+        level: bool,
+    },
+    Marker {
+        text: String,
+    },
+    /// 停止记录时写在结尾的流量统计
+    Stats(SessionStats),
+}
+
+impl CaptureEvent {
+    pub fn from_entry(entry: &DataEntry) -> Self {
+        CaptureEvent::Data {
+            direction: entry.direction.clone(),
+            hex: entry.hex.replace(' ', ""),
+            valid: entry.valid,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    pub ts: String, // RFC 3339，微秒精度
+    pub session: String,
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: CaptureEvent,
+}
+
+impl CaptureRecord {
+    pub fn new(session: &str, seq: u64, event: CaptureEvent) -> Self {
+        Self::at(session, seq, event, Local::now())
+    }
+
+    /// 指定记录时刻，收发数据以收到或发出的时间为准
+    pub fn at(session: &str, seq: u64, event: CaptureEvent, time: DateTime<Local>) -> Self {
+        Self {
+            ts: time.to_rfc3339_opts(SecondsFormat::Micros, false),
+            session: session.to_string(),
+            seq,
+            event,
+        }
+    }
+
+    pub fn timestamp(&self) -> Result<DateTime<FixedOffset>, String> {
+        DateTime::parse_from_rfc3339(&self.ts).map_err(|e| format!("无效的时间戳 {}: {}", self.ts, e))
+    }
+
+    /// 数据记录的原始字节，其他事件返回 None
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        match &self.event {
+            CaptureEvent::Data { hex, .. } => serial::parse_hex_string(hex).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// 逐行读取抓包文件，.gz 文件自动解压
+pub struct CaptureReader {
+    lines: std::io::Lines<BufReader<Box<dyn Read + Send>>>,
+    line_no: usize,
+}
+
+impl CaptureReader {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("打开抓包文件 {} 失败: {}", path, e))?;
+        let input: Box<dyn Read + Send> = if Path::new(path).extension().is_some_and(|e| e == "gz") {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+
+        Ok(Self {
+            lines: BufReader::new(input).lines(),
+            line_no: 0,
+        })
+    }
+}
+
+impl Iterator for CaptureReader {
+    type Item = Result<CaptureRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(format!("读取抓包文件失败: {}", e))),
+            };
+            self.line_no += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            return Some(
+                serde_json::from_str(&line).map_err(|e| format!("第 {} 行解析失败: {}", self.line_no, e)),
+            );
+        }
+    }
+}
+
+/// 读取整个抓包文件
+pub fn read_capture(path: &str) -> Result<Vec<CaptureRecord>, String> {
+    CaptureReader::open(path)?.collect()
+}
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+/// 帧校验算法，校验值附加在帧尾
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumAlgorithm {
+    #[default]
+    None,
+    Sum8,
+    Xor8,
+    Crc8,
+    Crc16Modbus, // 低字节在前
+    Crc16Ccitt,  // CCITT-FALSE，高字节在前
+    Crc32,       // IEEE，低字节在前
+}
+
+impl ChecksumAlgorithm {
+    pub const ALL: [ChecksumAlgorithm; 7] = [
+        ChecksumAlgorithm::None,
+        ChecksumAlgorithm::Sum8,
+        ChecksumAlgorithm::Xor8,
+        ChecksumAlgorithm::Crc8,
+        ChecksumAlgorithm::Crc16Modbus,
+        ChecksumAlgorithm::Crc16Ccitt,
+        ChecksumAlgorithm::Crc32,
+    ];
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "" | "none" | "off" => Ok(ChecksumAlgorithm::None),
+            "sum8" | "sum" => Ok(ChecksumAlgorithm::Sum8),
+            "xor8" | "xor" => Ok(ChecksumAlgorithm::Xor8),
+            "crc8" => Ok(ChecksumAlgorithm::Crc8),
+            "crc16" | "crc16_modbus" | "modbus" => Ok(ChecksumAlgorithm::Crc16Modbus),
+            "crc16_ccitt" | "ccitt" => Ok(ChecksumAlgorithm::Crc16Ccitt),
+            "crc32" => Ok(ChecksumAlgorithm::Crc32),
+            _ => Err(format!("未知校验算法: {}", s)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::None => "none",
+            ChecksumAlgorithm::Sum8 => "sum8",
+            ChecksumAlgorithm::Xor8 => "xor8",
+            ChecksumAlgorithm::Crc8 => "crc8",
+            ChecksumAlgorithm::Crc16Modbus => "crc16_modbus",
+            ChecksumAlgorithm::Crc16Ccitt => "crc16_ccitt",
+            ChecksumAlgorithm::Crc32 => "crc32",
+        }
+    }
+
+    /// 校验值占用的字节数
+    pub fn width(&self) -> usize {
+        match self {
+            ChecksumAlgorithm::None => 0,
+            ChecksumAlgorithm::Sum8 | ChecksumAlgorithm::Xor8 | ChecksumAlgorithm::Crc8 => 1,
+            ChecksumAlgorithm::Crc16Modbus | ChecksumAlgorithm::Crc16Ccitt => 2,
+            ChecksumAlgorithm::Crc32 => 4,
+        }
+    }
+
+    /// 计算校验值，按线上字节顺序返回
+    pub fn compute(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            ChecksumAlgorithm::None => vec![],
+            ChecksumAlgorithm::Sum8 => vec![data.iter().fold(0u8, |acc, b| acc.wrapping_add(*b))],
+            ChecksumAlgorithm::Xor8 => vec![data.iter().fold(0u8, |acc, b| acc ^ b)],
+            ChecksumAlgorithm::Crc8 => vec![crc8(data)],
+            ChecksumAlgorithm::Crc16Modbus => crc16_modbus(data).to_le_bytes().to_vec(),
+            ChecksumAlgorithm::Crc16Ccitt => crc16_ccitt(data).to_be_bytes().to_vec(),
+            ChecksumAlgorithm::Crc32 => crc32(data).to_le_bytes().to_vec(),
+        }
+    }
+
+    pub fn append(&self, data: &mut Vec<u8>) {
+        let sum = self.compute(data);
+        data.extend_from_slice(&sum);
+    }
+
+    /// 校验帧尾的校验值。未启用校验时返回 None
+    pub fn verify(&self, frame: &[u8]) -> Option<bool> {
+        let width = self.width();
+        if width == 0 {
+            return None;
+        }
+        if frame.len() <= width {
+            return Some(false);
+        }
+
+        let (body, sum) = frame.split_at(frame.len() - width);
+        Some(self.compute(body) == sum)
+    }
+}
+
+/// CRC-8 (poly 0x07, init 0x00)
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &b in data {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// CRC-16/MODBUS (poly 0xA001 反射, init 0xFFFF)
+pub fn crc16_modbus(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &b in data {
+        crc ^= b as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+/// CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF)
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// CRC-16/XMODEM (poly 0x1021, init 0x0000)
+pub fn crc16_xmodem(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// CRC-32/IEEE
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Modbus ASCII 的 LRC：字节和取补
+pub fn lrc(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, &b| acc.wrapping_add(b)).wrapping_neg()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 各算法对 "123456789" 的标准校验值
+    #[test]
+    fn check_values() {
+        let data = b"123456789";
+        assert_eq!(crc16_modbus(data), 0x4B37);
+        assert_eq!(crc16_xmodem(data), 0x31C3);
+        assert_eq!(crc16_ccitt(data), 0x29B1);
+        assert_eq!(crc32(data), 0xCBF43926);
+        assert_eq!(crc8(data), 0xF4);
+    }
+
+    #[test]
+    fn lrc_of_modbus_ascii_request() {
+        assert_eq!(lrc(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x01]), 0xFB);
+    }
+}
@@ -1,18 +1,43 @@
-use crate::serial::{self, SerialManager};
-use chrono::Local;
+use crate::at::{self, AtResponse, AtResult};
+use crate::checksum::ChecksumAlgorithm;
+use crate::config::{self, AppConfig, CommandPreset, LogConfig};
+use crate::decoder::{self, Field};
+use crate::frames;
+use crate::framing::Framing;
+use crate::history;
+use crate::modbus::{self, ModbusMode, ModbusRequest, ModbusResponse, ValueFormat, WordOrder};
+use crate::modbus_gateway::{self, GatewayEvent};
+use crate::modbus_slave::{self, RegisterMap, RegisterTable, SlaveEvent, SlaveOptions};
+use crate::nmea::{self, GpsFix};
+use crate::plot::{self, Plotter};
+use crate::rawsend::RawSendOptions;
+use crate::replay::{self, ReplayOptions, ReplaySink, ReplayTarget};
+use crate::scheduler::{ScheduleSpec, Scheduler};
+use crate::script::{self, Script, StepReport, StepStatus};
+use crate::scripting::{self, LogFn, ScriptHooks};
+use crate::serial::{self, DataEntry, SerialManager};
+use crate::simulator::{self, SimEvent, SimOptions, SimPort, SimTable, SimTarget};
+use crate::stats::{self, SessionStats};
+use crate::timestamp::TimestampMode;
+use crate::transfer::{self, SessionLink, TransferProgress, TransferRequest};
+use crate::trigger::{self, TriggerAction, TriggerRule};
+use crate::zmodem::{self, ZmodemRequest};
+use clap::{Parser, Subcommand};
 use crossterm::{
     event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
     terminal::{disable_raw_mode, enable_raw_mode},
 };
+use parking_lot::Mutex;
 use rustyline::completion::{Completer, Pair};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
 use rustyline::hint::Hinter;
 use rustyline::validate::Validator;
 use rustyline::{Context, Editor, Helper};
+use std::collections::HashMap;
 use std::io::{self, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
@@ -20,11 +45,15 @@ use std::time::Duration;
 
 struct XToolsHelper {
     commands: Vec<String>,
+    config: Arc<Mutex<AppConfig>>,
+    at_mode: Arc<AtomicBool>, // AT 模式下整行补全 AT 命令
 }
 
 impl XToolsHelper {
-    fn new() -> Self {
+    fn new(config: Arc<Mutex<AppConfig>>, at_mode: Arc<AtomicBool>) -> Self {
         Self {
+            config,
+            at_mode,
             commands: vec![
                 "help".to_string(),
                 "list".to_string(),
@@ -36,6 +65,35 @@ impl XToolsHelper {
                 "send".to_string(),
                 "s".to_string(),
                 "hex".to_string(),
+                "checksum".to_string(),
+                "framing".to_string(),
+                "timestamp".to_string(),
+                "repeat".to_string(),
+                "run".to_string(),
+                "preset".to_string(),
+                "script".to_string(),
+                "rhai".to_string(),
+                "sx".to_string(),
+                "sy".to_string(),
+                "rx".to_string(),
+                "ry".to_string(),
+                "sz".to_string(),
+                "rz".to_string(),
+                "sendfile".to_string(),
+                "log".to_string(),
+                "replay".to_string(),
+                "sim".to_string(),
+                "mb".to_string(),
+                "mbs".to_string(),
+                "mbgw".to_string(),
+                "decode".to_string(),
+                "frame".to_string(),
+                "gps".to_string(),
+                "at".to_string(),
+                "plot".to_string(),
+                "history".to_string(),
+                "grep".to_string(),
+                "trigger".to_string(),
                 "terminal".to_string(),
                 "term".to_string(),
                 "config".to_string(),
@@ -43,6 +101,7 @@ impl XToolsHelper {
                 "clear".to_string(),
                 "cls".to_string(),
                 "status".to_string(),
+                "stats".to_string(),
                 "st".to_string(),
                 "exit".to_string(),
                 "quit".to_string(),
@@ -64,6 +123,25 @@ impl Completer for XToolsHelper {
         let mut candidates = Vec::new();
         let input = &line[..pos];
         
+        // AT 模式与 at 命令补全命令库中的 AT 命令
+        let at_input = if self.at_mode.load(Ordering::SeqCst) {
+            Some(input)
+        } else {
+            input.strip_prefix("at ")
+        };
+        if let Some(prefix) = at_input {
+            let upper = prefix.to_uppercase();
+            for info in at::LIBRARY {
+                if info.command.starts_with(&upper) {
+                    candidates.push(Pair {
+                        display: format!("{:<14} {}", info.command, info.description),
+                        replacement: info.command.to_string(),
+                    });
+                }
+            }
+            return Ok((pos - prefix.len(), candidates));
+        }
+        
         // 如果是第一个单词，补全命令
         if !input.contains(' ') {
             for cmd in &self.commands {
@@ -76,6 +154,21 @@ impl Completer for XToolsHelper {
             }
         }
         
+        // run 命令补全快捷命令名称
+        if let Some(prefix) = input.strip_prefix("run ") {
+            if !prefix.contains(' ') {
+                for preset in &self.config.lock().presets {
+                    if preset.name.starts_with(prefix) {
+                        candidates.push(Pair {
+                            display: preset.name.clone(),
+                            replacement: preset.name.clone(),
+                        });
+                    }
+                }
+                return Ok((pos - prefix.len(), candidates));
+            }
+        }
+        
         Ok((0, candidates))
     }
 }
@@ -84,7 +177,7 @@ impl Hinter for XToolsHelper {
     type Hint = String;
 
     fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
-        if line.is_empty() || pos < line.len() {
+        if line.is_empty() || pos < line.len() || self.at_mode.load(Ordering::SeqCst) {
             return None;
         }
         
@@ -111,30 +204,53 @@ pub fn run_interactive_repl() {
     print_banner();
     
     let manager = Arc::new(Mutex::new(SerialManager::new()));
+    let scheduler = Arc::new(Scheduler::new());
+    let slave = Mutex::new(None);
+    let config = Arc::new(Mutex::new(config::load_config().unwrap_or_default()));
+    if let Err(e) = manager.lock().set_triggers(&config.lock()) {
+        println!("\x1b[31m{}\x1b[0m", e);
+    }
+    manager
+        .lock()
+        .set_timestamp_mode(TimestampMode::parse(&config.lock().display.timestamp_mode).unwrap_or_default());
+    manager
+        .lock()
+        .set_rx_checksum(ChecksumAlgorithm::parse(&config.lock().serial.rx_checksum).unwrap_or_default());
+    manager
+        .lock()
+        .set_framing(Framing::parse(&config.lock().serial.framing).unwrap_or_default());
+    let hooks = Arc::new(ScriptHooks::new());
     let running = Arc::new(AtomicBool::new(true));
     let connected = Arc::new(AtomicBool::new(false));
     let in_terminal_mode = Arc::new(AtomicBool::new(false));  // 终端模式标志
+    let at_mode = Arc::new(AtomicBool::new(false));
+    let at_busy = Arc::new(AtomicBool::new(false)); // AT 命令执行中，由命令自己读取应答
     
     // 串口接收线程（仅在非终端模式时显示）
     let manager_rx = manager.clone();
     let running_rx = running.clone();
     let connected_rx = connected.clone();
     let in_terminal_rx = in_terminal_mode.clone();
+    let config_rx = config.clone();
+    let at_busy_rx = at_busy.clone();
     
     thread::spawn(move || {
         while running_rx.load(Ordering::SeqCst) {
             // 终端模式时不在这里处理数据
-            if connected_rx.load(Ordering::SeqCst) && !in_terminal_rx.load(Ordering::SeqCst) {
-                let mut mgr = manager_rx.lock().unwrap();
-                match mgr.read_available() {
-                    Ok(entries) => {
-                        for entry in entries {
-                            println!("\r\x1b[K[{}] RX: {}", entry.timestamp, entry.data.trim());
-                            // 不重新打印提示符，让 rustyline 处理
-                        }
+            if connected_rx.load(Ordering::SeqCst)
+                && !in_terminal_rx.load(Ordering::SeqCst)
+                && !at_busy_rx.load(Ordering::SeqCst)
+            {
+                let mut mgr = manager_rx.lock();
+                if let Ok(entries) = mgr.read_available() {
+                    for entry in entries {
+                        println!("\r\x1b[K{}", format_entry(&entry));
+                        // 不重新打印提示符，让 rustyline 处理
                     }
-                    Err(_) => {}
                 }
+                let hits = mgr.take_trigger_hits();
+                drop(mgr);
+                trigger::run_scripts(&hits, &manager_rx, &config_rx, &script_logger());
             }
             thread::sleep(Duration::from_millis(50));
         }
@@ -150,7 +266,7 @@ pub fn run_interactive_repl() {
     .expect("设置 Ctrl+C 处理失败");
     
     // 创建 rustyline 编辑器
-    let helper = XToolsHelper::new();
+    let helper = XToolsHelper::new(config.clone(), at_mode.clone());
     let mut rl = Editor::new().expect("无法创建编辑器");
     rl.set_helper(Some(helper));
     
@@ -168,7 +284,8 @@ pub fn run_interactive_repl() {
             break;
         }
         
-        let readline = rl.readline("xtools> ");
+        let in_at_mode = at_mode.load(Ordering::SeqCst);
+        let readline = rl.readline(if in_at_mode { "AT> " } else { "xtools> " });
         
         match readline {
             Ok(line) => {
@@ -181,7 +298,31 @@ pub fn run_interactive_repl() {
                 rl.add_history_entry(input)
                     .expect("添加历史失败");
                 
-                let result = handle_command(input, &manager, &connected, &in_terminal_mode);
+                // AT 模式下每行作为 AT 命令发送
+                if in_at_mode && matches!(input, "exit" | "quit") {
+                    at_mode.store(false, Ordering::SeqCst);
+                    continue;
+                }
+                let at_line;
+                let input = if in_at_mode {
+                    at_line = format!("at {}", input);
+                    at_line.as_str()
+                } else {
+                    input
+                };
+                
+                let session = ReplSession {
+                    manager: &manager,
+                    scheduler: &scheduler,
+                    slave: &slave,
+                    config: &config,
+                    hooks: &hooks,
+                    connected: &connected,
+                    in_terminal_mode: &in_terminal_mode,
+                    at_mode: &at_mode,
+                    at_busy: &at_busy,
+                };
+                let result = handle_command(input, &session);
                 
                 match result {
                     CommandResult::Exit => {
@@ -199,7 +340,7 @@ pub fn run_interactive_repl() {
                     }
                     CommandResult::EnterTerminal => {
                         // 连接成功，自动进入终端模式
-                        run_terminal_mode(&manager, &connected, &in_terminal_mode);
+                        run_terminal_mode(&manager, &config, &connected, &in_terminal_mode);
                     }
                 }
             }
@@ -207,6 +348,10 @@ pub fn run_interactive_repl() {
                 println!("^C");
                 continue;
             }
+            Err(ReadlineError::Eof) if at_mode.load(Ordering::SeqCst) => {
+                at_mode.store(false, Ordering::SeqCst);
+                continue;
+            }
             Err(ReadlineError::Eof) => {
                 println!("退出");
                 break;
@@ -232,12 +377,32 @@ enum CommandResult {
     EnterTerminal,  // 连接成功后进入终端模式
 }
 
-fn handle_command(
-    input: &str,
-    manager: &Arc<Mutex<SerialManager>>,
-    connected: &Arc<AtomicBool>,
-    in_terminal_mode: &Arc<AtomicBool>,
-) -> CommandResult {
+// REPL 命令共享的会话状态
+struct ReplSession<'a> {
+    manager: &'a Arc<Mutex<SerialManager>>,
+    scheduler: &'a Arc<Scheduler>,
+    slave: &'a Mutex<Option<ReplSlave>>,
+    config: &'a Arc<Mutex<AppConfig>>,
+    hooks: &'a Arc<ScriptHooks>,
+    connected: &'a Arc<AtomicBool>,
+    in_terminal_mode: &'a Arc<AtomicBool>,
+    at_mode: &'a Arc<AtomicBool>,
+    at_busy: &'a Arc<AtomicBool>,
+}
+
+fn handle_command(input: &str, session: &ReplSession) -> CommandResult {
+    let ReplSession {
+        manager,
+        scheduler,
+        slave,
+        config,
+        hooks,
+        connected,
+        in_terminal_mode,
+        at_mode,
+        at_busy,
+    } = *session;
+
     let parts: Vec<&str> = input.split_whitespace().collect();
     if parts.is_empty() {
         return CommandResult::Success(String::new());
@@ -261,6 +426,7 @@ fn handle_command(
         }
         
         "disconnect" | "disc" => {
+            scheduler.stop_all();
             cmd_disconnect(manager, connected)
         }
         
@@ -272,8 +438,106 @@ fn handle_command(
             cmd_send_hex(args, manager, connected)
         }
         
+        "checksum" => {
+            cmd_checksum(args, manager, config)
+        }
+        
+        "framing" => {
+            cmd_framing(args, manager, config)
+        }
+        
+        "timestamp" | "ts" => {
+            cmd_timestamp(args, manager, config)
+        }
+        
+        "repeat" => {
+            cmd_repeat(args, manager, scheduler, connected)
+        }
+        
+        "run" => {
+            cmd_run_preset(args, manager, config, connected)
+        }
+        
+        "preset" => {
+            cmd_preset(args, config)
+        }
+        
+        "script" => {
+            cmd_script(args, manager, connected)
+        }
+        
+        "rhai" => {
+            cmd_rhai(args, manager, config, hooks)
+        }
+        
+        "sx" | "sy" | "rx" | "ry" | "sz" | "rz" => {
+            cmd_transfer(&cmd, args, manager, connected)
+        }
+        
+        "sendfile" => {
+            cmd_send_file(args, manager, connected)
+        }
+        
+        "log" => {
+            cmd_log(args, manager, config)
+        }
+        
+        "replay" => {
+            cmd_replay(args, manager)
+        }
+        
+        "sim" => {
+            cmd_sim(args)
+        }
+        
+        "mb" => {
+            cmd_modbus(args, manager, config, connected)
+        }
+        
+        "mbs" => {
+            cmd_modbus_slave(args, slave)
+        }
+        
+        "decode" => {
+            cmd_decode(args, manager)
+        }
+        
+        "frame" => {
+            cmd_frame(args, manager, connected)
+        }
+        
+        "gps" => {
+            cmd_gps(args, manager, connected)
+        }
+        
+        "at" => {
+            // 保留命令中的空格，如 AT+CWJAP="my wifi","pass"
+            cmd_at(input[2..].trim(), manager, connected, at_mode, at_busy)
+        }
+        
+        "plot" => {
+            // 正则中可能有空格
+            cmd_plot(input[4..].trim(), manager, connected)
+        }
+        
+        "mbgw" => {
+            cmd_modbus_gateway(args, manager, config, connected)
+        }
+        
+        "history" => {
+            cmd_history(args, manager)
+        }
+        
+        "grep" => {
+            cmd_grep(args, manager)
+        }
+        
+        "trigger" => {
+            cmd_trigger(args, manager, config)
+        }
+        
         "terminal" | "term" => {
-            cmd_terminal(manager, connected, in_terminal_mode)
+            cmd_terminal(manager, config, connected, in_terminal_mode)
         }
         
         "config" | "cfg" => {
@@ -286,7 +550,11 @@ fn handle_command(
         }
         
         "status" | "st" => {
-            cmd_status(connected)
+            cmd_status(manager, connected)
+        }
+        
+        "stats" => {
+            cmd_stats(args, manager)
         }
         
         "exit" | "quit" | "q" => {
@@ -332,7 +600,7 @@ fn cmd_connect(
         115200
     };
     
-    let mut mgr = manager.lock().unwrap();
+    let mut mgr = manager.lock();
     match mgr.connect(port, baud, 8, 1, "none") {
         Ok(_) => {
             connected.store(true, Ordering::SeqCst);
@@ -347,7 +615,7 @@ fn cmd_disconnect(
     manager: &Arc<Mutex<SerialManager>>,
     connected: &Arc<AtomicBool>,
 ) -> CommandResult {
-    let mut mgr = manager.lock().unwrap();
+    let mut mgr = manager.lock();
     match mgr.disconnect() {
         Ok(_) => {
             connected.store(false, Ordering::SeqCst);
@@ -371,14 +639,11 @@ fn cmd_send(
     }
     
     let data = args.join(" ");
-    let mut mgr = manager.lock().unwrap();
+    let mut mgr = manager.lock();
     
+    // TX 记录由接收线程统一显示
     match mgr.send(&format!("{}\r\n", data), false) {
-        Ok(_) => {
-            let now = Local::now();
-            let timestamp = now.format("%H:%M:%S%.3f").to_string();
-            CommandResult::Success(format!("[{}] TX: {}", timestamp, data))
-        }
+        Ok(_) => CommandResult::Success(String::new()),
         Err(e) => CommandResult::Error(e),
     }
 }
@@ -397,165 +662,2363 @@ fn cmd_send_hex(
     }
     
     let data = args.join(" ");
-    let mut mgr = manager.lock().unwrap();
+    let mut mgr = manager.lock();
     
     match mgr.send(&data, true) {
-        Ok(_) => {
-            let now = Local::now();
-            let timestamp = now.format("%H:%M:%S%.3f").to_string();
-            CommandResult::Success(format!("[{}] TX HEX: {}", timestamp, data))
-        }
+        Ok(_) => CommandResult::Success(String::new()),
         Err(e) => CommandResult::Error(e),
     }
 }
 
-// 运行交互式终端模式
-fn run_terminal_mode(
+fn cmd_checksum(
+    args: &[&str],
     manager: &Arc<Mutex<SerialManager>>,
-    connected: &Arc<AtomicBool>,
-    in_terminal_mode: &Arc<AtomicBool>,
-) {
-    // 标记进入终端模式，暂停主 REPL 的接收线程
-    in_terminal_mode.store(true, Ordering::SeqCst);
-    
-    println!("\x1b[1;32m═══════════════════════════════════════════\x1b[0m");
-    println!("\x1b[1;32m   进入交互式终端模式\x1b[0m");
-    println!("\x1b[1;33m   重要: 按 Ctrl+] 退出到命令行模式\x1b[0m");
-    println!("\x1b[1;32m═══════════════════════════════════════════\x1b[0m\n");
-    
-    // 使用 crossterm 启用原始模式（跨平台）
-    if let Err(e) = enable_raw_mode() {
-        println!("\x1b[31m无法启用原始模式: {}\x1b[0m", e);
-        in_terminal_mode.store(false, Ordering::SeqCst);
-        return;
+    config: &Arc<Mutex<AppConfig>>,
+) -> CommandResult {
+    let mut mgr = manager.lock();
+
+    if args.is_empty() {
+        let names: Vec<&str> = ChecksumAlgorithm::ALL.iter().map(|a| a.name()).collect();
+        return CommandResult::Success(format!(
+            "接收校验: {}\n可选算法: {}",
+            mgr.rx_checksum().name(),
+            names.join(", ")
+        ));
     }
-    
-    let running = Arc::new(AtomicBool::new(true));
-    let running_rx = running.clone();
-    let manager_rx = manager.clone();
-    let connected_rx = connected.clone();
-    
-    // 接收线程 - 显示串口数据
-    let rx_handle = thread::spawn(move || {
-        while running_rx.load(Ordering::SeqCst) && connected_rx.load(Ordering::SeqCst) {
-            let mut mgr = manager_rx.lock().unwrap();
-            match mgr.read_available() {
-                Ok(entries) => {
-                    for entry in entries {
-                        // 直接输出数据，不添加时间戳
-                        print!("{}", entry.data);
-                        let _ = io::stdout().flush();
-                    }
-                }
-                Err(_) => {}
+
+    let algorithm = match ChecksumAlgorithm::parse(args[0]) {
+        Ok(algorithm) => algorithm,
+        Err(e) => return CommandResult::Error(e),
+    };
+    mgr.set_rx_checksum(algorithm);
+    let mut cfg = config.lock();
+    cfg.serial.rx_checksum = algorithm.name().to_string();
+    match config::save_config(&cfg) {
+        Ok(_) => CommandResult::Success(format!("✓ 接收校验已设置为 {}", algorithm.name())),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+fn cmd_framing(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+) -> CommandResult {
+    let mut mgr = manager.lock();
+
+    if args.is_empty() {
+        let names: Vec<&str> = Framing::ALL.iter().map(|f| f.name()).collect();
+        return CommandResult::Success(format!(
+            "分帧方式: {}\n可选: {}",
+            mgr.framing().name(),
+            names.join(", ")
+        ));
+    }
+
+    let framing = match Framing::parse(args[0]) {
+        Ok(framing) => framing,
+        Err(e) => return CommandResult::Error(e),
+    };
+    mgr.set_framing(framing);
+    let mut cfg = config.lock();
+    cfg.serial.framing = framing.name().to_string();
+    match config::save_config(&cfg) {
+        Ok(_) => CommandResult::Success(format!("✓ 分帧方式已设置为 {}", framing.name())),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+fn cmd_timestamp(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+) -> CommandResult {
+    let mut mgr = manager.lock();
+
+    if args.is_empty() {
+        let modes: Vec<String> = TimestampMode::ALL
+            .iter()
+            .map(|m| format!("{} ({})", m.name(), m.description()))
+            .collect();
+        return CommandResult::Success(format!(
+            "时间戳: {}\n可选: {}",
+            mgr.timestamp_mode().name(),
+            modes.join(", ")
+        ));
+    }
+
+    let mode = match TimestampMode::parse(args[0]) {
+        Ok(mode) => mode,
+        Err(e) => return CommandResult::Error(e),
+    };
+    mgr.set_timestamp_mode(mode);
+    let mut cfg = config.lock();
+    cfg.display.timestamp_mode = mode.name().to_string();
+    match config::save_config(&cfg) {
+        Ok(_) => CommandResult::Success(format!("✓ 时间戳已设置为 {} ({})", mode.name(), mode.description())),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+fn cmd_repeat(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    scheduler: &Arc<Scheduler>,
+    connected: &Arc<AtomicBool>,
+) -> CommandResult {
+    const USAGE: &str = "用法: repeat <间隔ms> [-n 次数] [-j 抖动ms] [text|hex] <数据>\n      repeat list\n      repeat stop <编号|all>";
+
+    match args.first() {
+        None => CommandResult::Error(USAGE.to_string()),
+        Some(&"list") => {
+            let jobs = scheduler.list();
+            if jobs.is_empty() {
+                return CommandResult::Success("没有定时发送任务".to_string());
             }
-            drop(mgr);
-            thread::sleep(Duration::from_millis(10));
+            let mut output = String::from("\n定时发送任务:\n");
+            for job in jobs {
+                let count = job.spec.count.map_or("∞".to_string(), |c| c.to_string());
+                let state = match (&job.last_error, job.running) {
+                    (Some(e), _) => format!("\x1b[31m失败: {}\x1b[0m", e),
+                    (None, true) => "\x1b[32m运行中\x1b[0m".to_string(),
+                    (None, false) => "已结束".to_string(),
+                };
+                output.push_str(&format!(
+                    "  [{}] 每 {}ms (±{}ms) {} {} - 已发送 {}/{} {}\n",
+                    job.id,
+                    job.spec.interval_ms,
+                    job.spec.jitter_ms,
+                    if job.spec.hex_mode { "HEX" } else { "TEXT" },
+                    job.spec.payload,
+                    job.sent,
+                    count,
+                    state
+                ));
+            }
+            CommandResult::Success(output)
         }
-    });
-    
-    // 主循环 - 读取键盘输入并发送 (使用 crossterm 跨平台)
-    loop {
-        if event::poll(Duration::from_millis(10)).unwrap_or(false) {
-            if let Ok(Event::Key(key_event)) = event::read() {
-                // 只处理按下事件，忽略释放和重复事件
-                if key_event.kind != KeyEventKind::Press {
-                    continue;
-                }
-                
-                // Ctrl+] 退出
-                if key_event.modifiers.contains(KeyModifiers::CONTROL) 
-                    && key_event.code == KeyCode::Char(']') 
-                {
-                    running.store(false, Ordering::SeqCst);
-                    break;
+        Some(&"stop") => match args.get(1) {
+            Some(&"all") | None => {
+                scheduler.stop_all();
+                CommandResult::Success("✓ 已停止所有定时发送任务".to_string())
+            }
+            Some(id) => match id.parse::<u32>() {
+                Ok(id) => match scheduler.stop(id) {
+                    Ok(_) => CommandResult::Success(format!("✓ 已停止定时任务 {}", id)),
+                    Err(e) => CommandResult::Error(e),
+                },
+                Err(_) => CommandResult::Error(format!("无效的任务编号: {}", id)),
+            },
+        },
+        Some(interval) => {
+            if !connected.load(Ordering::SeqCst) {
+                return CommandResult::Error("未连接到串口".to_string());
+            }
+
+            let interval_ms = match interval.parse::<u64>() {
+                Ok(ms) => ms,
+                Err(_) => return CommandResult::Error(USAGE.to_string()),
+            };
+
+            let mut count = None;
+            let mut jitter_ms = 0;
+            let mut rest = &args[1..];
+            while let [flag, value, tail @ ..] = rest {
+                match *flag {
+                    "-n" => match value.parse::<u64>() {
+                        Ok(n) => count = Some(n),
+                        Err(_) => return CommandResult::Error(format!("无效的次数: {}", value)),
+                    },
+                    "-j" => match value.parse::<u64>() {
+                        Ok(j) => jitter_ms = j,
+                        Err(_) => return CommandResult::Error(format!("无效的抖动: {}", value)),
+                    },
+                    _ => break,
                 }
-                
-                let data = match key_event.code {
-                    KeyCode::Enter => "\r".to_string(),
-                    KeyCode::Backspace => "\x7f".to_string(),
-                    KeyCode::Tab => "\t".to_string(),
-                    KeyCode::Esc => "\x1b".to_string(),
-                    KeyCode::Up => "\x1b[A".to_string(),
-                    KeyCode::Down => "\x1b[B".to_string(),
-                    KeyCode::Right => "\x1b[C".to_string(),
-                    KeyCode::Left => "\x1b[D".to_string(),
-                    KeyCode::Home => "\x1b[H".to_string(),
-                    KeyCode::End => "\x1b[F".to_string(),
-                    KeyCode::Delete => "\x1b[3~".to_string(),
-                    KeyCode::Char(c) => {
-                        if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                            // Ctrl+字母 转换为控制字符
-                            let ctrl_char = (c as u8 & 0x1f) as char;
-                            ctrl_char.to_string()
-                        } else {
-                            c.to_string()
-                        }
-                    }
-                    _ => continue,
-                };
-                
-                let mut mgr = manager.lock().unwrap();
-                let _ = mgr.send(&data, false);
+                rest = tail;
+            }
+
+            let hex_mode = rest.first() == Some(&"hex");
+            if matches!(rest.first(), Some(&"hex") | Some(&"text")) {
+                rest = &rest[1..];
+            }
+            if rest.is_empty() {
+                return CommandResult::Error(USAGE.to_string());
+            }
+
+            let payload = if hex_mode {
+                rest.join(" ")
+            } else {
+                format!("{}\r\n", rest.join(" "))
+            };
+            let spec = ScheduleSpec {
+                payload,
+                hex_mode,
+                interval_ms,
+                count,
+                jitter_ms,
+            };
+
+            match scheduler.start(manager.clone(), spec) {
+                Ok(id) => CommandResult::Success(format!("✓ 定时任务 {} 已启动 (repeat stop {} 停止)", id, id)),
+                Err(e) => CommandResult::Error(e),
             }
         }
     }
-    
-    let _ = disable_raw_mode();
-    let _ = rx_handle.join();
-    
-    // 退出终端模式
-    in_terminal_mode.store(false, Ordering::SeqCst);
-    
-    println!("\n\x1b[33m═══ 已退出终端模式 ═══\x1b[0m\n");
 }
 
-// 交互式终端模式命令
-fn cmd_terminal(
+fn cmd_run_preset(
+    args: &[&str],
     manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
     connected: &Arc<AtomicBool>,
-    in_terminal_mode: &Arc<AtomicBool>,
 ) -> CommandResult {
     if !connected.load(Ordering::SeqCst) {
-        return CommandResult::Error("未连接到串口，请先使用 connect 命令连接".to_string());
+        return CommandResult::Error("未连接到串口".to_string());
     }
-    
-    run_terminal_mode(manager, connected, in_terminal_mode);
-    CommandResult::Success(String::new())
-}
 
-fn cmd_config(args: &[&str]) -> CommandResult {
-    if args.is_empty() {
-        let output = "
-配置选项:
-  baud <速率>     - 设置波特率 (默认: 115200)
-  data <位数>     - 设置数据位 (5-8)
-  stop <位数>     - 设置停止位 (1-2)
-  parity <类型>   - 设置校验 (none/odd/even)
+    let name = match args.first() {
+        Some(name) => *name,
+        None => return CommandResult::Error("用法: run <快捷命令名称>".to_string()),
+    };
 
-示例: config baud 9600
-";
-        return CommandResult::Success(output.to_string());
+    let (bytes, hex) = match config.lock().find_preset(name) {
+        Some(preset) => (preset.to_bytes(), preset.hex_mode),
+        None => return CommandResult::Error(format!("快捷命令不存在: {}", name)),
+    };
+
+    match bytes.and_then(|b| manager.lock().send_payload(&b, hex)) {
+        Ok(_) => CommandResult::Success(String::new()),
+        Err(e) => CommandResult::Error(e),
     }
-    
-    // TODO: 实现配置功能
-    CommandResult::Success("配置已更新（功能待实现）".to_string())
 }
 
-fn cmd_status(connected: &Arc<AtomicBool>) -> CommandResult {
-    let status = if connected.load(Ordering::SeqCst) {
-        "\x1b[32m● 已连接\x1b[0m"
-    } else {
-        "\x1b[31m○ 未连接\x1b[0m"
-    };
-    CommandResult::Success(format!("状态: {}", status))
-}
+fn cmd_preset(args: &[&str], config: &Arc<Mutex<AppConfig>>) -> CommandResult {
+    const USAGE: &str = "用法: preset list [分组]\n      preset add <名称> [-g 分组] [text|hex] <数据>\n      preset del <名称>\n      preset import <文件>\n      preset export <文件> [分组]";
 
-fn print_banner() {
-    println!(r#"
-    ╔═══════════════════════════════════════════════════╗
-    ║                                                   ║
-    ║      ⚡ xTools CLI - 交互式串口终端 v0.1.0       ║
+    let mut cfg = config.lock();
+
+    match args {
+        [] | ["list"] | ["list", _] => {
+            let group = args.get(1).copied();
+            let presets: Vec<&CommandPreset> = cfg
+                .presets
+                .iter()
+                .filter(|p| group.is_none() || p.group.as_deref() == group)
+                .collect();
+            if presets.is_empty() {
+                return CommandResult::Success("没有快捷命令".to_string());
+            }
+            let mut output = String::from("\n快捷命令:\n");
+            for p in presets {
+                output.push_str(&format!(
+                    "  {:<16} [{}] {} {}{}\n",
+                    p.name,
+                    p.group.as_deref().unwrap_or("-"),
+                    if p.hex_mode { "HEX" } else { "TEXT" },
+                    p.payload,
+                    if p.newline.is_empty() || p.newline == "none" {
+                        String::new()
+                    } else {
+                        format!(" (+{})", p.newline)
+                    }
+                ));
+            }
+            CommandResult::Success(output)
+        }
+        ["add", name, rest @ ..] => {
+            let mut rest = rest;
+            let mut group = None;
+            if let ["-g", g, tail @ ..] = rest {
+                group = Some(g.to_string());
+                rest = tail;
+            }
+            let hex_mode = rest.first() == Some(&"hex");
+            if matches!(rest.first(), Some(&"hex") | Some(&"text")) {
+                rest = &rest[1..];
+            }
+            if rest.is_empty() {
+                return CommandResult::Error(USAGE.to_string());
+            }
+
+            let preset = CommandPreset {
+                name: name.to_string(),
+                payload: rest.join(" "),
+                hex_mode,
+                newline: if hex_mode { "none" } else { "crlf" }.to_string(),
+                group,
+            };
+            if let Err(e) = preset.to_bytes() {
+                return CommandResult::Error(e);
+            }
+
+            cfg.upsert_preset(preset);
+            match config::save_config(&cfg) {
+                Ok(_) => CommandResult::Success(format!("✓ 已保存快捷命令 {}", name)),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["del", name] => {
+            if !cfg.remove_preset(name) {
+                return CommandResult::Error(format!("快捷命令不存在: {}", name));
+            }
+            match config::save_config(&cfg) {
+                Ok(_) => CommandResult::Success(format!("✓ 已删除快捷命令 {}", name)),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["import", path] => {
+            let presets = match config::load_presets(path) {
+                Ok(p) => p,
+                Err(e) => return CommandResult::Error(e),
+            };
+            let count = presets.len();
+            for preset in presets {
+                cfg.upsert_preset(preset);
+            }
+            match config::save_config(&cfg) {
+                Ok(_) => CommandResult::Success(format!("✓ 已导入 {} 条快捷命令", count)),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["export", path] | ["export", path, _] => {
+            let group = args.get(2).copied();
+            let presets: Vec<CommandPreset> = cfg
+                .presets
+                .iter()
+                .filter(|p| group.is_none() || p.group.as_deref() == group)
+                .cloned()
+                .collect();
+            match config::save_presets(path, &presets) {
+                Ok(_) => CommandResult::Success(format!("✓ 已导出 {} 条快捷命令到 {}", presets.len(), path)),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        _ => CommandResult::Error(USAGE.to_string()),
+    }
+}
+
+fn cmd_script(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    connected: &Arc<AtomicBool>,
+) -> CommandResult {
+    let path = match args {
+        ["run", path] => *path,
+        _ => return CommandResult::Error("用法: script run <脚本文件>".to_string()),
+    };
+
+    if !connected.load(Ordering::SeqCst) {
+        return CommandResult::Error("未连接到串口".to_string());
+    }
+
+    let script = match Script::load(path) {
+        Ok(s) => s,
+        Err(e) => return CommandResult::Error(e),
+    };
+
+    println!("运行脚本 {} (按 Esc 停止)", path);
+    let manager = manager.clone();
+    let result = run_cancellable(move |cancel| {
+        let outcome = script::run_script(&script, &manager, cancel, print_step_report);
+        match outcome.status {
+            StepStatus::Passed => Ok(outcome),
+            _ => Err(outcome.message),
+        }
+    });
+
+    match result {
+        Ok(outcome) => CommandResult::Success(format!(
+            "\x1b[32m✓ {} (共 {} 步)\x1b[0m",
+            outcome.message, outcome.steps
+        )),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+fn cmd_rhai(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+    hooks: &Arc<ScriptHooks>,
+) -> CommandResult {
+    const USAGE: &str = "用法: rhai run <文件>\n      rhai hook add <名称> <文件>\n      rhai hook del <名称>\n      rhai hook list";
+
+    let log: LogFn = Arc::new(|msg: &str| println!("\r\x1b[K\x1b[36m[脚本] {}\x1b[0m", msg));
+
+    match args {
+        ["run", path] => {
+            println!("运行脚本 {} (按 Esc 停止)", path);
+            let path = path.to_string();
+            let manager = manager.clone();
+            let config = config.clone();
+            let result = run_cancellable(move |cancel| {
+                scripting::run_file(&path, manager, config, log, cancel.clone())
+            });
+            match result {
+                Ok(_) => CommandResult::Success("✓ 脚本执行完成".to_string()),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["hook", "add", name, path] => {
+            match hooks.register(name, path, manager.clone(), config.clone(), log) {
+                Ok(_) => CommandResult::Success(format!("✓ 已注册接收钩子 {}", name)),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["hook", "del", name] => {
+            if hooks.unregister(name) {
+                CommandResult::Success(format!("✓ 已移除接收钩子 {}", name))
+            } else {
+                CommandResult::Error(format!("接收钩子不存在: {}", name))
+            }
+        }
+        ["hook", "list"] | ["hook"] => {
+            let list = hooks.list();
+            if list.is_empty() {
+                return CommandResult::Success("没有接收钩子".to_string());
+            }
+            let mut output = String::from("\n接收钩子:\n");
+            for hook in list {
+                output.push_str(&format!("  {:<12} {} (调用 {} 次)", hook.name, hook.path, hook.calls));
+                if let Some(e) = hook.last_error {
+                    output.push_str(&format!(" \x1b[31m{}\x1b[0m", e));
+                }
+                output.push('\n');
+            }
+            CommandResult::Success(output)
+        }
+        _ => CommandResult::Error(USAGE.to_string()),
+    }
+}
+
+fn cmd_transfer(
+    cmd: &str,
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    connected: &Arc<AtomicBool>,
+) -> CommandResult {
+    if !connected.load(Ordering::SeqCst) {
+        return CommandResult::Error("未连接到串口".to_string());
+    }
+
+    let flag = |f: &str| args.contains(&f);
+    let files: Vec<String> = args
+        .iter()
+        .filter(|a| !a.starts_with('-'))
+        .map(|a| a.to_string())
+        .collect();
+
+    let request = match (cmd, files.as_slice()) {
+        ("sx", [path]) => TransferRequest::XmodemSend {
+            path: path.clone(),
+            one_k: flag("-k"),
+        },
+        ("rx", [path]) => TransferRequest::XmodemReceive {
+            path: path.clone(),
+            crc: !flag("-c"),
+        },
+        ("sy", paths) if !paths.is_empty() => TransferRequest::YmodemSend {
+            paths: paths.to_vec(),
+        },
+        ("ry", []) => TransferRequest::YmodemReceive { dir: ".".to_string() },
+        ("ry", [dir]) => TransferRequest::YmodemReceive { dir: dir.clone() },
+        ("sz", paths) if !paths.is_empty() => TransferRequest::ZmodemSend {
+            paths: paths.to_vec(),
+        },
+        ("rz", []) => TransferRequest::ZmodemReceive { dir: ".".to_string() },
+        ("rz", [dir]) => TransferRequest::ZmodemReceive { dir: dir.clone() },
+        _ => {
+            return CommandResult::Error(
+                "用法: sx [-k] <文件> | rx [-c] <文件> | sy <文件...> | ry [目录] | sz <文件...> | rz [目录]"
+                    .to_string(),
+            )
+        }
+    };
+
+    println!("等待对方开始传输... (按 Esc 取消)");
+    let manager = manager.clone();
+    let result = run_cancellable(move |cancel| {
+        transfer::run_transfer(&request, &manager, cancel, &mut print_transfer_progress)
+    });
+    println!();
+
+    match result {
+        Ok(_) => CommandResult::Success("✓ 传输完成".to_string()),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+const LOG_USAGE: &str =
+    "用法: log start [-j] [-d 目录] [-f 文件名模板] [-s 轮转KB] [-r 轮转分钟] [-z] [-x] | log stop | log status";
+
+fn cmd_log(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+) -> CommandResult {
+    match args.first().copied() {
+        Some("start") => {
+            let options = match parse_log_args(&args[1..], config.lock().logging.clone()) {
+                Ok(o) => o,
+                Err(e) => return CommandResult::Error(e),
+            };
+            match manager.lock().start_logging(&options) {
+                Ok(path) => CommandResult::Success(format!("✓ 开始记录日志: {}", path)),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        Some("stop") => match manager.lock().stop_logging() {
+            Some(Ok(status)) => CommandResult::Success(format!("✓ 日志已保存: {}", status.path)),
+            Some(Err(e)) => CommandResult::Error(e),
+            None => CommandResult::Error("未在记录日志".to_string()),
+        },
+        Some("status") | None => match manager.lock().logging_status() {
+            Some(status) => {
+                let mut output = format!(
+                    "正在记录: {} ({} 字节, 共 {} 个文件)",
+                    status.path, status.bytes, status.files
+                );
+                if let Some(e) = status.last_error {
+                    output.push_str(&format!("\n\x1b[31m最近错误: {}\x1b[0m", e));
+                }
+                CommandResult::Success(output)
+            }
+            None => CommandResult::Success("未在记录日志".to_string()),
+        },
+        _ => CommandResult::Error(LOG_USAGE.to_string()),
+    }
+}
+
+// 解析 log start 参数，覆盖配置中的日志设置
+fn parse_log_args(args: &[&str], mut options: LogConfig) -> Result<LogConfig, String> {
+    let mut iter = args.iter();
+    while let Some(&arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| format!("{} 需要参数", name))
+        };
+        let number = |v: String| v.parse::<u64>().map_err(|_| format!("无效的数值: {}", v));
+
+        match arg {
+            "-j" => options.format = "jsonl".to_string(),
+            "-d" => options.dir = value("-d")?,
+            "-f" => options.filename = value("-f")?,
+            "-s" => options.max_size_kb = number(value("-s")?)?,
+            "-r" => options.rotate_minutes = number(value("-r")?)?,
+            "-z" => options.gzip = true,
+            "-x" => options.include_hex = true,
+            _ => return Err(LOG_USAGE.to_string()),
+        }
+    }
+    Ok(options)
+}
+
+const HISTORY_USAGE: &str =
+    "用法: history [条数] | history -s <序号> [条数] | history -t <起始 HH:MM:SS> [结束 HH:MM:SS] | history clear";
+
+fn cmd_history(args: &[&str], manager: &Arc<Mutex<SerialManager>>) -> CommandResult {
+    let mut mgr = manager.lock();
+    let count = |s: Option<&&str>| s.map_or(Ok(20), |s| s.parse::<usize>());
+
+    let page = match args {
+        ["clear"] => {
+            mgr.history_mut().clear();
+            return CommandResult::Success("✓ 历史已清空".to_string());
+        }
+        ["-s", seq, rest @ ..] if rest.len() <= 1 => match (seq.parse::<u64>(), count(rest.first())) {
+            (Ok(seq), Ok(n)) => mgr.history().page_by_seq(Some(seq), n),
+            _ => return CommandResult::Error(HISTORY_USAGE.to_string()),
+        },
+        ["-t", start, rest @ ..] if rest.len() <= 1 => {
+            let end = rest.first().copied().unwrap_or("23:59:59.999");
+            match (today_ms(start), today_ms(end)) {
+                (Some(start), Some(end)) => mgr.history().page_by_time(start, end, usize::MAX),
+                _ => return CommandResult::Error("时间格式应为 HH:MM:SS".to_string()),
+            }
+        }
+        [] | [_] => match count(args.first()) {
+            Ok(n) => mgr.history().page_by_seq(None, n),
+            Err(_) => return CommandResult::Error(HISTORY_USAGE.to_string()),
+        },
+        _ => return CommandResult::Error(HISTORY_USAGE.to_string()),
+    };
+
+    if page.entries.is_empty() {
+        return CommandResult::Success("没有记录".to_string());
+    }
+    for e in &page.entries {
+        println!("\x1b[90m#{:<6}\x1b[0m {}", e.seq, format_entry(&e.entry));
+    }
+    CommandResult::Success(format!(
+        "共 {} 条 (缓冲中序号 {}..{})",
+        page.entries.len(),
+        page.oldest_seq,
+        page.next_seq
+    ))
+}
+
+// 今天某个时刻的 Unix 毫秒时间戳
+fn today_ms(time: &str) -> Option<i64> {
+    let time = chrono::NaiveTime::parse_from_str(time, "%H:%M:%S%.f").ok()?;
+    chrono::Local::now()
+        .date_naive()
+        .and_time(time)
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|t| t.timestamp_millis())
+}
+
+const GREP_USAGE: &str = "用法: grep [-x] [-d rx|tx] [-n 最多条数] <正则 | 十六进制 (-x，?? 为任意字节)>";
+
+fn cmd_grep(args: &[&str], manager: &Arc<Mutex<SerialManager>>) -> CommandResult {
+    let mut hex = false;
+    let mut direction = None;
+    let mut limit = 100;
+    let mut rest = args;
+
+    loop {
+        match rest {
+            ["-x", tail @ ..] => {
+                hex = true;
+                rest = tail;
+            }
+            ["-d", dir, tail @ ..] => {
+                direction = Some(dir.to_lowercase());
+                rest = tail;
+            }
+            ["-n", n, tail @ ..] => {
+                limit = match n.parse() {
+                    Ok(n) => n,
+                    Err(_) => return CommandResult::Error(GREP_USAGE.to_string()),
+                };
+                rest = tail;
+            }
+            _ => break,
+        }
+    }
+    if rest.is_empty() {
+        return CommandResult::Error(GREP_USAGE.to_string());
+    }
+
+    let regex = match history::compile_pattern(&rest.join(" "), hex) {
+        Ok(r) => r,
+        Err(e) => return CommandResult::Error(e),
+    };
+
+    let mgr = manager.lock();
+    let history = mgr.history();
+    let matches = history.search(&regex, direction.as_deref(), limit);
+    for m in &matches {
+        let raw = match history.get(m.seq) {
+            Some(e) => e.raw(),
+            None => continue,
+        };
+        let (before, hit, after) = (&raw[..m.start], &raw[m.start..m.end], &raw[m.end..]);
+        let shown = if hex {
+            format!(
+                "{} \x1b[1;31m{}\x1b[0m {}",
+                serial::bytes_to_hex_string(before),
+                serial::bytes_to_hex_string(hit),
+                serial::bytes_to_hex_string(after)
+            )
+        } else {
+            format!(
+                "{}\x1b[1;31m{}\x1b[0m{}",
+                String::from_utf8_lossy(before).escape_debug(),
+                String::from_utf8_lossy(hit).escape_debug(),
+                String::from_utf8_lossy(after).escape_debug()
+            )
+        };
+        println!(
+            "\x1b[90m#{:<6}\x1b[0m [{}] {} @{}..{}: {}",
+            m.seq,
+            m.timestamp,
+            m.direction.to_uppercase(),
+            m.start,
+            m.end,
+            shown.trim()
+        );
+    }
+
+    CommandResult::Success(format!("{} 处匹配", matches.len()))
+}
+
+const TRIGGER_USAGE: &str = "用法: trigger list
+      trigger add <名称> [-x] <模式> <动作> [参数]
+        动作: reply <文本> | replyhex <HEX> | mark <文本> | color <颜色>
+              logstart | logstop | dtr [毫秒] | script <文件>
+      trigger del <名称>
+      trigger on|off <名称>";
+
+fn cmd_trigger(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+) -> CommandResult {
+    let mut cfg = config.lock();
+    let mut updated = cfg.clone();
+
+    let message = match args {
+        [] | ["list"] => {
+            if cfg.triggers.is_empty() {
+                return CommandResult::Success("没有触发规则".to_string());
+            }
+            let mut output = String::from("\n触发规则:\n");
+            for rule in &cfg.triggers {
+                let actions: Vec<String> = rule.actions.iter().map(describe_action).collect();
+                output.push_str(&format!(
+                    "  {:<12} {} {}{} → {}\n",
+                    rule.name,
+                    if rule.enabled { "✓" } else { "✗" },
+                    if rule.hex { "HEX " } else { "" },
+                    rule.pattern,
+                    actions.join(", ")
+                ));
+            }
+            return CommandResult::Success(output);
+        }
+        ["add", name, rest @ ..] => {
+            let (hex, rest) = match rest {
+                ["-x", tail @ ..] => (true, tail),
+                _ => (false, rest),
+            };
+            let (pattern, action) = match rest {
+                [pattern, action @ ..] if !action.is_empty() => (pattern, action),
+                _ => return CommandResult::Error(TRIGGER_USAGE.to_string()),
+            };
+            let action = match parse_trigger_action(action) {
+                Some(a) => a,
+                None => return CommandResult::Error(TRIGGER_USAGE.to_string()),
+            };
+            updated.upsert_trigger(TriggerRule {
+                name: name.to_string(),
+                enabled: true,
+                pattern: pattern.to_string(),
+                hex,
+                actions: vec![action],
+            });
+            format!("✓ 已保存触发规则 {}", name)
+        }
+        ["del", name] => {
+            if !updated.remove_trigger(name) {
+                return CommandResult::Error(format!("触发规则不存在: {}", name));
+            }
+            format!("✓ 已删除触发规则 {}", name)
+        }
+        [switch @ ("on" | "off"), name] => {
+            match updated.triggers.iter_mut().find(|r| r.name == *name) {
+                Some(rule) => rule.enabled = *switch == "on",
+                None => return CommandResult::Error(format!("触发规则不存在: {}", name)),
+            }
+            format!("✓ 触发规则 {} 已{}", name, if *switch == "on" { "启用" } else { "停用" })
+        }
+        _ => return CommandResult::Error(TRIGGER_USAGE.to_string()),
+    };
+
+    // 先确认规则可用再保存
+    if let Err(e) = manager.lock().set_triggers(&updated) {
+        return CommandResult::Error(e);
+    }
+    *cfg = updated;
+    match config::save_config(&cfg) {
+        Ok(_) => CommandResult::Success(message),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+fn parse_trigger_action(args: &[&str]) -> Option<TriggerAction> {
+    let text = || args[1..].join(" ");
+    let action = match args {
+        ["reply", _, ..] => TriggerAction::Reply { data: text(), hex: false, newline: "crlf".to_string() },
+        ["replyhex", _, ..] => TriggerAction::Reply { data: text(), hex: true, newline: String::new() },
+        ["mark", _, ..] => TriggerAction::Marker { text: text() },
+        ["color", color] => TriggerAction::Highlight { color: color.to_string() },
+        ["logstart"] => TriggerAction::StartLog,
+        ["logstop"] => TriggerAction::StopLog,
+        ["dtr"] => TriggerAction::PulseDtr { level: false, ms: 100 },
+        ["dtr", ms] => TriggerAction::PulseDtr { level: false, ms: ms.parse().ok()? },
+        ["script", path] => TriggerAction::RunScript { path: path.to_string() },
+        _ => return None,
+    };
+    Some(action)
+}
+
+fn describe_action(action: &TriggerAction) -> String {
+    match action {
+        TriggerAction::Reply { data, hex: true, .. } => format!("回复 HEX {}", data),
+        TriggerAction::Reply { data, .. } => format!("回复 {}", data),
+        TriggerAction::Marker { text } => format!("标记 {}", text),
+        TriggerAction::Highlight { color } => format!("高亮 {}", color),
+        TriggerAction::StartLog => "开始记录".to_string(),
+        TriggerAction::StopLog => "停止记录".to_string(),
+        TriggerAction::PulseDtr { level, ms } => format!("DTR={} {}ms", u8::from(*level), ms),
+        TriggerAction::RunScript { path } => format!("脚本 {}", path),
+    }
+}
+
+const REPLAY_USAGE: &str =
+    "用法: replay <文件> [-s 倍速] [-l] [-d rx|tx] [--pty | -p 串口 [-b 波特率]]";
+
+fn cmd_replay(args: &[&str], manager: &Arc<Mutex<SerialManager>>) -> CommandResult {
+    let mut options = ReplayOptions::default();
+    let mut path = None;
+    let mut port = None;
+    let mut baud = 115200;
+    let mut pty = false;
+
+    let mut iter = args.iter();
+    while let Some(&arg) = iter.next() {
+        let value = match arg {
+            "-s" | "-d" | "-p" | "-b" => match iter.next() {
+                Some(v) => *v,
+                None => return CommandResult::Error(REPLAY_USAGE.to_string()),
+            },
+            _ => "",
+        };
+
+        match arg {
+            "-s" => match value.parse::<f64>() {
+                Ok(speed) if speed > 0.0 => options.speed = speed,
+                _ => return CommandResult::Error(format!("无效的倍速: {}", value)),
+            },
+            "-d" => options.direction = value.to_lowercase(),
+            "-p" => port = Some(value.to_string()),
+            "-b" => match value.parse() {
+                Ok(b) => baud = b,
+                Err(_) => return CommandResult::Error(format!("无效的波特率: {}", value)),
+            },
+            "-l" => options.looping = true,
+            "--pty" => pty = true,
+            _ if arg.starts_with('-') || path.is_some() => {
+                return CommandResult::Error(REPLAY_USAGE.to_string())
+            }
+            _ => path = Some(arg.to_string()),
+        }
+    }
+
+    let path = match path {
+        Some(p) => p,
+        None => return CommandResult::Error(REPLAY_USAGE.to_string()),
+    };
+    let target = match (pty, port) {
+        (true, _) => ReplayTarget::Pty,
+        (false, Some(name)) => ReplayTarget::Port { name, baud_rate: baud },
+        (false, None) => ReplayTarget::Session,
+    };
+
+    let frames = match replay::load_frames(&path, &options.direction) {
+        Ok(f) => f,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let mut sink = match ReplaySink::open(&target, manager) {
+        Ok(s) => s,
+        Err(e) => return CommandResult::Error(e),
+    };
+
+    println!(
+        "回放 {} 段数据到 {} ({}x{}，按 Esc 停止)",
+        frames.len(),
+        sink.name(),
+        options.speed,
+        if options.looping { "，循环" } else { "" }
+    );
+    let result = run_cancellable(move |cancel| {
+        replay::run_replay(&frames, &mut sink, &options, cancel, |p| {
+            print!("\r\x1b[K回放 {}/{} 段, {} 字节, 循环 {} 次", p.frame, p.frames, p.bytes, p.loops);
+            let _ = io::stdout().flush();
+        })
+    });
+    println!();
+
+    match result {
+        Ok(_) => CommandResult::Success("✓ 回放结束".to_string()),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+const MODBUS_USAGE: &str = "用法: mb read <从站> <c|d|h|i> <地址> [数量] [-f u16|i16|u32|i32|f32] [-o abcd|cdab|badc|dcba]
+      mb write <从站> <c|h> <地址> <值...> [-m]
+      mb mode [rtu|ascii] | mb timeout <ms> | mb retries <次数>";
+
+fn cmd_modbus(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+    connected: &Arc<AtomicBool>,
+) -> CommandResult {
+    // 设置项保存在配置中
+    let setting = match args {
+        [] | ["mode"] => {
+            let cfg = config.lock();
+            return CommandResult::Success(format!(
+                "模式 {:?}，超时 {}ms，重试 {} 次",
+                cfg.modbus.mode, cfg.modbus.timeout_ms, cfg.modbus.retries
+            ));
+        }
+        ["mode", mode] => ModbusMode::parse(mode).map(|m| config.lock().modbus.mode = m),
+        ["timeout", ms] => ms
+            .parse()
+            .map(|ms| config.lock().modbus.timeout_ms = ms)
+            .map_err(|_| format!("无效的超时: {}", ms)),
+        ["retries", n] => n
+            .parse()
+            .map(|n| config.lock().modbus.retries = n)
+            .map_err(|_| format!("无效的重试次数: {}", n)),
+        _ => Err(String::new()),
+    };
+    match setting {
+        Ok(_) => {
+            return match config::save_config(&config.lock()) {
+                Ok(_) => CommandResult::Success("✓ Modbus 设置已保存".to_string()),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        Err(e) if !e.is_empty() => return CommandResult::Error(e),
+        Err(_) => {}
+    }
+
+    if !connected.load(Ordering::SeqCst) {
+        return CommandResult::Error("未连接到串口".to_string());
+    }
+
+    // 分出选项
+    let mut format = ValueFormat::U16;
+    let mut order = WordOrder::Abcd;
+    let mut multiple = false;
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(&arg) = iter.next() {
+        let parsed = match arg {
+            "-f" => match iter.next() {
+                Some(v) => ValueFormat::parse(v).map(|f| format = f),
+                None => Err(MODBUS_USAGE.to_string()),
+            },
+            "-o" => match iter.next() {
+                Some(v) => WordOrder::parse(v).map(|o| order = o),
+                None => Err(MODBUS_USAGE.to_string()),
+            },
+            "-m" => {
+                multiple = true;
+                Ok(())
+            }
+            _ => {
+                positional.push(arg);
+                Ok(())
+            }
+        };
+        if let Err(e) = parsed {
+            return CommandResult::Error(e);
+        }
+    }
+
+    let request = match parse_modbus_request(&positional, multiple) {
+        Ok(r) => r,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let unit = match positional.get(1).map(|u| parse_number(u)) {
+        Some(Some(u)) if u <= 247 => u as u8,
+        _ => return CommandResult::Error("从站地址必须在 0..=247 之间".to_string()),
+    };
+
+    let start = match &request {
+        ModbusRequest::ReadCoils { address, .. }
+        | ModbusRequest::ReadDiscreteInputs { address, .. }
+        | ModbusRequest::ReadHoldingRegisters { address, .. }
+        | ModbusRequest::ReadInputRegisters { address, .. } => *address as usize,
+        _ => 0,
+    };
+
+    // 重试期间可按 Esc 或 Ctrl+C 放弃
+    let options = config.lock().modbus.clone();
+    let manager = manager.clone();
+    let response = match run_cancellable(move |cancel| {
+        let mut link = SessionLink::new(&manager, cancel);
+        modbus::transact(&mut link, unit, &request, &options)
+    }) {
+        Ok(r) => r,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let output = match response {
+        ModbusResponse::Bits { values } => {
+            let mut output = String::new();
+            for (row, chunk) in values.chunks(8).enumerate() {
+                let bits: Vec<&str> = chunk.iter().map(|&b| if b { "1" } else { "0" }).collect();
+                output.push_str(&format!("  [{:>5}] {}\n", start + row * 8, bits.join(" ")));
+            }
+            output
+        }
+        ModbusResponse::Registers { values } => {
+            let width = format.width();
+            let decoded = modbus::decode_registers(&values, format, order);
+            let mut output = String::new();
+            for (i, value) in decoded.iter().enumerate() {
+                let raw: Vec<String> = values[i * width..(i + 1) * width]
+                    .iter()
+                    .map(|r| format!("0x{:04X}", r))
+                    .collect();
+                output.push_str(&format!("  [{:>5}] {:<14} {}\n", start + i * width, raw.join(" "), value));
+            }
+            output
+        }
+        ModbusResponse::Written { .. } if unit == 0 => "✓ 已广播".to_string(),
+        ModbusResponse::Written { address, value } => format!("✓ 写入成功 (地址 {}，{})", address, value),
+    };
+    CommandResult::Success(output.trim_end().to_string())
+}
+
+// 十进制或 0x 开头的十六进制
+fn parse_number(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_modbus_request(args: &[&str], multiple: bool) -> Result<ModbusRequest, String> {
+    let u16_arg = |s: &str| {
+        parse_number(s)
+            .filter(|&n| n <= u16::MAX as u32)
+            .map(|n| n as u16)
+            .ok_or_else(|| format!("无效的数值: {}", s))
+    };
+
+    match args {
+        ["read", _, table, address, rest @ ..] if rest.len() <= 1 => {
+            let address = u16_arg(address)?;
+            let count = rest.first().map(|c| u16_arg(c)).transpose()?.unwrap_or(1);
+            match *table {
+                "c" => Ok(ModbusRequest::ReadCoils { address, count }),
+                "d" => Ok(ModbusRequest::ReadDiscreteInputs { address, count }),
+                "h" => Ok(ModbusRequest::ReadHoldingRegisters { address, count }),
+                "i" => Ok(ModbusRequest::ReadInputRegisters { address, count }),
+                _ => Err("读取类型: c 线圈, d 离散输入, h 保持寄存器, i 输入寄存器".to_string()),
+            }
+        }
+        ["write", _, table, address, values @ ..] if !values.is_empty() => {
+            let address = u16_arg(address)?;
+            let single = values.len() == 1 && !multiple;
+            match *table {
+                "c" => {
+                    let bits = values
+                        .iter()
+                        .map(|v| match *v {
+                            "1" | "on" => Ok(true),
+                            "0" | "off" => Ok(false),
+                            _ => Err(format!("线圈值应为 0/1: {}", v)),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if single {
+                        Ok(ModbusRequest::WriteSingleCoil { address, value: bits[0] })
+                    } else {
+                        Ok(ModbusRequest::WriteMultipleCoils { address, values: bits })
+                    }
+                }
+                "h" => {
+                    // 负数按 i16 补码写入
+                    let regs = values
+                        .iter()
+                        .map(|v| match v.parse::<i16>() {
+                            Ok(n) if n < 0 => Ok(n as u16),
+                            _ => u16_arg(v),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if single {
+                        Ok(ModbusRequest::WriteSingleRegister { address, value: regs[0] })
+                    } else {
+                        Ok(ModbusRequest::WriteMultipleRegisters { address, values: regs })
+                    }
+                }
+                _ => Err("写入类型: c 线圈, h 保持寄存器".to_string()),
+            }
+        }
+        _ => Err(MODBUS_USAGE.to_string()),
+    }
+}
+
+fn cmd_decode(args: &[&str], manager: &Arc<Mutex<SerialManager>>) -> CommandResult {
+    match args {
+        [] => match manager.lock().decoder_name() {
+            Some(name) => CommandResult::Success(format!("当前解码器: {}", name)),
+            None => CommandResult::Success("未启用解码 (decode list 查看可用解码器)".to_string()),
+        },
+        ["list"] => {
+            let lines: Vec<String> = decoder::available()
+                .iter()
+                .map(|d| format!("  {:<14} {}", d.name, d.description))
+                .collect();
+            CommandResult::Success(format!("可用解码器:\n{}", lines.join("\n")))
+        }
+        ["off"] => {
+            manager.lock().set_decoder(None);
+            CommandResult::Success("✓ 已关闭解码".to_string())
+        }
+        [spec] => match decoder::create(spec) {
+            Ok(d) => {
+                let name = d.name();
+                manager.lock().set_decoder(Some(d));
+                CommandResult::Success(format!("✓ 收发记录将按 {} 解码", name))
+            }
+            Err(e) => CommandResult::Error(e),
+        },
+        _ => CommandResult::Error("用法: decode [名称[:参数] | list | off]".to_string()),
+    }
+}
+
+const FRAME_USAGE: &str = "用法: frame list | frame build <模板> [字段=值...] | frame send <模板> [字段=值...] | frame decode <十六进制>";
+
+fn cmd_frame(args: &[&str], manager: &Arc<Mutex<SerialManager>>, connected: &Arc<AtomicBool>) -> CommandResult {
+    match args {
+        ["list"] => {
+            let templates = match frames::load_templates() {
+                Ok(t) => t,
+                Err(e) => return CommandResult::Error(e),
+            };
+            if templates.is_empty() {
+                return CommandResult::Success(format!(
+                    "没有帧模板，请在 {} 中定义",
+                    frames::templates_path().display()
+                ));
+            }
+            let mut output = String::from("帧模板:");
+            for t in &templates {
+                let fields: Vec<&str> = t.fields.iter().map(|f| f.name.as_str()).collect();
+                output.push_str(&format!("\n  {:<12} [{}] {}", t.name, fields.join(" "), t.description));
+            }
+            CommandResult::Success(output)
+        }
+        ["build" | "send", name, assignments @ ..] => {
+            let mut values = HashMap::new();
+            for assignment in assignments {
+                match assignment.split_once('=') {
+                    Some((field, value)) => {
+                        values.insert(field.to_string(), value.to_string());
+                    }
+                    None => return CommandResult::Error(FRAME_USAGE.to_string()),
+                }
+            }
+            let frame = match frames::find_template(name).and_then(|t| t.build(&values)) {
+                Ok(f) => f,
+                Err(e) => return CommandResult::Error(e),
+            };
+
+            if args[0] == "build" {
+                return CommandResult::Success(serial::bytes_to_hex_string(&frame));
+            }
+            if !connected.load(Ordering::SeqCst) {
+                return CommandResult::Error("未连接到串口".to_string());
+            }
+            match manager.lock().send_bytes(&frame) {
+                Ok(_) => CommandResult::Success(format!("✓ 已发送: {}", serial::bytes_to_hex_string(&frame))),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["decode", hex @ ..] if !hex.is_empty() => {
+            let data = match serial::parse_hex_string(&hex.join("")) {
+                Ok(d) => d,
+                Err(e) => return CommandResult::Error(e),
+            };
+            let decoder = match decoder::create("template") {
+                Ok(d) => d,
+                Err(e) => return CommandResult::Error(e),
+            };
+            match decoder.decode(&data, decoder::Direction::Rx) {
+                Some(fields) => {
+                    let mut output = String::from("解码结果:");
+                    format_fields(&fields, 0, &mut output);
+                    CommandResult::Success(output.replace("\r\n", "\n"))
+                }
+                None => CommandResult::Error("没有匹配的帧模板".to_string()),
+            }
+        }
+        _ => CommandResult::Error(FRAME_USAGE.to_string()),
+    }
+}
+
+const GPS_USAGE: &str = "用法: gps | gps sats | gps watch | gps reset | gps send <命令> | gps ubx <类> <ID> [负载十六进制]";
+
+fn cmd_gps(args: &[&str], manager: &Arc<Mutex<SerialManager>>, connected: &Arc<AtomicBool>) -> CommandResult {
+    match args {
+        [] => CommandResult::Success(format_gps_fix(manager.lock().gps_fix())),
+        ["sats"] => {
+            let mgr = manager.lock();
+            let fix = mgr.gps_fix();
+            if fix.satellites.is_empty() {
+                return CommandResult::Success("没有收到 GSV 语句".to_string());
+            }
+            let mut output = format!("可见卫星 {} 颗:\n  系统 PRN  仰角 方位 信噪比", fix.satellites.len());
+            for sat in &fix.satellites {
+                let used = fix.used_prns.contains(&sat.prn);
+                output.push_str(&format!(
+                    "\n  {:<4} {:>3} {:>5} {:>4} {:>6}{}",
+                    sat.system,
+                    sat.prn,
+                    sat.elevation.map(|e| e.to_string()).unwrap_or_else(|| "-".to_string()),
+                    sat.azimuth.map(|a| a.to_string()).unwrap_or_else(|| "-".to_string()),
+                    sat.snr.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string()),
+                    if used { " *" } else { "" }
+                ));
+            }
+            CommandResult::Success(output)
+        }
+        ["watch"] => {
+            if !connected.load(Ordering::SeqCst) {
+                return CommandResult::Error("未连接到串口".to_string());
+            }
+            println!("实时定位 (按 Esc 停止)");
+            let manager = manager.clone();
+            let result = run_cancellable(move |cancel| {
+                let mut last = None;
+                while !cancel.load(Ordering::SeqCst) {
+                    let fix = manager.lock().gps_fix().clone();
+                    if fix.updated.is_some() && fix.updated != last {
+                        print!("\r\x1b[K{}\r\n", format_gps_line(&fix));
+                        let _ = io::stdout().flush();
+                        last = fix.updated;
+                    }
+                    thread::sleep(Duration::from_millis(200));
+                }
+                Ok(())
+            });
+            match result {
+                Ok(()) => CommandResult::Success(String::new()),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["reset"] => {
+            manager.lock().reset_gps();
+            CommandResult::Success("✓ 已清除定位状态".to_string())
+        }
+        ["send", command @ ..] if !command.is_empty() => {
+            if !connected.load(Ordering::SeqCst) {
+                return CommandResult::Error("未连接到串口".to_string());
+            }
+            let line = nmea::build_command(&command.join(" "));
+            match manager.lock().send_bytes(line.as_bytes()) {
+                Ok(_) => CommandResult::Success(format!("✓ 已发送: {}", line.trim_end())),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["ubx", class, id, payload @ ..] => {
+            let (class, id) = match (parse_number(class), parse_number(id)) {
+                (Some(c), Some(i)) if c <= 0xFF && i <= 0xFF => (c as u8, i as u8),
+                _ => return CommandResult::Error("UBX 类与 ID 为 0-255 的数字，如 0x06 0x08".to_string()),
+            };
+            let payload = match serial::parse_hex_string(&payload.join("")) {
+                Ok(p) => p,
+                Err(e) => return CommandResult::Error(e),
+            };
+            if !connected.load(Ordering::SeqCst) {
+                return CommandResult::Error("未连接到串口".to_string());
+            }
+            let frame = nmea::build_ubx(class, id, &payload);
+            match manager.lock().send_bytes(&frame) {
+                Ok(_) => CommandResult::Success(format!("✓ 已发送: {}", serial::bytes_to_hex_string(&frame))),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        _ => CommandResult::Error(GPS_USAGE.to_string()),
+    }
+}
+
+fn format_gps_fix(fix: &GpsFix) -> String {
+    if fix.sentences == 0 {
+        return format!("尚未收到 NMEA 语句 (校验错误 {})", fix.checksum_errors);
+    }
+    let or_dash = |v: Option<String>| v.unwrap_or_else(|| "-".to_string());
+    let fix_type = match fix.fix_type {
+        2 => "2D",
+        3 => "3D",
+        _ => "无",
+    };
+    format!(
+        "定位: {} ({}，{}，RMC {})\n\
+         UTC: {} {}\n\
+         位置: {}\n\
+         海拔: {}\n\
+         速度: {}  航向: {}\n\
+         卫星: 使用 {} / 可见 {}  PDOP {} HDOP {} VDOP {}\n\
+         语句: {} (校验错误 {})  更新: {}",
+        if fix.valid { "\x1b[32m有效\x1b[0m" } else { "\x1b[31m无效\x1b[0m" },
+        fix.quality_name(),
+        fix_type,
+        if fix.valid { "A" } else { "V" },
+        or_dash(fix.date.clone()),
+        or_dash(fix.time.clone()),
+        format_position(fix),
+        or_dash(fix.altitude.map(|a| format!("{:.1} m", a))),
+        or_dash(fix.speed_kmh.map(|s| format!("{:.1} km/h", s))),
+        or_dash(fix.course.map(|c| format!("{:.1}°", c))),
+        fix.satellites_used,
+        fix.satellites.len(),
+        or_dash(fix.pdop.map(|v| v.to_string())),
+        or_dash(fix.hdop.map(|v| v.to_string())),
+        or_dash(fix.vdop.map(|v| v.to_string())),
+        fix.sentences,
+        fix.checksum_errors,
+        or_dash(fix.updated.clone()),
+    )
+}
+
+fn format_position(fix: &GpsFix) -> String {
+    match (fix.latitude, fix.longitude) {
+        (Some(lat), Some(lon)) => format!("{:.6}, {:.6}", lat, lon),
+        _ => "-".to_string(),
+    }
+}
+
+// gps watch 每次更新输出的一行
+fn format_gps_line(fix: &GpsFix) -> String {
+    format!(
+        "{} {} {} 卫星 {} 海拔 {} 速度 {}",
+        fix.time.as_deref().unwrap_or("--:--:--"),
+        if fix.valid { "\x1b[32m●\x1b[0m" } else { "\x1b[31m○\x1b[0m" },
+        format_position(fix),
+        fix.satellites_used,
+        fix.altitude.map(|a| format!("{:.1}m", a)).unwrap_or_else(|| "-".to_string()),
+        fix.speed_kmh.map(|s| format!("{:.1}km/h", s)).unwrap_or_else(|| "-".to_string()),
+    )
+}
+
+const AT_USAGE: &str = "用法: at 进入 AT 模式 | at <命令> [-t 超时ms] | at list";
+
+fn cmd_at(
+    rest: &str,
+    manager: &Arc<Mutex<SerialManager>>,
+    connected: &Arc<AtomicBool>,
+    at_mode: &Arc<AtomicBool>,
+    at_busy: &Arc<AtomicBool>,
+) -> CommandResult {
+    if rest == "list" {
+        let lines: Vec<String> = at::LIBRARY
+            .iter()
+            .map(|c| format!("  {:<14} {:<6} {}", c.command, format!("{}s", c.timeout_ms as f64 / 1000.0), c.description))
+            .collect();
+        return CommandResult::Success(format!("AT 命令库 (命令 超时 说明):\n{}", lines.join("\n")));
+    }
+    if !connected.load(Ordering::SeqCst) {
+        return CommandResult::Error("未连接到串口".to_string());
+    }
+    if rest.is_empty() {
+        at_mode.store(true, Ordering::SeqCst);
+        return CommandResult::Success(
+            "已进入 AT 模式：每行作为 AT 命令发送，Tab 补全命令，Esc 取消等待，exit 或 Ctrl+D 退出".to_string(),
+        );
+    }
+
+    let (command, timeout) = match rest.strip_prefix("-t ") {
+        Some(opts) => {
+            let (ms, command) = opts.trim_start().split_once(' ').unwrap_or((opts, ""));
+            match ms.parse::<u64>() {
+                Ok(ms) if !command.trim().is_empty() => (command.trim(), Some(Duration::from_millis(ms))),
+                _ => return CommandResult::Error(AT_USAGE.to_string()),
+            }
+        }
+        None => (rest, None),
+    };
+    // +CSQ 等省略 AT 前缀的写法
+    let command = if command.to_uppercase().starts_with("AT") {
+        command.to_string()
+    } else {
+        format!("AT{}", command)
+    };
+    let timeout = timeout.unwrap_or_else(|| at::timeout_for(&command));
+
+    let manager = manager.clone();
+    at_busy.store(true, Ordering::SeqCst);
+    let result = run_cancellable(move |cancel| {
+        let response = at::execute(&manager, &command, timeout, cancel)?;
+        print!("{}", format_at_response(&response).replace('\n', "\r\n"));
+        let _ = io::stdout().flush();
+        Ok(())
+    });
+    at_busy.store(false, Ordering::SeqCst);
+
+    match result {
+        Ok(()) => CommandResult::Success(String::new()),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+// 命令与应答成对显示，主动上报单独列出
+fn format_at_response(response: &AtResponse) -> String {
+    let mut output = format!("\x1b[36m> {}\x1b[0m\n", response.command);
+    for line in &response.lines {
+        output.push_str(&format!("  {}\n", line));
+    }
+    let color = match response.result {
+        AtResult::Ok | AtResult::Prompt => "\x1b[32m",
+        AtResult::Timeout => "\x1b[33m",
+        _ => "\x1b[31m",
+    };
+    output.push_str(&format!("  {}{}\x1b[0m ({}ms)\n", color, response.result, response.elapsed_ms));
+    for urc in &response.urcs {
+        output.push_str(&format!("  \x1b[35m[URC] {}\x1b[0m\n", urc));
+    }
+    output
+}
+
+const PLOT_USAGE: &str = "用法: plot [csv[:名称,...] | kv | regex:<正则> | off] | plot show [点数] | plot watch | plot clear | plot export <文件.csv>";
+
+fn cmd_plot(rest: &str, manager: &Arc<Mutex<SerialManager>>, connected: &Arc<AtomicBool>) -> CommandResult {
+    let (sub, arg) = rest.split_once(' ').map(|(s, a)| (s, a.trim())).unwrap_or((rest, ""));
+    match (sub, arg) {
+        ("", _) => {
+            let mgr = manager.lock();
+            let plot = mgr.plot();
+            let parser = match plot.parser_spec() {
+                Some(spec) => format!("数值提取: {}", spec),
+                None => "未启用数值提取".to_string(),
+            };
+            CommandResult::Success(format!("{}\n{}", parser, format_plot_table(plot, 40)))
+        }
+        ("show", count) => {
+            let count = if count.is_empty() { Ok(40) } else { count.parse::<usize>() };
+            match count {
+                Ok(count) if count > 0 => CommandResult::Success(format_plot_table(manager.lock().plot(), count)),
+                _ => CommandResult::Error(PLOT_USAGE.to_string()),
+            }
+        }
+        ("watch", "") => {
+            if !connected.load(Ordering::SeqCst) {
+                return CommandResult::Error("未连接到串口".to_string());
+            }
+            println!("实时数值 (按 Esc 停止)");
+            let manager = manager.clone();
+            let result = run_cancellable(move |cancel| {
+                let mut last = Vec::new();
+                while !cancel.load(Ordering::SeqCst) {
+                    let summaries = manager.lock().plot().summaries();
+                    let counts: Vec<usize> = summaries.iter().map(|s| s.count).collect();
+                    if counts != last && !summaries.is_empty() {
+                        let values: Vec<String> = summaries.iter().map(|s| format!("{}={}", s.name, s.last)).collect();
+                        print!("\r\x1b[K{}\r\n", values.join("  "));
+                        let _ = io::stdout().flush();
+                        last = counts;
+                    }
+                    thread::sleep(Duration::from_millis(200));
+                }
+                Ok(())
+            });
+            match result {
+                Ok(()) => CommandResult::Success(String::new()),
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ("clear", "") => {
+            manager.lock().plot_mut().clear();
+            CommandResult::Success("✓ 已清除数值通道".to_string())
+        }
+        ("export", path) if !path.is_empty() => match manager.lock().plot().export_csv(path) {
+            Ok(rows) => CommandResult::Success(format!("✓ 已导出 {} 行到 {}", rows, path)),
+            Err(e) => CommandResult::Error(e),
+        },
+        ("off", "") => {
+            manager.lock().set_plot_parser(None);
+            CommandResult::Success("✓ 已停止数值提取".to_string())
+        }
+        _ => match plot::ChannelParser::parse(rest) {
+            Ok(parser) => {
+                manager.lock().set_plot_parser(Some(parser));
+                CommandResult::Success(format!("✓ 接收的文本行将按 {} 提取数值", rest))
+            }
+            Err(e) => CommandResult::Error(format!("{}\n{}", e, PLOT_USAGE)),
+        },
+    }
+}
+
+// 各通道的统计与最近 count 个点的走势
+fn format_plot_table(plot: &Plotter, count: usize) -> String {
+    let summaries = plot.summaries();
+    if summaries.is_empty() {
+        return "还没有提取到数值".to_string();
+    }
+    let mut output = format!(
+        // 中文占两列，表头宽度相应减少
+        "{:<10} {:>8} {:>8} {:>8} {:>8} {:>5}  走势",
+        "通道", "最新", "最小", "最大", "平均", "点数"
+    );
+    for s in &summaries {
+        output.push_str(&format!(
+            "\n{:<12} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>7}  {}",
+            s.name,
+            s.last,
+            s.min,
+            s.max,
+            s.mean,
+            s.count,
+            plot::sparkline(&plot.recent(&s.name, count))
+        ));
+    }
+    output
+}
+
+fn cmd_modbus_gateway(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+    connected: &Arc<AtomicBool>,
+) -> CommandResult {
+    let addr = match args {
+        [] => "0.0.0.0:502",
+        [addr] => *addr,
+        _ => return CommandResult::Error("用法: mbgw [监听地址:端口]，默认 0.0.0.0:502".to_string()),
+    };
+    if !connected.load(Ordering::SeqCst) {
+        return CommandResult::Error("未连接到串口".to_string());
+    }
+
+    let listener = match modbus_gateway::bind(addr) {
+        Ok(l) => l,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let options = config.lock().modbus.clone();
+    println!(
+        "Modbus TCP 网关已启动: {} → 串口 ({:?}，超时 {}ms，按 Esc 停止)",
+        listener.local_addr().map(|a| a.to_string()).unwrap_or_default(),
+        options.mode,
+        options.timeout_ms
+    );
+
+    let manager = manager.clone();
+    let result = run_cancellable(move |cancel| {
+        modbus_gateway::run_gateway(&listener, &manager, &options, cancel, |event| {
+            match event {
+                GatewayEvent::Client { addr, connected: true } => print!("\r\x1b[K客户端已连接: {}\r\n", addr),
+                GatewayEvent::Client { addr, connected: false } => print!("\r\x1b[K客户端已断开: {}\r\n", addr),
+                GatewayEvent::Request { error: None, .. } => {}
+                GatewayEvent::Request { client, transaction, unit, error: Some(e), .. } => print!(
+                    "\r\x1b[K\x1b[33m{} 事务 {} 从站 {}: {}\x1b[0m\r\n",
+                    client, transaction, unit, e
+                ),
+            }
+            let _ = io::stdout().flush();
+        })
+    });
+
+    match result {
+        Ok(_) => CommandResult::Success("✓ 网关已停止".to_string()),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+// 后台运行的 Modbus 从站
+struct ReplSlave {
+    path: String,
+    port: String,
+    map: Arc<Mutex<RegisterMap>>,
+    cancel: Arc<AtomicBool>,
+}
+
+const MODBUS_SLAVE_USAGE: &str = "用法: mbs start <寄存器表> [--pty | -p 串口 [-b 波特率]] [-a] [-l 日志文件]
+      mbs show [c|d|h|i] | mbs set <c|d|h|i> <地址> <值...> | mbs save [文件] | mbs stop";
+
+fn cmd_modbus_slave(args: &[&str], slave: &Mutex<Option<ReplSlave>>) -> CommandResult {
+    let mut current = slave.lock();
+    // 从站线程出错退出后清理
+    if current.as_ref().is_some_and(|s| s.cancel.load(Ordering::SeqCst)) {
+        *current = None;
+    }
+
+    match args {
+        ["start", rest @ ..] => {
+            if current.is_some() {
+                return CommandResult::Error("从站已在运行，先执行 mbs stop".to_string());
+            }
+            match start_modbus_slave(rest) {
+                Ok(started) => {
+                    let msg = format!(
+                        "✓ Modbus 从站 {} 已启动: {} (寄存器表 {})",
+                        started.map.lock().unit,
+                        started.port,
+                        started.path
+                    );
+                    *current = Some(started);
+                    CommandResult::Success(msg)
+                }
+                Err(e) => CommandResult::Error(e),
+            }
+        }
+        ["stop"] => match current.take() {
+            Some(s) => {
+                s.cancel.store(true, Ordering::SeqCst);
+                CommandResult::Success("✓ Modbus 从站已停止".to_string())
+            }
+            None => CommandResult::Error("从站未运行".to_string()),
+        },
+        _ => {
+            let Some(running) = current.as_ref() else {
+                return CommandResult::Error(format!("从站未运行\n{}", MODBUS_SLAVE_USAGE));
+            };
+            let mut map = running.map.lock();
+            match args {
+                [] | ["show"] => {
+                    let mut output = format!("从站 {}，端口 {}，寄存器表 {}", map.unit, running.port, running.path);
+                    for (name, table) in [
+                        ("线圈", RegisterTable::Coils),
+                        ("离散输入", RegisterTable::DiscreteInputs),
+                        ("保持寄存器", RegisterTable::HoldingRegisters),
+                        ("输入寄存器", RegisterTable::InputRegisters),
+                    ] {
+                        output.push_str(&format!("\n  {}: {} 个", name, map.entries(table).len()));
+                    }
+                    CommandResult::Success(output)
+                }
+                ["show", table] => match RegisterTable::parse(table) {
+                    Ok(table) => {
+                        let entries = map.entries(table);
+                        if entries.is_empty() {
+                            return CommandResult::Success("（空）".to_string());
+                        }
+                        let lines: Vec<String> = entries
+                            .iter()
+                            .map(|(address, value)| {
+                                if table.is_bits() {
+                                    format!("  [{:>5}] {}", address, value)
+                                } else {
+                                    format!("  [{:>5}] 0x{:04X} {}", address, value, value)
+                                }
+                            })
+                            .collect();
+                        CommandResult::Success(lines.join("\n"))
+                    }
+                    Err(e) => CommandResult::Error(e),
+                },
+                ["set", table, address, values @ ..] if !values.is_empty() => {
+                    let table = match RegisterTable::parse(table) {
+                        Ok(t) => t,
+                        Err(e) => return CommandResult::Error(e),
+                    };
+                    let address = match parse_number(address).filter(|&a| a <= u16::MAX as u32) {
+                        Some(a) => a as u16,
+                        None => return CommandResult::Error(format!("无效的地址: {}", address)),
+                    };
+                    let values = match values
+                        .iter()
+                        .map(|v| match v.parse::<i16>() {
+                            Ok(n) if n < 0 => Some(n as u16),
+                            _ => parse_number(v).filter(|&n| n <= u16::MAX as u32).map(|n| n as u16),
+                        })
+                        .collect::<Option<Vec<_>>>()
+                    {
+                        Some(v) => v,
+                        None => return CommandResult::Error("无效的数值".to_string()),
+                    };
+                    match map.set(table, address, &values) {
+                        Ok(_) => CommandResult::Success(format!("✓ 已设置 {} 个", values.len())),
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                ["save"] | ["save", _] => {
+                    let path = args.get(1).copied().unwrap_or(&running.path);
+                    match map.save(path) {
+                        Ok(_) => CommandResult::Success(format!("✓ 已保存到 {}", path)),
+                        Err(e) => CommandResult::Error(e),
+                    }
+                }
+                _ => CommandResult::Error(MODBUS_SLAVE_USAGE.to_string()),
+            }
+        }
+    }
+}
+
+fn start_modbus_slave(args: &[&str]) -> Result<ReplSlave, String> {
+    let mut path = None;
+    let mut port = None;
+    let mut baud = 9600;
+    let mut pty = false;
+    let mut options = SlaveOptions::default();
+
+    let mut iter = args.iter();
+    while let Some(&arg) = iter.next() {
+        let value = match arg {
+            "-p" | "-b" | "-l" => *iter.next().ok_or(MODBUS_SLAVE_USAGE)?,
+            _ => "",
+        };
+
+        match arg {
+            "-p" => port = Some(value.to_string()),
+            "-b" => baud = value.parse().map_err(|_| format!("无效的波特率: {}", value))?,
+            "-l" => options.log_path = Some(value.to_string()),
+            "-a" => options.mode = ModbusMode::Ascii,
+            "--pty" => pty = true,
+            _ if arg.starts_with('-') || path.is_some() => return Err(MODBUS_SLAVE_USAGE.to_string()),
+            _ => path = Some(arg.to_string()),
+        }
+    }
+
+    let path = path.ok_or(MODBUS_SLAVE_USAGE)?;
+    let target = match (pty, port) {
+        (true, None) => SimTarget::Pty,
+        (false, Some(name)) => SimTarget::Port { name, baud_rate: baud },
+        _ => return Err(MODBUS_SLAVE_USAGE.to_string()),
+    };
+
+    let map = Arc::new(Mutex::new(RegisterMap::load(&path)?));
+    let mut sim_port = SimPort::open(&target)?;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let started = ReplSlave {
+        path,
+        port: sim_port.name().to_string(),
+        map: map.clone(),
+        cancel: cancel.clone(),
+    };
+
+    thread::spawn(move || {
+        let result = modbus_slave::run_slave(&map, &mut sim_port, &options, &cancel, |event| {
+            match event {
+                SlaveEvent::Transaction { unit, summary, exception: None, .. } => {
+                    print!("\r\x1b[K\x1b[36m[从站 {}]\x1b[0m {}\r\n", unit, summary)
+                }
+                SlaveEvent::Transaction { unit, summary, exception: Some(code), .. } => print!(
+                    "\r\x1b[K\x1b[36m[从站 {}]\x1b[0m {} \x1b[33m→ 异常 {:02X} {}\x1b[0m\r\n",
+                    unit,
+                    summary,
+                    code,
+                    modbus::exception_name(*code)
+                ),
+                SlaveEvent::BadFrame { frame, error, .. } => {
+                    print!("\r\x1b[K\x1b[31m[从站] {}: {}\x1b[0m\r\n", error, frame)
+                }
+            }
+            let _ = io::stdout().flush();
+        });
+        if let Err(e) = result {
+            println!("\r\x1b[K\x1b[31mModbus 从站已退出: {}\x1b[0m", e);
+            cancel.store(true, Ordering::SeqCst);
+        }
+    });
+    Ok(started)
+}
+
+const SIM_USAGE: &str =
+    "用法: sim <应答表> [--pty | -p 串口 [-b 波特率] | -t 地址:端口] [-u 未匹配记录文件]";
+
+fn cmd_sim(args: &[&str]) -> CommandResult {
+    let mut path = None;
+    let mut port = None;
+    let mut baud = 115200;
+    let mut tcp = None;
+    let mut pty = false;
+    let mut options = SimOptions::default();
+
+    let mut iter = args.iter();
+    while let Some(&arg) = iter.next() {
+        let value = match arg {
+            "-p" | "-b" | "-t" | "-u" => match iter.next() {
+                Some(v) => *v,
+                None => return CommandResult::Error(SIM_USAGE.to_string()),
+            },
+            _ => "",
+        };
+
+        match arg {
+            "-p" => port = Some(value.to_string()),
+            "-b" => match value.parse() {
+                Ok(b) => baud = b,
+                Err(_) => return CommandResult::Error(format!("无效的波特率: {}", value)),
+            },
+            "-t" => tcp = Some(value.to_string()),
+            "-u" => options.unmatched_path = Some(value.to_string()),
+            "--pty" => pty = true,
+            _ if arg.starts_with('-') || path.is_some() => {
+                return CommandResult::Error(SIM_USAGE.to_string())
+            }
+            _ => path = Some(arg.to_string()),
+        }
+    }
+
+    let path = match path {
+        Some(p) => p,
+        None => return CommandResult::Error(SIM_USAGE.to_string()),
+    };
+    let target = match (pty, port, tcp) {
+        (true, None, None) => SimTarget::Pty,
+        (false, Some(name), None) => SimTarget::Port { name, baud_rate: baud },
+        (false, None, Some(addr)) => SimTarget::Tcp { addr },
+        _ => return CommandResult::Error(SIM_USAGE.to_string()),
+    };
+
+    let table = match SimTable::load(&path) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::Error(e),
+    };
+    let mut sim_port = match SimPort::open(&target) {
+        Ok(p) => p,
+        Err(e) => return CommandResult::Error(e),
+    };
+
+    println!(
+        "模拟器已启动: {} ({} 条规则，按 Esc 停止)",
+        sim_port.name(),
+        table.rules.len()
+    );
+    let result = run_cancellable(move |cancel| {
+        simulator::run_simulator(&table, &mut sim_port, &options, cancel, |event| {
+            match event {
+                SimEvent::Client { addr, connected: true } => print!("\r\x1b[K客户端已连接: {}\r\n", addr),
+                SimEvent::Client { addr, connected: false } => print!("\r\x1b[K客户端已断开: {}\r\n", addr),
+                SimEvent::Matched { rule, request, responses, state } => print!(
+                    "\r\x1b[K\x1b[32m✓\x1b[0m {} ← {} (应答 {} 条{})\r\n",
+                    rule,
+                    request,
+                    responses,
+                    if state.is_empty() { String::new() } else { format!("，状态 {}", state) }
+                ),
+                SimEvent::Unmatched { request, .. } => {
+                    print!("\r\x1b[K\x1b[33m? 未匹配\x1b[0m {}\r\n", request)
+                }
+            }
+            let _ = io::stdout().flush();
+        })
+    });
+
+    match result {
+        Ok(_) => CommandResult::Success("✓ 模拟器已停止".to_string()),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+const SEND_FILE_USAGE: &str =
+    "用法: sendfile <文件> [-c 块大小] [-d 块间隔ms] [-l] [-L 行间隔ms] [-n crlf|lf|cr] [-e] [-w 提示符正则] [-t 超时ms]";
+
+fn cmd_send_file(
+    args: &[&str],
+    manager: &Arc<Mutex<SerialManager>>,
+    connected: &Arc<AtomicBool>,
+) -> CommandResult {
+    if !connected.load(Ordering::SeqCst) {
+        return CommandResult::Error("未连接到串口".to_string());
+    }
+
+    let request = match parse_send_file_args(args) {
+        Ok(r) => r,
+        Err(e) => return CommandResult::Error(e),
+    };
+
+    println!("发送中... (按 Esc 取消)");
+    let manager = manager.clone();
+    let result = run_cancellable(move |cancel| {
+        transfer::run_transfer(&request, &manager, cancel, &mut print_transfer_progress)
+    });
+    println!();
+
+    match result {
+        Ok(_) => CommandResult::Success("✓ 发送完成".to_string()),
+        Err(e) => CommandResult::Error(e),
+    }
+}
+
+// 解析 sendfile 参数；-L/-n/-e/-w 隐含按行发送
+fn parse_send_file_args(args: &[&str]) -> Result<TransferRequest, String> {
+    let mut options = RawSendOptions::default();
+    let mut path = None;
+
+    let mut iter = args.iter();
+    while let Some(&arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .map(|v| v.to_string())
+                .ok_or_else(|| format!("{} 需要参数", name))
+        };
+        let number = |v: String| v.parse::<u64>().map_err(|_| format!("无效的数值: {}", v));
+
+        match arg {
+            "-c" => options.chunk_size = number(value("-c")?)? as usize,
+            "-d" => options.chunk_delay_ms = number(value("-d")?)?,
+            "-l" => options.line_mode = true,
+            "-L" => {
+                options.line_mode = true;
+                options.line_delay_ms = number(value("-L")?)?;
+            }
+            "-n" => {
+                options.line_mode = true;
+                options.newline = value("-n")?;
+            }
+            "-e" => {
+                options.line_mode = true;
+                options.wait_echo = true;
+            }
+            "-w" => {
+                options.line_mode = true;
+                options.wait_for = Some(value("-w")?);
+            }
+            "-t" => options.wait_timeout_ms = number(value("-t")?)?,
+            _ if arg.starts_with('-') => return Err(SEND_FILE_USAGE.to_string()),
+            _ if path.is_none() => path = Some(arg.to_string()),
+            _ => return Err(SEND_FILE_USAGE.to_string()),
+        }
+    }
+
+    let path = path.ok_or_else(|| SEND_FILE_USAGE.to_string())?;
+    Ok(TransferRequest::RawSend { path, options })
+}
+
+// 在后台线程执行任务，期间按 Esc 或 Ctrl+C 取消（原始模式下 Ctrl+C 不会结束程序）
+fn run_cancellable<T, F>(job: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&Arc<AtomicBool>) -> Result<T, String> + Send + 'static,
+{
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancel_thread = cancel.clone();
+    let handle = thread::spawn(move || job(&cancel_thread));
+
+    let raw = enable_raw_mode().is_ok();
+    while !handle.is_finished() {
+        if event::poll(Duration::from_millis(50)).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press && (key.code == KeyCode::Esc || ctrl_c) {
+                    cancel.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+    }
+    if raw {
+        let _ = disable_raw_mode();
+    }
+
+    handle.join().unwrap_or_else(|_| Err("后台任务异常退出".to_string()))
+}
+
+fn print_transfer_progress(p: &TransferProgress) {
+    let total = match p.total {
+        Some(t) if t > 0 => format!("/{} ({}%)", t, p.bytes * 100 / t),
+        _ => String::new(),
+    };
+    print!(
+        "\r\x1b[K{} {} {}{} 字节, {} 块, 重试 {}",
+        p.protocol, p.file, p.bytes, total, p.blocks, p.retries
+    );
+    let _ = io::stdout().flush();
+}
+
+fn print_step_report(report: &StepReport) {
+    let status = match report.status {
+        StepStatus::Passed => "\x1b[32m通过\x1b[0m",
+        StepStatus::Failed => "\x1b[31m失败\x1b[0m",
+        StepStatus::TimedOut => "\x1b[33m超时\x1b[0m",
+    };
+    println!(
+        "\r\x1b[K  [{:>4}] {} {} ({}ms) {}",
+        report.line, status, report.step, report.elapsed_ms, report.message
+    );
+}
+
+// 格式化一条收发记录用于命令行显示
+fn format_entry(entry: &DataEntry) -> String {
+    let flag = if entry.valid == Some(false) {
+        " \x1b[31m[校验错误]\x1b[0m"
+    } else {
+        ""
+    };
+
+    if entry.direction == "marker" {
+        return format!("[{}] \x1b[35m-- {}\x1b[0m", entry.timestamp, entry.data);
+    }
+    let line = if let Some(color) = &entry.highlight {
+        format!("{}[{}] RX: {}\x1b[0m{}", ansi_color(color), entry.timestamp, entry.data.trim(), flag)
+    } else if entry.direction == "tx" {
+        let printable = entry
+            .data
+            .chars()
+            .all(|c| !c.is_control() || matches!(c, '\r' | '\n' | '\t'));
+        if printable {
+            format!("[{}] TX: {}", entry.timestamp, entry.data.trim())
+        } else {
+            format!("[{}] TX HEX: {}", entry.timestamp, entry.hex)
+        }
+    } else {
+        format!("[{}] RX: {}{}", entry.timestamp, entry.data.trim(), flag)
+    };
+
+    match &entry.decoded {
+        Some(decoded) => {
+            let mut out = line;
+            format_fields(&decoded.fields, 1, &mut out);
+            out
+        }
+        None => line,
+    }
+}
+
+// 解码字段树，每层缩进两格
+fn format_fields(fields: &[Field], depth: usize, out: &mut String) {
+    for field in fields {
+        let mark = match field.valid {
+            Some(true) => " \x1b[32m✓\x1b[0m",
+            Some(false) => " \x1b[31m✗\x1b[0m",
+            None => "",
+        };
+        out.push_str(&format!(
+            "\r\n{}\x1b[90m{}:\x1b[0m {}{}",
+            "  ".repeat(depth + 1),
+            field.name,
+            field.value,
+            mark
+        ));
+        format_fields(&field.children, depth + 1, out);
+    }
+}
+
+// 触发规则的高亮颜色，未知颜色以粗体显示
+fn ansi_color(color: &str) -> &'static str {
+    match color.to_lowercase().as_str() {
+        "red" => "\x1b[1;31m",
+        "green" => "\x1b[1;32m",
+        "yellow" => "\x1b[1;33m",
+        "blue" => "\x1b[1;34m",
+        "magenta" | "purple" => "\x1b[1;35m",
+        "cyan" => "\x1b[1;36m",
+        _ => "\x1b[1m",
+    }
+}
+
+// 触发规则运行的脚本输出，终端模式下也能正常换行
+fn script_logger() -> LogFn {
+    Arc::new(|msg: &str| print!("\r\x1b[K\x1b[36m[脚本] {}\x1b[0m\r\n", msg))
+}
+
+// 运行交互式终端模式
+fn run_terminal_mode(
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+    connected: &Arc<AtomicBool>,
+    in_terminal_mode: &Arc<AtomicBool>,
+) {
+    // 标记进入终端模式，暂停主 REPL 的接收线程
+    in_terminal_mode.store(true, Ordering::SeqCst);
+    
+    println!("\x1b[1;32m═══════════════════════════════════════════\x1b[0m");
+    println!("\x1b[1;32m   进入交互式终端模式\x1b[0m");
+    println!("\x1b[1;33m   重要: 按 Ctrl+] 退出到命令行模式\x1b[0m");
+    println!("\x1b[1;32m   按 F2 发送文件\x1b[0m");
+    println!("\x1b[1;32m═══════════════════════════════════════════\x1b[0m\n");
+    
+    // 使用 crossterm 启用原始模式（跨平台）
+    if let Err(e) = enable_raw_mode() {
+        println!("\x1b[31m无法启用原始模式: {}\x1b[0m", e);
+        in_terminal_mode.store(false, Ordering::SeqCst);
+        return;
+    }
+    
+    let running = Arc::new(AtomicBool::new(true));
+    let running_rx = running.clone();
+    let manager_rx = manager.clone();
+    let config_rx = config.clone();
+    let connected_rx = connected.clone();
+    let zmodem_pending: Arc<Mutex<Option<ZmodemRequest>>> = Arc::new(Mutex::new(None));
+    let zmodem_rx = zmodem_pending.clone();
+    
+    // 接收线程 - 显示串口数据
+    let rx_handle = thread::spawn(move || {
+        while running_rx.load(Ordering::SeqCst) && connected_rx.load(Ordering::SeqCst) {
+            // 等待主循环处理 ZMODEM 请求期间不读取串口
+            if zmodem_rx.lock().is_some() {
+                thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+
+            let mut mgr = manager_rx.lock();
+            if let Ok(entries) = mgr.read_available() {
+                // 直接输出接收数据，不添加时间戳；键盘输入由设备回显
+                for entry in &entries {
+                    match (entry.direction.as_str(), &entry.highlight) {
+                        ("rx", None) => print!("{}", entry.data),
+                        ("rx", Some(color)) => print!("{}{}\x1b[0m", ansi_color(color), entry.data),
+                        ("marker", _) => print!("\r\n\x1b[35m[标记] {}\x1b[0m\r\n", entry.data),
+                        _ => {}
+                    }
+                    let _ = io::stdout().flush();
+                }
+            }
+            if let Some(request) = mgr.take_zmodem_request() {
+                *zmodem_rx.lock() = Some(request);
+            }
+            let hits = mgr.take_trigger_hits();
+            drop(mgr);
+            trigger::run_scripts(&hits, &manager_rx, &config_rx, &script_logger());
+            thread::sleep(Duration::from_millis(10));
+        }
+    });
+    
+    // F2 启动的后台文件发送
+    let mut send_job: Option<SendJob> = None;
+    
+    // 主循环 - 读取键盘输入并发送 (使用 crossterm 跨平台)
+    loop {
+        if send_job.as_ref().is_some_and(|(handle, _)| handle.is_finished()) {
+            if let Some((handle, _)) = send_job.take() {
+                match handle.join().unwrap_or_else(|_| Err("发送线程异常退出".to_string())) {
+                    Ok(_) => print!("\r\n\x1b[32m✓ 文件发送完成\x1b[0m\r\n"),
+                    Err(e) => print!("\r\n\x1b[31m✗ {}\x1b[0m\r\n", e),
+                }
+                let _ = io::stdout().flush();
+            }
+        }
+
+        let request = *zmodem_pending.lock();
+        if let Some(request) = request {
+            let _ = disable_raw_mode();
+            run_zmodem_prompt(manager, request);
+            let _ = enable_raw_mode();
+            *zmodem_pending.lock() = None;
+        }
+
+        if event::poll(Duration::from_millis(10)).unwrap_or(false) {
+            if let Ok(Event::Key(key_event)) = event::read() {
+                // 只处理按下事件，忽略释放和重复事件
+                if key_event.kind != KeyEventKind::Press {
+                    continue;
+                }
+                
+                // Ctrl+] 退出
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) 
+                    && key_event.code == KeyCode::Char(']') 
+                {
+                    running.store(false, Ordering::SeqCst);
+                    break;
+                }
+                
+                // F2 发送文件，发送期间 Esc 取消
+                if key_event.code == KeyCode::F(2) && send_job.is_none() {
+                    let _ = disable_raw_mode();
+                    send_job = prompt_send_file(manager);
+                    let _ = enable_raw_mode();
+                    continue;
+                }
+                if key_event.code == KeyCode::Esc {
+                    if let Some((_, cancel)) = &send_job {
+                        cancel.store(true, Ordering::SeqCst);
+                        continue;
+                    }
+                }
+                
+                let data = match key_event.code {
+                    KeyCode::Enter => "\r".to_string(),
+                    KeyCode::Backspace => "\x7f".to_string(),
+                    KeyCode::Tab => "\t".to_string(),
+                    KeyCode::Esc => "\x1b".to_string(),
+                    KeyCode::Up => "\x1b[A".to_string(),
+                    KeyCode::Down => "\x1b[B".to_string(),
+                    KeyCode::Right => "\x1b[C".to_string(),
+                    KeyCode::Left => "\x1b[D".to_string(),
+                    KeyCode::Home => "\x1b[H".to_string(),
+                    KeyCode::End => "\x1b[F".to_string(),
+                    KeyCode::Delete => "\x1b[3~".to_string(),
+                    KeyCode::Char(c) => {
+                        if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                            // Ctrl+字母 转换为控制字符
+                            let ctrl_char = (c as u8 & 0x1f) as char;
+                            ctrl_char.to_string()
+                        } else {
+                            c.to_string()
+                        }
+                    }
+                    _ => continue,
+                };
+                
+                let mut mgr = manager.lock();
+                let _ = mgr.send(&data, false);
+            }
+        }
+    }
+    
+    if let Some((handle, cancel)) = send_job {
+        cancel.store(true, Ordering::SeqCst);
+        let _ = handle.join();
+    }
+    
+    let _ = disable_raw_mode();
+    let _ = rx_handle.join();
+    
+    // 退出终端模式
+    in_terminal_mode.store(false, Ordering::SeqCst);
+    
+    println!("\n\x1b[33m═══ 已退出终端模式 ═══\x1b[0m\n");
+}
+
+// 后台发送任务及其取消标志
+type SendJob = (thread::JoinHandle<Result<(), String>>, Arc<AtomicBool>);
+
+// 终端模式下询问要发送的文件及参数，在后台线程发送，设备回显照常显示
+fn prompt_send_file(
+    manager: &Arc<Mutex<SerialManager>>,
+) -> Option<SendJob> {
+    print!("\r\n\x1b[1;36m发送文件 (<文件> [选项]，同 sendfile 命令，空行取消): \x1b[0m");
+    let _ = io::stdout().flush();
+
+    let mut input = String::new();
+    let _ = io::stdin().read_line(&mut input);
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.is_empty() {
+        return None;
+    }
+
+    let request = match parse_send_file_args(&args) {
+        Ok(r) => r,
+        Err(e) => {
+            println!("\x1b[31m{}\x1b[0m", e);
+            return None;
+        }
+    };
+    println!("\x1b[33m发送中，按 Esc 取消\x1b[0m");
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let manager = manager.clone();
+    let cancel_thread = cancel.clone();
+    let handle = thread::spawn(move || {
+        transfer::run_transfer(&request, &manager, &cancel_thread, &mut |_| {})
+    });
+    Some((handle, cancel))
+}
+
+// 对端启动了 sz/rz：询问用户后执行 ZMODEM 传输，拒绝时取消对端会话
+fn run_zmodem_prompt(manager: &Arc<Mutex<SerialManager>>, request: ZmodemRequest) {
+    let prompt = match request {
+        ZmodemRequest::Receive => "\r\n\x1b[1;36m检测到 ZMODEM 发送请求，保存到目录 [.] (输入 n 取消): \x1b[0m",
+        ZmodemRequest::Send => "\r\n\x1b[1;36m检测到 ZMODEM 接收请求，输入要发送的文件 (空行取消): \x1b[0m",
+    };
+    print!("{}", prompt);
+    let _ = io::stdout().flush();
+
+    let mut input = String::new();
+    let _ = io::stdin().read_line(&mut input);
+    let input = input.trim();
+
+    let transfer_request = match request {
+        ZmodemRequest::Receive if input.eq_ignore_ascii_case("n") => None,
+        ZmodemRequest::Receive => Some(TransferRequest::ZmodemReceive {
+            dir: if input.is_empty() { ".".to_string() } else { input.to_string() },
+        }),
+        ZmodemRequest::Send if input.is_empty() => None,
+        ZmodemRequest::Send => Some(TransferRequest::ZmodemSend {
+            paths: input.split_whitespace().map(|s| s.to_string()).collect(),
+        }),
+    };
+
+    match transfer_request {
+        Some(transfer_request) => {
+            println!("\x1b[33m按 Esc 取消传输\x1b[0m");
+            let manager = manager.clone();
+            let result = run_cancellable(move |cancel| {
+                transfer::run_transfer(&transfer_request, &manager, cancel, &mut print_transfer_progress)
+            });
+            match result {
+                Ok(_) => println!("\n\x1b[32m✓ 传输完成\x1b[0m"),
+                Err(e) => println!("\n\x1b[31m✗ {}\x1b[0m", e),
+            }
+        }
+        None => {
+            let _ = manager.lock().write_raw(&zmodem::ABORT_SEQUENCE);
+            println!("已取消");
+        }
+    }
+
+    // 丢弃对端残留的 ZMODEM 帧，避免再次触发提示
+    thread::sleep(Duration::from_millis(500));
+    let mut mgr = manager.lock();
+    let _ = mgr.read_available();
+    mgr.take_zmodem_request();
+}
+
+// 交互式终端模式命令
+fn cmd_terminal(
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+    connected: &Arc<AtomicBool>,
+    in_terminal_mode: &Arc<AtomicBool>,
+) -> CommandResult {
+    if !connected.load(Ordering::SeqCst) {
+        return CommandResult::Error("未连接到串口，请先使用 connect 命令连接".to_string());
+    }
+    
+    run_terminal_mode(manager, config, connected, in_terminal_mode);
+    CommandResult::Success(String::new())
+}
+
+fn cmd_config(args: &[&str]) -> CommandResult {
+    if args.is_empty() {
+        let output = "
+配置选项:
+  baud <速率>     - 设置波特率 (默认: 115200)
+  data <位数>     - 设置数据位 (5-8)
+  stop <位数>     - 设置停止位 (1-2)
+  parity <类型>   - 设置校验 (none/odd/even)
+
+示例: config baud 9600
+";
+        return CommandResult::Success(output.to_string());
+    }
+    
+    // TODO: 实现配置功能
+    CommandResult::Success("配置已更新（功能待实现）".to_string())
+}
+
+fn cmd_status(manager: &Arc<Mutex<SerialManager>>, connected: &Arc<AtomicBool>) -> CommandResult {
+    let status = if connected.load(Ordering::SeqCst) {
+        "\x1b[32m● 已连接\x1b[0m"
+    } else {
+        "\x1b[31m○ 未连接\x1b[0m"
+    };
+
+    let mgr = manager.lock();
+    let mut output = format!("状态: {}", status);
+    if mgr.rx_checksum() != ChecksumAlgorithm::None {
+        output.push_str(&format!("\n接收校验: {}", mgr.rx_checksum().name()));
+    }
+    if mgr.framing() != Framing::None {
+        output.push_str(&format!("\n分帧: {}", mgr.framing().name()));
+    }
+    if let Some(log) = mgr.logging_status() {
+        output.push_str(&format!("\n日志: {}", log.path));
+    }
+    output.push('\n');
+    output.push_str(&format_stats(&mgr.stats()));
+    CommandResult::Success(output)
+}
+
+fn cmd_stats(args: &[&str], manager: &Arc<Mutex<SerialManager>>) -> CommandResult {
+    match args {
+        [] => CommandResult::Success(format_stats(&manager.lock().stats())),
+        ["reset"] => {
+            manager.lock().reset_stats();
+            CommandResult::Success("✓ 已重置流量统计".to_string())
+        }
+        _ => CommandResult::Error("用法: stats [reset]".to_string()),
+    }
+}
+
+fn format_stats(s: &SessionStats) -> String {
+    format!(
+        "连接时长: {}  (统计自 {})\n\
+         接收: {} 字节, {} 条  当前 {}/s  峰值 {}/s\n\
+         发送: {} 字节, {} 条  当前 {}/s  峰值 {}/s\n\
+         校验错误: {}  读取错误: {}  丢弃: {} 条 ({} 字节)",
+        stats::format_duration(s.connected_secs),
+        s.since,
+        s.rx_bytes,
+        s.rx_frames,
+        stats::format_bytes(s.rx_rate),
+        stats::format_bytes(s.peak_rx_rate),
+        s.tx_bytes,
+        s.tx_frames,
+        stats::format_bytes(s.tx_rate),
+        stats::format_bytes(s.peak_tx_rate),
+        s.bad_frames,
+        s.read_errors,
+        s.dropped_entries,
+        s.dropped_bytes
+    )
+}
+
+fn print_banner() {
+    println!(r#"
+    ╔═══════════════════════════════════════════════════╗
+    ║                                                   ║
+    ║      ⚡ xTools CLI - 交互式串口终端 v0.1.0       ║
     ║                                                   ║
     ║           🐱 按 Tab 键补全命令 🔌                 ║
     ║                                                   ║
@@ -575,13 +3038,132 @@ fn print_help() {
                              ⚠️  连接后自动进入终端模式
                              ⚠️  按 Ctrl+] 退出终端模式
     disconnect, disc      - 断开串口连接
-    status, st           - 查看连接状态
+    status, st           - 查看连接状态与流量统计
+    stats [reset]        - 查看/重置流量统计 (收发字节与条数、吞吐量、连接时长、错误与丢弃)
 
   数据收发:
     send <数据>          - 发送文本数据 (自动添加 \r\n)
     hex <十六进制>       - 发送十六进制数据 (如: hex 48 65 6C 6C 6F)
+    checksum [算法]      - 查看/设置接收帧校验 (none/sum8/xor8/crc8/crc16_modbus/crc16_ccitt/crc32)
+    framing [方式]       - 查看/设置分帧 (none/slip/cobs/hdlc)，接收按帧去转义，hex 发送自动转义
+    timestamp, ts [方式] - 查看/设置时间戳 (datetime 完整日期时间/time 时刻/delta 间隔/elapsed 连接以来)
+    decode [名称|list|off] - 查看/选择协议解码器，收发记录下方显示解码字段 (如: decode frame:AA55:sum8)
+    frame list           - 列出配置目录下 frames.toml 中的帧模板
+    frame build|send <模板> [字段=值...]
+                         - 按模板组帧并显示/发送，长度与校验自动计算 (如: frame send sensor cmd=读取 data=0102)
+    frame decode <十六进制> - 按帧模板解析一段数据
+    repeat <ms> [-n 次数] [-j 抖动] [text|hex] <数据>
+                         - 定时重复发送 (如: repeat 500 hex 01 03 00 00 00 02)
+    repeat list          - 查看定时发送任务
+    repeat stop <编号|all> - 停止定时发送任务
     terminal, term       - 手动进入交互式终端模式
 
+  日志:
+    log start [选项]     - 开始记录收发日志 (默认使用配置中的 logging 设置)
+                           -j JSON Lines 抓包格式  -d 目录  -f 文件名模板 ({{port}} {{date}} {{time}})
+                           -s 按大小轮转KB  -r 按时间轮转分钟  -z 压缩旧文件  -x 记录 HEX
+    log stop             - 停止记录
+    log status           - 查看日志状态
+
+  历史:
+    history [条数]       - 查看最近的收发记录 (默认 20 条，含序号)
+    history -s <序号> [条数] - 从指定序号开始查看
+    history -t <起始> [结束] - 查看今天某时间段的记录 (HH:MM:SS)
+    history clear        - 清空历史
+    grep [-x] [-d rx|tx] [-n 条数] <模式>
+                         - 搜索历史，显示匹配位置 (-x 按十六进制搜索，?? 匹配任意字节)
+
+  触发规则:
+    trigger [list]       - 列出触发规则
+    trigger add <名称> [-x] <模式> <动作> [参数]
+                         - 接收数据匹配时执行动作 (-x 按十六进制匹配)
+                           动作: reply <文本> | replyhex <HEX> | mark <文本> | color <颜色>
+                                 logstart | logstop | dtr [毫秒] | script <文件>
+    trigger del <名称>   - 删除规则
+    trigger on|off <名称> - 启用/停用规则
+
+  Modbus 主站:
+    mb read <从站> <c|d|h|i> <地址> [数量]
+                         - 读线圈/离散输入/保持寄存器/输入寄存器 (功能码 01-04)
+                           -f u16|i16|u32|i32|f32 显示格式  -o abcd|cdab|badc|dcba 字节顺序
+    mb write <从站> <c|h> <地址> <值...>
+                         - 写线圈/寄存器，单个值用 05/06，多个值或 -m 用 0F/10
+    mb mode [rtu|ascii]  - 查看/设置模式
+    mb timeout <ms>      - 应答超时
+    mb retries <次数>    - 超时重试次数
+
+  Modbus 从站:
+    mbs start <寄存器表> --pty | -p <串口> [-b 波特率]
+                         - 后台模拟从站，-a 使用 ASCII，-l 记录每次交互
+    mbs show [c|d|h|i]   - 查看从站状态或寄存器内容
+    mbs set <c|d|h|i> <地址> <值...>
+                         - 修改寄存器，运行中立即生效
+    mbs save [文件]      - 保存寄存器表（默认覆盖加载的文件）
+    mbs stop             - 停止从站
+
+  Modbus 网关:
+    mbgw [地址:端口]     - 监听 Modbus TCP（默认 0.0.0.0:502），转发到当前串口，按 Esc 停止
+
+  数值通道:
+    plot csv[:名称,...]|kv|regex:<正则>
+                         - 从接收的文本行提取数值通道 (如: plot kv 解析 t=12.3,v=4.98)
+    plot off             - 停止提取
+    plot [show [点数]]   - 查看各通道统计与走势
+    plot watch           - 数值更新时实时显示，按 Esc 停止
+    plot clear           - 清除已提取的数据
+    plot export <文件>   - 导出为 CSV
+
+  AT 命令:
+    at                   - 进入 AT 模式，每行作为 AT 命令发送 (exit 退出)，Tab 补全
+    at <命令> [-t 超时ms] - 发送一条 AT 命令，等待 OK/ERROR/+CME ERROR 等结果码并成对显示应答，
+                           主动上报 (URC) 单独列出 (如: at AT+CSQ, at +CREG?)
+    at list              - 查看 AT 命令库及各命令的默认超时
+
+  GPS (NMEA 0183):
+    gps                  - 查看当前定位 (由接收的 GGA/RMC/GSA/GSV/VTG 语句汇总)
+    gps sats             - 查看可见卫星，* 为参与定位
+    gps watch            - 定位更新时实时显示，按 Esc 停止
+    gps reset            - 清除定位状态
+    gps send <命令>      - 发送 NMEA 格式命令，自动添加 $ 与 *校验 (如: gps send PMTK220,1000)
+    gps ubx <类> <ID> [负载] - 发送 UBX 命令，自动添加同步字、长度与校验 (如: gps ubx 0x06 0x08 E803 0100 0100)
+
+  回放:
+    replay <文件> [选项] - 按原始时间间隔回放抓包 (.jsonl) 或文本日志中的数据，按 Esc 停止
+                           -s 倍速 (如 2 或 0.5)  -l 循环  -d rx|tx 回放方向 (默认 rx)
+                           --pty 创建虚拟串口  -p 串口 [-b 波特率] 写入其他串口 (默认当前串口)
+
+  设备模拟:
+    sim <应答表> [--pty | -p 串口 [-b 波特率] | -t 地址:端口] [-u 文件]
+                         - 按应答表模拟设备，-u 记录未匹配的请求 (规则草稿)
+
+  脚本:
+    script run <文件>    - 运行 send/expect 脚本 (.xts)，按 Esc 停止
+    rhai run <文件>      - 运行 Rhai 脚本 (.rhai)，按 Esc 停止
+    rhai hook add <名称> <文件> - 注册接收钩子 (脚本定义 on_receive(text, bytes))
+    rhai hook del <名称> - 移除接收钩子
+    rhai hook list       - 查看接收钩子
+
+  文件传输 (传输中按 Esc 取消):
+    sx [-k] <文件>       - XMODEM 发送 (-k 使用 1K 块)
+    rx [-c] <文件>       - XMODEM 接收 (-c 使用校验和模式)
+    sy <文件...>         - YMODEM 批量发送 (如 U-Boot loady)
+    ry [目录]            - YMODEM 批量接收
+    sendfile <文件> [选项] - 分块/按行发送文件，按 Esc 取消 (终端模式下按 F2)
+                           -c 块大小  -d 块间隔ms  -l 按行发送  -L 行间隔ms
+                           -n crlf|lf|cr 替换换行  -e 等待回显  -w 等待提示符正则  -t 等待超时ms
+    sz <文件...>         - ZMODEM 发送 (对方已有部分文件时续传)
+    rz [目录]            - ZMODEM 接收
+                         ⚠️  终端模式下对方运行 sz/rz 时会自动提示传输
+
+  快捷命令:
+    run <名称>           - 发送快捷命令 (Tab 补全名称)
+    preset list [分组]   - 查看快捷命令
+    preset add <名称> [-g 分组] [text|hex] <数据>
+                         - 添加快捷命令
+    preset del <名称>    - 删除快捷命令
+    preset import <文件> - 从共享文件导入快捷命令
+    preset export <文件> [分组] - 导出快捷命令
+
   配置:
     config, cfg          - 查看/设置串口参数
 
@@ -594,6 +3176,7 @@ fn print_help() {
     Tab                  - 命令自动补全
     Ctrl+C               - 中断/退出
     Ctrl+]               - 退出终端模式 (重要!)
+    F2                   - 终端模式下发送文件
     ↑/↓                  - 浏览命令历史
 
 工作流程:
@@ -606,6 +3189,104 @@ fn print_help() {
     "#);
 }
 
+// ============ 命令行参数 ============
+
+#[derive(Parser)]
+#[command(name = "xtools_cli", version, about = "xTools 串口命令行工具")]
+struct CliArgs {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// 无界面运行 send/expect 脚本。返回码: 0 通过, 1 失败, 2 超时, 3 错误
+    Script {
+        /// 脚本文件
+        file: String,
+        /// 串口名称
+        #[arg(short, long)]
+        port: String,
+        /// 波特率
+        #[arg(short, long, default_value_t = 115200)]
+        baud: u32,
+    },
+}
+
+const EXIT_FAILED: i32 = 1;
+const EXIT_TIMED_OUT: i32 = 2;
+const EXIT_ERROR: i32 = 3;
+
+/// 命令行入口：无子命令时进入交互式 REPL，返回进程退出码
+pub fn run_cli() -> i32 {
+    let args = CliArgs::parse();
+
+    match args.command {
+        None => {
+            run_interactive_repl();
+            0
+        }
+        Some(CliCommand::Script { file, port, baud }) => run_script_headless(&file, &port, baud),
+    }
+}
+
+fn run_script_headless(file: &str, port: &str, baud: u32) -> i32 {
+    let script = match Script::load(file) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_ERROR;
+        }
+    };
+
+    let manager = Arc::new(Mutex::new(SerialManager::new()));
+    if let Err(e) = manager.lock().connect(port, baud, 8, 1, "none") {
+        eprintln!("连接失败: {}", e);
+        return EXIT_ERROR;
+    }
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancel_ctrlc = cancel.clone();
+    let _ = ctrlc::set_handler(move || {
+        cancel_ctrlc.store(true, Ordering::SeqCst);
+    });
+
+    // 接收线程：显示收发数据
+    let done = Arc::new(AtomicBool::new(false));
+    let done_rx = done.clone();
+    let manager_rx = manager.clone();
+    let rx_handle = thread::spawn(move || {
+        while !done_rx.load(Ordering::SeqCst) {
+            if let Ok(entries) = manager_rx.lock().read_available() {
+                for entry in entries {
+                    println!("{}", format_entry(&entry));
+                }
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    });
+
+    let outcome = script::run_script(&script, &manager, &cancel, print_step_report);
+
+    done.store(true, Ordering::SeqCst);
+    let _ = rx_handle.join();
+
+    match outcome.status {
+        StepStatus::Passed => {
+            println!("✓ {} (共 {} 步)", outcome.message, outcome.steps);
+            0
+        }
+        StepStatus::Failed => {
+            eprintln!("✗ {}", outcome.message);
+            EXIT_FAILED
+        }
+        StepStatus::TimedOut => {
+            eprintln!("✗ {}", outcome.message);
+            EXIT_TIMED_OUT
+        }
+    }
+}
+
 // ============ 旧版 CLI（兼容保留）============
 
 pub fn run_serial_cli(port: Option<String>, baud: u32, terminal_mode: bool) {
@@ -650,6 +3331,9 @@ pub fn run_serial_cli(port: Option<String>, baud: u32, terminal_mode: bool) {
 
     // 连接串口
     let mut manager = SerialManager::new();
+    if let Ok(config) = config::load_config() {
+        manager.set_timestamp_mode(TimestampMode::parse(&config.display.timestamp_mode).unwrap_or_default());
+    }
     if let Err(e) = manager.connect(&port_name, baud, 8, 1, "none") {
         eprintln!("连接失败: {}", e);
         return;
@@ -676,13 +3360,10 @@ pub fn run_serial_cli(port: Option<String>, baud: u32, terminal_mode: bool) {
         }
 
         // 读取数据
-        match manager.read_available() {
-            Ok(entries) => {
-                for entry in entries {
-                    println!("[{}] RX: {}", entry.timestamp, entry.data.trim());
-                }
+        if let Ok(entries) = manager.read_available() {
+            for entry in entries {
+                println!("{}", format_entry(&entry));
             }
-            Err(_) => {}
         }
 
         thread::sleep(Duration::from_millis(50));
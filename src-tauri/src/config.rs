@@ -1,3 +1,6 @@
+use crate::modbus::ModbusConfig;
+use crate::serial;
+use crate::trigger::TriggerRule;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -6,6 +9,14 @@ use std::path::PathBuf;
 pub struct AppConfig {
     pub serial: SerialConfig,
     pub display: DisplayConfig,
+    #[serde(default)]
+    pub presets: Vec<CommandPreset>,
+    #[serde(default)]
+    pub logging: LogConfig,
+    #[serde(default)]
+    pub triggers: Vec<TriggerRule>,
+    #[serde(default)]
+    pub modbus: ModbusConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,17 +31,116 @@ pub struct SerialConfig {
     pub hex_mode: bool,
     pub append_newline: bool,
     pub newline_type: String, // "crlf", "lf", "cr"
+    #[serde(default)]
+    pub rx_checksum: String, // "none", "sum8", "xor8", "crc8", "crc16_modbus", "crc16_ccitt", "crc32"
+    #[serde(default)]
+    pub framing: String, // "none", "slip", "cobs", "hdlc"
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DisplayConfig {
     pub auto_scroll: bool,
     pub show_timestamp: bool,
+    #[serde(default)]
+    pub timestamp_mode: String, // "datetime", "time", "delta", "elapsed"
     pub show_hex: bool,
     pub font_size: u32,
     pub terminal_mode: bool,
 }
 
+/// 命名快捷发送命令
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandPreset {
+    pub name: String,
+    pub payload: String,
+    pub hex_mode: bool,
+    #[serde(default)]
+    pub newline: String, // "none", "crlf", "lf", "cr"
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+/// 会话日志设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub format: String, // "text" 或 "jsonl"（JSON Lines 抓包）
+    pub dir: String, // 为空时使用配置目录下的 logs
+    pub filename: String, // 文件名模板，可用 {port} {date} {time}
+    pub max_size_kb: u64, // 超过后轮转，0 表示不按大小轮转
+    pub rotate_minutes: u64, // 0 表示不按时间轮转
+    pub gzip: bool, // 压缩轮转出的旧文件
+    pub include_hex: bool, // 仅文本格式
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: "text".to_string(),
+            dir: String::new(),
+            filename: "serial_{port}_{date}_{time}.log".to_string(),
+            max_size_kb: 0,
+            rotate_minutes: 0,
+            gzip: false,
+            include_hex: false,
+        }
+    }
+}
+
+impl CommandPreset {
+    /// 生成实际发送的字节（含换行）
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = if self.hex_mode {
+            serial::parse_hex_string(&self.payload)?
+        } else {
+            self.payload.as_bytes().to_vec()
+        };
+
+        match self.newline.as_str() {
+            "crlf" => bytes.extend_from_slice(b"\r\n"),
+            "lf" => bytes.push(b'\n'),
+            "cr" => bytes.push(b'\r'),
+            _ => {}
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl AppConfig {
+    pub fn find_preset(&self, name: &str) -> Option<&CommandPreset> {
+        self.presets.iter().find(|p| p.name == name)
+    }
+
+    /// 添加快捷命令，同名时覆盖
+    pub fn upsert_preset(&mut self, preset: CommandPreset) {
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    pub fn remove_preset(&mut self, name: &str) -> bool {
+        let len = self.presets.len();
+        self.presets.retain(|p| p.name != name);
+        self.presets.len() != len
+    }
+
+    /// 添加触发规则，同名时覆盖
+    pub fn upsert_trigger(&mut self, rule: TriggerRule) {
+        match self.triggers.iter_mut().find(|r| r.name == rule.name) {
+            Some(existing) => *existing = rule,
+            None => self.triggers.push(rule),
+        }
+    }
+
+    pub fn remove_trigger(&mut self, name: &str) -> bool {
+        let len = self.triggers.len();
+        self.triggers.retain(|r| r.name != name);
+        self.triggers.len() != len
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -44,19 +154,27 @@ impl Default for AppConfig {
                 hex_mode: false,
                 append_newline: true,
                 newline_type: "crlf".to_string(),
+                rx_checksum: "none".to_string(),
+                framing: "none".to_string(),
             },
             display: DisplayConfig {
                 auto_scroll: true,
                 show_timestamp: true,
+                timestamp_mode: "time".to_string(),
                 show_hex: false,
                 font_size: 14,
                 terminal_mode: false,
             },
+            presets: Vec::new(),
+            logging: LogConfig::default(),
+            triggers: Vec::new(),
+            modbus: ModbusConfig::default(),
         }
     }
 }
 
-fn get_config_path() -> PathBuf {
+/// 配置目录，config.json 与日志等默认存放于此
+pub fn config_dir() -> PathBuf {
     let config_dir = dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("xtools");
@@ -65,7 +183,11 @@ fn get_config_path() -> PathBuf {
         let _ = fs::create_dir_all(&config_dir);
     }
     
-    config_dir.join("config.json")
+    config_dir
+}
+
+fn get_config_path() -> PathBuf {
+    config_dir().join("config.json")
 }
 
 pub fn load_config() -> Result<AppConfig, String> {
@@ -91,3 +213,20 @@ pub fn save_config(config: &AppConfig) -> Result<(), String> {
     fs::write(&path, content)
         .map_err(|e| format!("保存配置失败: {}", e))
 }
+
+/// 从共享文件读取快捷命令（每个产品一份）
+pub fn load_presets(path: &str) -> Result<Vec<CommandPreset>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("读取快捷命令文件失败: {}", e))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| format!("解析快捷命令文件失败: {}", e))
+}
+
+pub fn save_presets(path: &str, presets: &[CommandPreset]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(presets)
+        .map_err(|e| format!("序列化快捷命令失败: {}", e))?;
+
+    fs::write(path, content)
+        .map_err(|e| format!("保存快捷命令文件失败: {}", e))
+}
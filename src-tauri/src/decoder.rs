@@ -0,0 +1,318 @@
+//! 协议解码：把一段收发数据解析为带名称的字段树，附加在 DataEntry 上供界面显示。
+//!
+//! 解码器实现 [`Decoder`]，内置解码器见 [`BUILTIN`]。自定义协议实现该 trait 后用 [`register`]
+//! 注册即可按名称选择，不需要修改 serial.rs。名称后可带参数，如 `frame:AA55:crc16_modbus`。
+
+use crate::checksum::{self, ChecksumAlgorithm};
+use crate::frames;
+use crate::modbus::{self, ModbusMode, ModbusRequest};
+use crate::serial;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Rx,
+    Tx,
+}
+
+/// 解码结果中的一个字段，可嵌套
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid: Option<bool>, // 校验类字段的结果
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Field>,
+}
+
+impl Field {
+    pub fn new(name: &str, value: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            valid: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// 以十六进制显示字节
+    pub fn bytes(name: &str, data: &[u8]) -> Self {
+        Self::new(name, serial::bytes_to_hex_string(data))
+    }
+
+    /// 校验字段，value 为收到的校验值
+    pub fn check(name: &str, data: &[u8], ok: bool) -> Self {
+        Self {
+            valid: Some(ok),
+            ..Self::bytes(name, data)
+        }
+    }
+
+    pub fn with_children(mut self, children: Vec<Field>) -> Self {
+        self.children = children;
+        self
+    }
+}
+
+/// 附加在 DataEntry 上的解码结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decoded {
+    pub decoder: String,
+    pub fields: Vec<Field>,
+}
+
+pub trait Decoder: Send {
+    /// 选择解码器时使用的完整名称（含参数）
+    fn name(&self) -> String;
+
+    /// 解析一段数据，不符合本协议时返回 None
+    fn decode(&self, data: &[u8], direction: Direction) -> Option<Vec<Field>>;
+}
+
+/// 按参数创建解码器，参数为名称中第一个 ':' 之后的部分
+pub type DecoderFactory = fn(args: &str) -> Result<Box<dyn Decoder>, String>;
+
+#[derive(Clone, Copy, Serialize)]
+pub struct DecoderInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    #[serde(skip)]
+    pub factory: DecoderFactory,
+}
+
+pub const BUILTIN: &[DecoderInfo] = &[
+    DecoderInfo {
+        name: "modbus_rtu",
+        description: "Modbus RTU，发送按请求、接收按应答解析",
+        factory: |_| Ok(Box::new(ModbusDecoder(ModbusMode::Rtu))),
+    },
+    DecoderInfo {
+        name: "modbus_ascii",
+        description: "Modbus ASCII，发送按请求、接收按应答解析",
+        factory: |_| Ok(Box::new(ModbusDecoder(ModbusMode::Ascii))),
+    },
+    DecoderInfo {
+        name: "tlv",
+        description: "类型(1) 长度(1) 值 的序列",
+        factory: |_| Ok(Box::new(TlvDecoder)),
+    },
+    DecoderInfo {
+        name: "frame",
+        description: "帧头 长度(1) 命令(1) 数据 校验，参数 frame:帧头:校验算法，如 frame:AA55:sum8",
+        factory: FrameDecoder::create,
+    },
+    DecoderInfo {
+        name: "template",
+        description: "按配置目录下 frames.toml 中的帧模板解析，template:名称 只使用指定模板",
+        factory: frames::TemplateDecoder::create,
+    },
+];
+
+// 运行时注册的解码器
+static REGISTRY: Mutex<Vec<DecoderInfo>> = Mutex::new(Vec::new());
+
+/// 注册自定义解码器，与已有名称相同时覆盖
+pub fn register(info: DecoderInfo) {
+    let mut registry = REGISTRY.lock();
+    registry.retain(|d| d.name != info.name);
+    registry.push(info);
+}
+
+/// 所有可选的解码器
+pub fn available() -> Vec<DecoderInfo> {
+    let registry = REGISTRY.lock();
+    BUILTIN
+        .iter()
+        .filter(|b| !registry.iter().any(|r| r.name == b.name))
+        .chain(registry.iter())
+        .copied()
+        .collect()
+}
+
+/// 按名称创建解码器，如 "modbus_rtu"、"frame:AA55:crc16_modbus"
+pub fn create(spec: &str) -> Result<Box<dyn Decoder>, String> {
+    let (name, args) = spec.split_once(':').unwrap_or((spec, ""));
+    let info = available()
+        .into_iter()
+        .find(|d| d.name == name)
+        .ok_or_else(|| format!("未知解码器: {}", name))?;
+    (info.factory)(args)
+}
+
+// ============ 内置解码器 ============
+
+struct ModbusDecoder(ModbusMode);
+
+impl Decoder for ModbusDecoder {
+    fn name(&self) -> String {
+        match self.0 {
+            ModbusMode::Rtu => "modbus_rtu".to_string(),
+            ModbusMode::Ascii => "modbus_ascii".to_string(),
+        }
+    }
+
+    fn decode(&self, data: &[u8], direction: Direction) -> Option<Vec<Field>> {
+        // 校验失败时仍按结构显示，只标记校验字段
+        let (adu, check, ok) = match self.0 {
+            ModbusMode::Rtu => {
+                if data.len() < 4 {
+                    return None;
+                }
+                let (adu, crc) = data.split_at(data.len() - 2);
+                (adu.to_vec(), crc.to_vec(), checksum::crc16_modbus(adu).to_le_bytes() == crc)
+            }
+            ModbusMode::Ascii => {
+                // 接收路径上可能混入任意字节，非 ASCII 的数据不是 Modbus ASCII 帧
+                if !data.is_ascii() {
+                    return None;
+                }
+                let text = std::str::from_utf8(data).ok()?.trim();
+                let mut adu = serial::parse_hex_string(text.strip_prefix(':')?).ok()?;
+                if adu.len() < 3 {
+                    return None;
+                }
+                let lrc = adu.pop()?;
+                let ok = checksum::lrc(&adu) == lrc;
+                (adu, vec![lrc], ok)
+            }
+        };
+
+        let (unit, pdu) = (adu[0], &adu[1..]);
+        let function = pdu[0];
+        let mut fields = vec![
+            Field::new("从站", unit),
+            Field::new("功能码", format!("{:02X}", function)),
+        ];
+
+        if function & 0x80 != 0 {
+            let code = pdu.get(1).copied().unwrap_or(0);
+            fields.push(Field::new("异常", format!("{:02X} {}", code, modbus::exception_name(code))));
+        } else if direction == Direction::Tx {
+            match ModbusRequest::from_pdu(pdu) {
+                Ok(request) => fields.push(Field::new("请求", request)),
+                Err(_) => fields.push(Field::bytes("数据", &pdu[1..])),
+            }
+        } else {
+            fields.extend(decode_modbus_response(pdu));
+        }
+
+        let check_name = if self.0 == ModbusMode::Rtu { "CRC" } else { "LRC" };
+        fields.push(Field::check(check_name, &check, ok));
+        Some(fields)
+    }
+}
+
+fn decode_modbus_response(pdu: &[u8]) -> Vec<Field> {
+    let data = &pdu[1..];
+    match pdu[0] {
+        0x01 | 0x02 if !data.is_empty() => {
+            let bits: Vec<String> = data[1..]
+                .iter()
+                .flat_map(|b| (0..8).map(move |i| ((b >> i) & 1).to_string()))
+                .collect();
+            vec![
+                Field::new("字节数", data[0]),
+                Field::new("状态", bits.join(" ")),
+            ]
+        }
+        0x03 | 0x04 if !data.is_empty() => {
+            let registers = data[1..]
+                .chunks(2)
+                .enumerate()
+                .map(|(i, c)| match c {
+                    [hi, lo] => {
+                        let value = u16::from_be_bytes([*hi, *lo]);
+                        Field::new(&format!("[{}]", i), format!("0x{:04X} {}", value, value))
+                    }
+                    _ => Field::bytes(&format!("[{}]", i), c),
+                })
+                .collect();
+            vec![
+                Field::new("字节数", data[0]),
+                Field::new("寄存器", (data.len() - 1) / 2).with_children(registers),
+            ]
+        }
+        0x05 | 0x06 | 0x0F | 0x10 if data.len() == 4 => vec![
+            Field::new("地址", u16::from_be_bytes([data[0], data[1]])),
+            Field::new("值/数量", u16::from_be_bytes([data[2], data[3]])),
+        ],
+        _ => vec![Field::bytes("数据", data)],
+    }
+}
+
+struct TlvDecoder;
+
+impl Decoder for TlvDecoder {
+    fn name(&self) -> String {
+        "tlv".to_string()
+    }
+
+    fn decode(&self, data: &[u8], _direction: Direction) -> Option<Vec<Field>> {
+        let mut fields = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            let (&tag, &len) = (rest.first()?, rest.get(1)?);
+            let value = rest.get(2..2 + len as usize)?;
+            fields.push(
+                Field::new("类型", format!("{:02X}", tag))
+                    .with_children(vec![Field::new("长度", len), Field::bytes("值", value)]),
+            );
+            rest = &rest[2 + len as usize..];
+        }
+        (!fields.is_empty()).then_some(fields)
+    }
+}
+
+/// 帧头 + 长度 + 命令 + 数据 + 校验，长度为命令与数据的字节数，校验覆盖校验值之前的全部内容
+struct FrameDecoder {
+    header: Vec<u8>,
+    checksum: ChecksumAlgorithm,
+}
+
+impl FrameDecoder {
+    fn create(args: &str) -> Result<Box<dyn Decoder>, String> {
+        let (header, algorithm) = args.split_once(':').unwrap_or((args, "none"));
+        let header = serial::parse_hex_string(header)?;
+        if header.is_empty() {
+            return Err("frame 解码器需要帧头，如 frame:AA55:sum8".to_string());
+        }
+        Ok(Box::new(FrameDecoder {
+            header,
+            checksum: ChecksumAlgorithm::parse(algorithm)?,
+        }))
+    }
+}
+
+impl Decoder for FrameDecoder {
+    fn name(&self) -> String {
+        format!(
+            "frame:{}:{}",
+            serial::bytes_to_hex_string(&self.header).replace(' ', ""),
+            self.checksum.name()
+        )
+    }
+
+    fn decode(&self, data: &[u8], _direction: Direction) -> Option<Vec<Field>> {
+        let body = data.strip_prefix(self.header.as_slice())?;
+        let len = *body.first()? as usize;
+        if len == 0 || body.len() != 1 + len + self.checksum.width() {
+            return None;
+        }
+        let (command, payload) = (body[1], &body[2..1 + len]);
+
+        let mut fields = vec![
+            Field::bytes("帧头", &self.header),
+            Field::new("长度", len),
+            Field::new("命令", format!("{:02X}", command)),
+            Field::bytes("数据", payload),
+        ];
+        if self.checksum != ChecksumAlgorithm::None {
+            let end = data.len() - self.checksum.width();
+            let ok = self.checksum.compute(&data[..end]) == data[end..];
+            fields.push(Field::check(self.checksum.name(), &data[end..], ok));
+        }
+        Some(fields)
+    }
+}
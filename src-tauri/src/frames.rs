@@ -0,0 +1,704 @@
+//! 帧模板：用 TOML 描述二进制帧格式，既用于解码接收数据，也用于按字段值组装发送帧。
+//!
+//! 模板保存在配置目录下的 frames.toml（与 config.json 同目录），例如：
+//!
+//! ```toml
+//! [[frame]]
+//! name = "sensor"
+//! sync = "AA 55"          # 同步字节
+//! endian = "big"          # 字段默认字节序，可在字段上单独指定
+//!
+//! [[frame.field]]
+//! name = "len"
+//! type = "u8"
+//! length_of = "cmd..data" # 长度字段：cmd 到 data 的字节数，可用 length_adjust 修正
+//!
+//! [[frame.field]]
+//! name = "cmd"
+//! type = "u8"
+//! enum = { 1 = "读取", 2 = "写入", 0x10 = "心跳" }
+//!
+//! [[frame.field]]
+//! name = "data"
+//! type = "bytes"          # 长度由 len 决定；也可用 length 固定长度
+//!
+//! [[frame.field]]
+//! name = "crc"
+//! type = "checksum"
+//! algorithm = "crc16_modbus"
+//! range = "len..data"     # 校验覆盖的字段，默认为同步字节之后、校验之前的全部内容
+//! ```
+//!
+//! 字段类型：u8 i8 u16 i16 u32 i32 f32 bytes string checksum。value 为字段的默认值或固定值，
+//! 长度字段与校验字段在组帧时自动计算。
+
+use crate::checksum::ChecksumAlgorithm;
+use crate::config;
+use crate::decoder::{Decoder, Direction, Field};
+use crate::serial;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FrameFile {
+    #[serde(default, rename = "frame")]
+    pub frames: Vec<FrameTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub sync: String, // 十六进制
+    #[serde(default)]
+    pub endian: Endian,
+    #[serde(default, rename = "field")]
+    pub fields: Vec<FieldSpec>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endian {
+    #[default]
+    Big,
+    Little,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    Bytes,
+    String,
+    Checksum,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endian: Option<Endian>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<usize>, // bytes / string 的固定长度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length_of: Option<String>, // "data" 或 "cmd..data"
+    #[serde(default)]
+    pub length_adjust: i64, // 长度字段的值 = 所指字段的字节数 + length_adjust
+    #[serde(default, rename = "enum", skip_serializing_if = "BTreeMap::is_empty")]
+    pub enum_values: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Scalar>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<String>, // 校验算法
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<String>, // 校验覆盖的字段
+}
+
+/// 模板中的字面值，TOML 中可写数字或字符串
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Scalar {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Int(v) => write!(f, "{}", v),
+            Scalar::Float(v) => write!(f, "{}", v),
+            Scalar::Text(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+pub fn templates_path() -> PathBuf {
+    config::config_dir().join("frames.toml")
+}
+
+/// 读取配置目录下的帧模板，文件不存在时为空
+pub fn load_templates() -> Result<Vec<FrameTemplate>, String> {
+    let path = templates_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("读取帧模板失败: {}", e))?;
+    parse_templates(&content)
+}
+
+pub fn parse_templates(content: &str) -> Result<Vec<FrameTemplate>, String> {
+    let file: FrameFile = toml::from_str(content).map_err(|e| format!("解析帧模板失败: {}", e))?;
+    for template in &file.frames {
+        template.validate()?;
+    }
+    Ok(file.frames)
+}
+
+pub fn find_template(name: &str) -> Result<FrameTemplate, String> {
+    load_templates()?
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| format!("未找到帧模板: {}", name))
+}
+
+impl FieldSpec {
+    fn checksum(&self) -> Option<ChecksumAlgorithm> {
+        match self.kind {
+            FieldType::Checksum => ChecksumAlgorithm::parse(self.algorithm.as_deref().unwrap_or("")).ok(),
+            _ => None,
+        }
+    }
+
+    // 固定长度字段的字节数，变长字段为 None
+    fn fixed_size(&self) -> Option<usize> {
+        match self.kind {
+            FieldType::U8 | FieldType::I8 => Some(1),
+            FieldType::U16 | FieldType::I16 => Some(2),
+            FieldType::U32 | FieldType::I32 | FieldType::F32 => Some(4),
+            FieldType::Bytes | FieldType::String => self.length,
+            FieldType::Checksum => self.checksum().map(|c| c.width()),
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        !matches!(self.kind, FieldType::Bytes | FieldType::String | FieldType::Checksum)
+    }
+
+    // 枚举值 → 名称
+    fn enum_name(&self, value: i64) -> Option<&str> {
+        self.enum_values
+            .iter()
+            .find(|(k, _)| parse_int(k) == Some(value))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl FrameTemplate {
+    fn validate(&self) -> Result<(), String> {
+        let err = |msg: String| format!("帧模板 {}: {}", self.name, msg);
+        serial::parse_hex_string(&self.sync).map_err(|e| err(format!("sync {}", e)))?;
+
+        for (i, field) in self.fields.iter().enumerate() {
+            if self.fields[..i].iter().any(|f| f.name == field.name) {
+                return Err(err(format!("字段 {} 重复", field.name)));
+            }
+            if field.length_of.is_some() && (!field.is_number() || field.kind == FieldType::F32) {
+                return Err(err(format!("长度字段 {} 必须是整数类型", field.name)));
+            }
+            if let Some(range) = &field.length_of {
+                self.range(range).map_err(err)?;
+            }
+            if field.kind == FieldType::Checksum {
+                let algorithm = field.algorithm.as_deref().unwrap_or("");
+                match ChecksumAlgorithm::parse(algorithm) {
+                    Ok(ChecksumAlgorithm::None) | Err(_) => {
+                        return Err(err(format!("校验字段 {} 的 algorithm 无效: {}", field.name, algorithm)))
+                    }
+                    Ok(_) => {}
+                }
+                if let Some(range) = &field.range {
+                    let (_, end) = self.range(range).map_err(err)?;
+                    if end >= i {
+                        return Err(err(format!("校验字段 {} 只能覆盖它之前的字段", field.name)));
+                    }
+                }
+            }
+            for key in field.enum_values.keys() {
+                if parse_int(key).is_none() {
+                    return Err(err(format!("字段 {} 的枚举值无效: {}", field.name, key)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // "a" 或 "a..b" 转为字段下标区间（含两端）
+    fn range(&self, spec: &str) -> Result<(usize, usize), String> {
+        let (first, last) = spec.split_once("..").unwrap_or((spec, spec));
+        let index = |name: &str| {
+            self.fields
+                .iter()
+                .position(|f| f.name == name.trim())
+                .ok_or_else(|| format!("字段 {} 不存在", name.trim()))
+        };
+        let (first, last) = (index(first)?, index(last)?);
+        if first > last {
+            return Err(format!("字段区间 {} 顺序错误", spec));
+        }
+        Ok((first, last))
+    }
+
+    fn field_endian(&self, field: &FieldSpec) -> Endian {
+        field.endian.unwrap_or(self.endian)
+    }
+
+    /// 按字段值组帧。values 中未给出的字段使用模板中的 value，长度与校验字段自动计算
+    pub fn build(&self, values: &HashMap<String, String>) -> Result<Vec<u8>, String> {
+        if let Some(name) = values.keys().find(|k| !self.fields.iter().any(|f| &f.name == *k)) {
+            return Err(format!("帧模板 {} 没有字段 {}", self.name, name));
+        }
+
+        let mut parts: Vec<Vec<u8>> = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let part = if field.length_of.is_some() || field.kind == FieldType::Checksum {
+                // 先占位，下面再计算
+                vec![0; field.fixed_size().unwrap_or(0)]
+            } else {
+                let text = match (values.get(&field.name), &field.value) {
+                    (Some(v), _) => v.clone(),
+                    (None, Some(v)) => v.to_string(),
+                    (None, None) if field.is_number() => return Err(format!("缺少字段 {} 的值", field.name)),
+                    (None, None) => String::new(),
+                };
+                self.encode_field(field, &text)?
+            };
+            parts.push(part);
+        }
+
+        for (i, field) in self.fields.iter().enumerate() {
+            if let Some(range) = &field.length_of {
+                let (first, last) = self.range(range)?;
+                let size: usize = parts[first..=last].iter().map(|p| p.len()).sum();
+                let value = size as i64 + field.length_adjust;
+                parts[i] = self.encode_field(field, &value.to_string())?;
+            }
+        }
+
+        let sync = serial::parse_hex_string(&self.sync)?;
+        for (i, field) in self.fields.iter().enumerate() {
+            if let Some(algorithm) = field.checksum() {
+                let covered = match &field.range {
+                    Some(range) => {
+                        let (first, last) = self.range(range)?;
+                        parts[first..=last].concat()
+                    }
+                    None => parts[..i].concat(),
+                };
+                parts[i] = algorithm.compute(&covered);
+            }
+        }
+
+        let mut frame = sync;
+        for part in parts {
+            frame.extend(part);
+        }
+        Ok(frame)
+    }
+
+    fn encode_field(&self, field: &FieldSpec, text: &str) -> Result<Vec<u8>, String> {
+        let text = text.trim();
+        let bad = || format!("字段 {} 的值无效: {}", field.name, text);
+        let endian = self.field_endian(field);
+
+        let mut bytes = match field.kind {
+            FieldType::Bytes => serial::parse_hex_string(text)?,
+            FieldType::String => text.as_bytes().to_vec(),
+            FieldType::Checksum => return Err(bad()),
+            FieldType::F32 => {
+                let v: f32 = text.parse().map_err(|_| bad())?;
+                match endian {
+                    Endian::Big => v.to_be_bytes().to_vec(),
+                    Endian::Little => v.to_le_bytes().to_vec(),
+                }
+            }
+            _ => {
+                // 枚举名称或数字
+                let value = field
+                    .enum_values
+                    .iter()
+                    .find(|(_, name)| name.as_str() == text)
+                    .and_then(|(k, _)| parse_int(k))
+                    .or_else(|| parse_int(text))
+                    .ok_or_else(bad)?;
+                let (size, min, max) = match field.kind {
+                    FieldType::U8 => (1, 0, u8::MAX as i64),
+                    FieldType::I8 => (1, i8::MIN as i64, i8::MAX as i64),
+                    FieldType::U16 => (2, 0, u16::MAX as i64),
+                    FieldType::I16 => (2, i16::MIN as i64, i16::MAX as i64),
+                    FieldType::U32 => (4, 0, u32::MAX as i64),
+                    _ => (4, i32::MIN as i64, i32::MAX as i64),
+                };
+                if value < min || value > max {
+                    return Err(format!("字段 {} 的值超出范围: {}", field.name, value));
+                }
+                let be = value.to_be_bytes()[8 - size..].to_vec();
+                match endian {
+                    Endian::Big => be,
+                    Endian::Little => be.into_iter().rev().collect(),
+                }
+            }
+        };
+
+        if let Some(length) = field.length {
+            if bytes.len() > length {
+                return Err(format!("字段 {} 超过 {} 字节", field.name, length));
+            }
+            bytes.resize(length, 0);
+        }
+        Ok(bytes)
+    }
+
+    /// 按模板解析一帧，数据不是完整的本帧时返回 None
+    pub fn decode(&self, data: &[u8]) -> Option<Vec<Field>> {
+        let sync = serial::parse_hex_string(&self.sync).ok()?;
+        let mut pos = sync.len();
+        if !data.starts_with(&sync) {
+            return None;
+        }
+
+        let mut spans: Vec<(usize, usize)> = Vec::with_capacity(self.fields.len());
+        let mut numbers: Vec<Option<i64>> = Vec::with_capacity(self.fields.len());
+        for (i, field) in self.fields.iter().enumerate() {
+            let size = match field.fixed_size() {
+                Some(size) => size,
+                None => self.variable_size(i, &numbers, data.len() - pos)?,
+            };
+            let raw = data.get(pos..pos + size)?;
+            numbers.push(match field.kind {
+                FieldType::F32 | FieldType::Bytes | FieldType::String | FieldType::Checksum => None,
+                _ => Some(self.read_int(field, raw)),
+            });
+            spans.push((pos, pos + size));
+            pos += size;
+        }
+        if pos != data.len() {
+            return None;
+        }
+
+        let mut fields = Vec::new();
+        if !sync.is_empty() {
+            fields.push(Field::bytes("同步", &sync));
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            let raw = &data[spans[i].0..spans[i].1];
+            let decoded = match field.kind {
+                FieldType::Bytes => Field::bytes(&field.name, raw),
+                FieldType::String => Field::new(&field.name, String::from_utf8_lossy(raw).trim_end_matches('\0')),
+                FieldType::F32 => {
+                    let bytes = [raw[0], raw[1], raw[2], raw[3]];
+                    let value = match self.field_endian(field) {
+                        Endian::Big => f32::from_be_bytes(bytes),
+                        Endian::Little => f32::from_le_bytes(bytes),
+                    };
+                    Field::new(&field.name, value)
+                }
+                FieldType::Checksum => {
+                    let algorithm = field.checksum()?;
+                    let start = match &field.range {
+                        Some(range) => spans[self.range(range).ok()?.0].0,
+                        None => sync.len(),
+                    };
+                    let end = match &field.range {
+                        Some(range) => spans[self.range(range).ok()?.1].1,
+                        None => spans[i].0,
+                    };
+                    Field::check(&field.name, raw, algorithm.compute(&data[start..end]) == raw)
+                }
+                _ => {
+                    let value = numbers[i].unwrap_or_default();
+                    match field.enum_name(value) {
+                        Some(name) => Field::new(&field.name, format!("{} ({})", value, name)),
+                        None => Field::new(&field.name, value),
+                    }
+                }
+            };
+            fields.push(decoded);
+        }
+        Some(fields)
+    }
+
+    // 变长字段的字节数：优先由前面的长度字段决定，否则为剩余数据减去后面的定长字段
+    fn variable_size(&self, index: usize, numbers: &[Option<i64>], remaining: usize) -> Option<usize> {
+        let length_field = self.fields[..index].iter().enumerate().find_map(|(j, f)| {
+            let (first, last) = self.range(f.length_of.as_deref()?).ok()?;
+            (first..=last).contains(&index).then_some((j, first, last))
+        });
+
+        match length_field {
+            Some((j, first, last)) => {
+                let total = numbers[j]? - self.fields[j].length_adjust;
+                let others = (first..=last)
+                    .filter(|&k| k != index)
+                    .map(|k| self.fields[k].fixed_size())
+                    .sum::<Option<usize>>()? as i64;
+                usize::try_from(total - others).ok()
+            }
+            None => {
+                let after = self.fields[index + 1..]
+                    .iter()
+                    .map(|f| f.fixed_size())
+                    .sum::<Option<usize>>()?;
+                remaining.checked_sub(after)
+            }
+        }
+    }
+
+    fn read_int(&self, field: &FieldSpec, raw: &[u8]) -> i64 {
+        let mut bytes = raw.to_vec();
+        if self.field_endian(field) == Endian::Little {
+            bytes.reverse();
+        }
+        let unsigned = bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+        match field.kind {
+            FieldType::I8 => unsigned as u8 as i8 as i64,
+            FieldType::I16 => unsigned as u16 as i16 as i64,
+            FieldType::I32 => unsigned as u32 as i32 as i64,
+            _ => unsigned as i64,
+        }
+    }
+}
+
+// 十进制（可为负）或 0x 开头的十六进制
+fn parse_int(s: &str) -> Option<i64> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// 按帧模板解码，可指定一个模板或尝试全部模板
+pub struct TemplateDecoder {
+    name: String,
+    templates: Vec<FrameTemplate>,
+}
+
+impl TemplateDecoder {
+    /// args 为模板名称，为空时使用全部模板
+    pub fn create(args: &str) -> Result<Box<dyn Decoder>, String> {
+        let templates = if args.is_empty() {
+            load_templates()?
+        } else {
+            vec![find_template(args)?]
+        };
+        if templates.is_empty() {
+            return Err(format!("没有帧模板，请在 {} 中定义", templates_path().display()));
+        }
+        let name = if args.is_empty() { "template".to_string() } else { format!("template:{}", args) };
+        Ok(Box::new(TemplateDecoder { name, templates }))
+    }
+}
+
+impl Decoder for TemplateDecoder {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn decode(&self, data: &[u8], _direction: Direction) -> Option<Vec<Field>> {
+        self.templates.iter().find_map(|t| {
+            let mut fields = t.decode(data)?;
+            fields.insert(0, Field::new("帧", &t.name));
+            Some(fields)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 模块文档中的示例
+    const SENSOR: &str = r#"
+[[frame]]
+name = "sensor"
+sync = "AA 55"
+endian = "big"
+
+[[frame.field]]
+name = "len"
+type = "u8"
+length_of = "cmd..data"
+
+[[frame.field]]
+name = "cmd"
+type = "u8"
+enum = { 1 = "读取", 2 = "写入", 0x10 = "心跳" }
+
+[[frame.field]]
+name = "data"
+type = "bytes"
+
+[[frame.field]]
+name = "crc"
+type = "checksum"
+algorithm = "crc16_modbus"
+range = "len..data"
+"#;
+
+    fn template(toml: &str) -> FrameTemplate {
+        parse_templates(toml).unwrap().remove(0)
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn checksum(algorithm: &str, data: &[u8]) -> Vec<u8> {
+        ChecksumAlgorithm::parse(algorithm).unwrap().compute(data)
+    }
+
+    fn summary(fields: &[Field]) -> Vec<(String, String, Option<bool>)> {
+        fields.iter().map(|f| (f.name.clone(), f.value.clone(), f.valid)).collect()
+    }
+
+    #[test]
+    fn build_and_decode_round_trip() {
+        let sensor = template(SENSOR);
+        let frame = sensor.build(&values(&[("cmd", "读取"), ("data", "01 02 03")])).unwrap();
+
+        let body = [0x04, 0x01, 0x01, 0x02, 0x03];
+        let mut expected = vec![0xAA, 0x55];
+        expected.extend_from_slice(&body);
+        expected.extend(checksum("crc16_modbus", &body));
+        assert_eq!(frame, expected);
+
+        let crc = serial::bytes_to_hex_string(&frame[7..]);
+        assert_eq!(
+            summary(&sensor.decode(&frame).unwrap()),
+            [
+                ("同步".to_string(), "AA 55".to_string(), None),
+                ("len".to_string(), "4".to_string(), None),
+                ("cmd".to_string(), "1 (读取)".to_string(), None),
+                ("data".to_string(), "01 02 03".to_string(), None),
+                ("crc".to_string(), crc, Some(true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_checksum_is_flagged() {
+        let sensor = template(SENSOR);
+        let mut frame = sensor.build(&values(&[("cmd", "2"), ("data", "")])).unwrap();
+        let last = frame.len() - 1;
+        frame[last] ^= 0xFF;
+        let fields = sensor.decode(&frame).unwrap();
+        assert_eq!(fields.last().unwrap().valid, Some(false));
+    }
+
+    #[test]
+    fn wrong_length_is_not_this_frame() {
+        let sensor = template(SENSOR);
+        let frame = sensor.build(&values(&[("cmd", "1"), ("data", "01 02 03")])).unwrap();
+        for len in [0x03, 0x05, 0x00] {
+            let mut bad = frame.clone();
+            bad[2] = len;
+            assert!(sensor.decode(&bad).is_none(), "len {}", len);
+        }
+        assert!(sensor.decode(&frame[..frame.len() - 1]).is_none());
+        assert!(sensor.decode(&frame[1..]).is_none());
+    }
+
+    #[test]
+    fn enum_names() {
+        let sensor = template(SENSOR);
+        let by_name = sensor.build(&values(&[("cmd", "心跳"), ("data", "FF")])).unwrap();
+        let by_value = sensor.build(&values(&[("cmd", "0x10"), ("data", "FF")])).unwrap();
+        assert_eq!(by_name, by_value);
+        assert_eq!(by_name[3], 0x10);
+        assert_eq!(sensor.decode(&by_name).unwrap()[2].value, "16 (心跳)");
+
+        let other = sensor.build(&values(&[("cmd", "7"), ("data", "")])).unwrap();
+        assert_eq!(sensor.decode(&other).unwrap()[2].value, "7");
+        assert!(sensor.build(&values(&[("cmd", "未知"), ("data", "")])).is_err());
+    }
+
+    #[test]
+    fn endianness_and_length_adjust() {
+        let le = template(
+            r#"
+[[frame]]
+name = "le"
+endian = "little"
+
+[[frame.field]]
+name = "id"
+type = "u16"
+
+[[frame.field]]
+name = "len"
+type = "u16"
+length_of = "text"
+length_adjust = 2
+
+[[frame.field]]
+name = "text"
+type = "string"
+
+[[frame.field]]
+name = "temp"
+type = "i16"
+endian = "big"
+
+[[frame.field]]
+name = "sum"
+type = "checksum"
+algorithm = "sum8"
+"#,
+        );
+        let frame = le.build(&values(&[("id", "0x1234"), ("text", "hi"), ("temp", "-2")])).unwrap();
+        let body = [0x34, 0x12, 0x04, 0x00, b'h', b'i', 0xFF, 0xFE];
+        let mut expected = body.to_vec();
+        expected.extend(checksum("sum8", &body));
+        assert_eq!(frame, expected);
+
+        let fields = summary(&le.decode(&frame).unwrap());
+        let values: Vec<&str> = fields.iter().map(|(_, v, _)| v.as_str()).collect();
+        assert_eq!(&values[..4], ["4660", "4", "hi", "-2"]);
+        assert_eq!(fields[4].2, Some(true));
+    }
+
+    #[test]
+    fn variable_size_without_length_field() {
+        let raw = template(
+            r#"
+[[frame]]
+name = "raw"
+sync = "7E"
+
+[[frame.field]]
+name = "data"
+type = "bytes"
+
+[[frame.field]]
+name = "crc"
+type = "checksum"
+algorithm = "crc8"
+"#,
+        );
+        let frame = raw.build(&values(&[("data", "01 02 03")])).unwrap();
+        assert_eq!(frame.len(), 5);
+        let fields = raw.decode(&frame).unwrap();
+        assert_eq!(fields[1].value, "01 02 03");
+        assert_eq!(fields[2].valid, Some(true));
+        assert!(raw.decode(&[0x7E]).is_none());
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        let field = |body: &str| format!("[[frame]]\nname = \"t\"\n{}", body);
+        for toml in [
+            field("sync = \"a中\""),
+            field("sync = \"A\""),
+            field("[[frame.field]]\nname = \"s\"\ntype = \"string\"\nlength_of = \"s\""),
+            field("[[frame.field]]\nname = \"c\"\ntype = \"checksum\"\nalgorithm = \"none\""),
+            field("[[frame.field]]\nname = \"a\"\ntype = \"u8\"\n[[frame.field]]\nname = \"c\"\ntype = \"checksum\"\nalgorithm = \"sum8\"\nrange = \"a..c\""),
+            field("[[frame.field]]\nname = \"a\"\ntype = \"u8\"\nenum = { x = \"y\" }"),
+            field("[[frame.field]]\nname = \"a\"\ntype = \"u8\"\n[[frame.field]]\nname = \"a\"\ntype = \"u8\""),
+        ] {
+            assert!(parse_templates(&toml).is_err(), "{}", toml);
+        }
+    }
+}
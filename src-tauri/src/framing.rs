@@ -0,0 +1,309 @@
+//! 分帧层：SLIP、COBS、HDLC 式字节填充。
+//!
+//! 接收时按分隔符切分并去除转义，每帧生成一条收发记录；十六进制发送时自动转义并加分隔符。
+
+use serde::{Deserialize, Serialize};
+
+/// 分帧方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Framing {
+    #[default]
+    None,
+    Slip, // RFC 1055，帧尾 C0，转义 DB DC / DB DD
+    Cobs, // 帧尾 00
+    Hdlc, // 帧首尾 7E，转义 7D 后异或 0x20，不含 FCS
+}
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+const HDLC_FLAG: u8 = 0x7E;
+const HDLC_ESC: u8 = 0x7D;
+
+// 一直收不到分隔符时，缓冲超过该长度即作为错误帧丢出
+const MAX_FRAME: usize = 64 * 1024;
+
+impl Framing {
+    pub const ALL: [Framing; 4] = [Framing::None, Framing::Slip, Framing::Cobs, Framing::Hdlc];
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "" | "none" | "off" => Ok(Framing::None),
+            "slip" => Ok(Framing::Slip),
+            "cobs" => Ok(Framing::Cobs),
+            "hdlc" => Ok(Framing::Hdlc),
+            _ => Err(format!("未知分帧方式: {}", s)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Framing::None => "none",
+            Framing::Slip => "slip",
+            Framing::Cobs => "cobs",
+            Framing::Hdlc => "hdlc",
+        }
+    }
+
+    /// 转义并加上分隔符，得到线路上发送的字节
+    pub fn encode(&self, payload: &[u8]) -> Vec<u8> {
+        match self {
+            Framing::None => payload.to_vec(),
+            Framing::Slip => {
+                let mut out = Vec::with_capacity(payload.len() + 2);
+                out.push(SLIP_END);
+                for &b in payload {
+                    match b {
+                        SLIP_END => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                        SLIP_ESC => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                        _ => out.push(b),
+                    }
+                }
+                out.push(SLIP_END);
+                out
+            }
+            Framing::Cobs => {
+                let mut out = cobs_encode(payload);
+                out.push(0);
+                out
+            }
+            Framing::Hdlc => {
+                let mut out = Vec::with_capacity(payload.len() + 2);
+                out.push(HDLC_FLAG);
+                for &b in payload {
+                    if b == HDLC_FLAG || b == HDLC_ESC {
+                        out.extend_from_slice(&[HDLC_ESC, b ^ 0x20]);
+                    } else {
+                        out.push(b);
+                    }
+                }
+                out.push(HDLC_FLAG);
+                out
+            }
+        }
+    }
+
+    // 去除一帧（不含分隔符）的转义，格式错误时返回 None
+    fn unescape(&self, frame: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Framing::None => Some(frame.to_vec()),
+            Framing::Slip => {
+                let mut out = Vec::with_capacity(frame.len());
+                let mut bytes = frame.iter();
+                while let Some(&b) = bytes.next() {
+                    if b != SLIP_ESC {
+                        out.push(b);
+                        continue;
+                    }
+                    match bytes.next() {
+                        Some(&SLIP_ESC_END) => out.push(SLIP_END),
+                        Some(&SLIP_ESC_ESC) => out.push(SLIP_ESC),
+                        _ => return None,
+                    }
+                }
+                Some(out)
+            }
+            Framing::Cobs => cobs_decode(frame),
+            Framing::Hdlc => {
+                let mut out = Vec::with_capacity(frame.len());
+                let mut bytes = frame.iter();
+                while let Some(&b) = bytes.next() {
+                    if b == HDLC_ESC {
+                        out.push(bytes.next()? ^ 0x20);
+                    } else {
+                        out.push(b);
+                    }
+                }
+                Some(out)
+            }
+        }
+    }
+
+    fn delimiter(&self) -> Option<u8> {
+        match self {
+            Framing::None => None,
+            Framing::Slip => Some(SLIP_END),
+            Framing::Cobs => Some(0),
+            Framing::Hdlc => Some(HDLC_FLAG),
+        }
+    }
+}
+
+/// 切分出的一帧
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub data: Vec<u8>, // 去除转义后的内容；格式错误时为原始字节
+    pub valid: bool,
+}
+
+/// 接收方向的分帧状态，跨多次读取累积不完整的帧
+#[derive(Debug, Default)]
+pub struct Deframer {
+    buffer: Vec<u8>,
+}
+
+impl Deframer {
+    /// 送入新读到的数据，返回其中完整的帧。连续分隔符之间的空帧忽略
+    pub fn feed(&mut self, framing: Framing, data: &[u8]) -> Vec<Frame> {
+        let Some(delimiter) = framing.delimiter() else {
+            return vec![Frame { data: data.to_vec(), valid: true }];
+        };
+
+        let mut frames = Vec::new();
+        for &b in data {
+            if b != delimiter {
+                self.buffer.push(b);
+                if self.buffer.len() >= MAX_FRAME {
+                    frames.push(Frame { data: std::mem::take(&mut self.buffer), valid: false });
+                }
+                continue;
+            }
+            if self.buffer.is_empty() {
+                continue;
+            }
+            let raw = std::mem::take(&mut self.buffer);
+            frames.push(match framing.unescape(&raw) {
+                Some(data) => Frame { data, valid: true },
+                None => Frame { data: raw, valid: false },
+            });
+        }
+        frames
+    }
+
+    /// 丢弃未完成的帧
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+fn cobs_encode(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + payload.len() / 254 + 2);
+    let mut code_pos = 0;
+    out.push(0);
+    let mut code = 1u8;
+    for (i, &b) in payload.iter().enumerate() {
+        if b != 0 {
+            out.push(b);
+            code += 1;
+        }
+        // 满 254 字节的块在数据末尾时不再另起一个空块
+        if b == 0 || (code == 0xFF && i + 1 < payload.len()) {
+            out[code_pos] = code;
+            code_pos = out.len();
+            out.push(0);
+            code = 1;
+        }
+    }
+    out[code_pos] = code;
+    out
+}
+
+fn cobs_decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(frame.len());
+    let mut i = 0;
+    while i < frame.len() {
+        let code = frame[i] as usize;
+        if code == 0 {
+            return None;
+        }
+        let block = frame.get(i + 1..i + code)?;
+        if block.contains(&0) {
+            return None;
+        }
+        out.extend_from_slice(block);
+        i += code;
+        // 0xFF 块后面不隐含 0，最后一块之后也没有
+        if code < 0xFF && i < frame.len() {
+            out.push(0);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deframe(framing: Framing, wire: &[u8]) -> Vec<Frame> {
+        Deframer::default().feed(framing, wire)
+    }
+
+    #[test]
+    fn cobs_block_boundaries() {
+        let run: Vec<u8> = (1..=0xFF).collect();
+
+        // 254 个非零字节正好一块
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&run[..254]);
+        assert_eq!(cobs_encode(&run[..254]), expected);
+
+        // 255 个非零字节跨两块
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&run[..254]);
+        expected.extend_from_slice(&[0x02, 0xFF]);
+        assert_eq!(cobs_encode(&run), expected);
+
+        // 开头的 0 后接 254 个非零字节
+        let mut payload = vec![0];
+        payload.extend_from_slice(&run[..254]);
+        let mut expected = vec![0x01, 0xFF];
+        expected.extend_from_slice(&run[..254]);
+        assert_eq!(cobs_encode(&payload), expected);
+
+        // 254 个非零字节后跟 0
+        let mut payload = run[1..].to_vec();
+        payload.push(0);
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&run[1..]);
+        expected.extend_from_slice(&[0x01, 0x01]);
+        assert_eq!(cobs_encode(&payload), expected);
+
+        for len in [0, 1, 253, 254, 255, 256, 508, 509, 600] {
+            for fill in [0u8, 1] {
+                let payload: Vec<u8> = (0..len).map(|i| if i % 300 == 7 { fill } else { (i % 255 + 1) as u8 }).collect();
+                let wire = Framing::Cobs.encode(&payload);
+                assert_eq!(wire.iter().filter(|&&b| b == 0).count(), 1);
+                assert_eq!(cobs_decode(&wire[..wire.len() - 1]), Some(payload));
+            }
+        }
+    }
+
+    #[test]
+    fn cobs_rejects_bad_frames() {
+        assert_eq!(cobs_decode(&[0x03, 0x11]), None);
+        assert_eq!(cobs_decode(&[0x02, 0x11, 0x00]), None);
+    }
+
+    #[test]
+    fn slip_round_trip() {
+        let payload = [0x01, SLIP_END, 0x02, SLIP_ESC, SLIP_ESC_END, SLIP_ESC_ESC];
+        let wire = Framing::Slip.encode(&payload);
+        assert_eq!(
+            wire,
+            [SLIP_END, 0x01, SLIP_ESC, SLIP_ESC_END, 0x02, SLIP_ESC, SLIP_ESC_ESC, SLIP_ESC_END, SLIP_ESC_ESC, SLIP_END]
+        );
+        assert_eq!(deframe(Framing::Slip, &wire), [Frame { data: payload.to_vec(), valid: true }]);
+
+        let bad = deframe(Framing::Slip, &[0x01, SLIP_ESC, 0x02, SLIP_END]);
+        assert_eq!(bad, [Frame { data: vec![0x01, SLIP_ESC, 0x02], valid: false }]);
+    }
+
+    #[test]
+    fn hdlc_round_trip() {
+        let payload = [0x7E, 0x01, 0x7D, 0x5E, 0x5D];
+        let wire = Framing::Hdlc.encode(&payload);
+        assert_eq!(wire, [0x7E, 0x7D, 0x5E, 0x01, 0x7D, 0x5D, 0x5E, 0x5D, 0x7E]);
+        assert_eq!(deframe(Framing::Hdlc, &wire), [Frame { data: payload.to_vec(), valid: true }]);
+    }
+
+    #[test]
+    fn frames_split_across_reads() {
+        let wire = Framing::Slip.encode(b"hello");
+        let mut deframer = Deframer::default();
+        assert!(deframer.feed(Framing::Slip, &wire[..3]).is_empty());
+        assert_eq!(deframer.feed(Framing::Slip, &wire[3..]), [Frame { data: b"hello".to_vec(), valid: true }]);
+    }
+}
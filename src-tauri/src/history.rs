@@ -0,0 +1,187 @@
+//! 会话历史：后端保留最近的收发记录（环形缓冲），支持按序号/时间分页与正则、HEX 搜索
+
+use crate::serial::DataEntry;
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub const DEFAULT_CAPACITY: usize = 10000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub seq: u64,
+    pub time_ms: i64, // Unix 毫秒时间戳，用于按时间查询
+    #[serde(flatten)]
+    pub entry: DataEntry,
+    #[serde(skip)]
+    raw: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    pub oldest_seq: u64, // 缓冲中最早的序号，更早的已被丢弃
+    pub next_seq: u64,   // 下一条记录将使用的序号
+}
+
+/// 搜索命中，start/end 为该条记录原始字节中的位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub seq: u64,
+    pub timestamp: String,
+    pub direction: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+pub struct SessionHistory {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+    next_seq: u64,
+}
+
+impl SessionHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+            next_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, entry: &DataEntry, raw: &[u8], time_ms: i64) {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry {
+            seq: self.next_seq,
+            time_ms,
+            entry: entry.clone(),
+            raw: raw.to_vec(),
+        });
+        self.next_seq += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn page(&self, entries: Vec<HistoryEntry>) -> HistoryPage {
+        HistoryPage {
+            entries,
+            oldest_seq: self.entries.front().map(|e| e.seq).unwrap_or(self.next_seq),
+            next_seq: self.next_seq,
+        }
+    }
+
+    /// 从 from_seq 开始取 limit 条；from_seq 为空时取最近 limit 条
+    pub fn page_by_seq(&self, from_seq: Option<u64>, limit: usize) -> HistoryPage {
+        let entries = match from_seq {
+            Some(from) => self
+                .entries
+                .iter()
+                .skip_while(|e| e.seq < from)
+                .take(limit)
+                .cloned()
+                .collect(),
+            None => self
+                .entries
+                .iter()
+                .skip(self.entries.len().saturating_sub(limit))
+                .cloned()
+                .collect(),
+        };
+        self.page(entries)
+    }
+
+    /// 取时间范围 [start_ms, end_ms] 内的前 limit 条
+    pub fn page_by_time(&self, start_ms: i64, end_ms: i64, limit: usize) -> HistoryPage {
+        let entries = self
+            .entries
+            .iter()
+            .filter(|e| e.time_ms >= start_ms && e.time_ms <= end_ms)
+            .take(limit)
+            .cloned()
+            .collect();
+        self.page(entries)
+    }
+
+    /// 搜索记录，direction 为空时搜索双向。不匹配跨记录的内容
+    pub fn search(&self, pattern: &Regex, direction: Option<&str>, limit: usize) -> Vec<SearchMatch> {
+        self.entries
+            .iter()
+            .filter(|e| direction.is_none() || direction == Some(e.entry.direction.as_str()))
+            .flat_map(|e| {
+                pattern.find_iter(&e.raw).map(move |m| SearchMatch {
+                    seq: e.seq,
+                    timestamp: e.entry.timestamp.clone(),
+                    direction: e.entry.direction.clone(),
+                    start: m.start(),
+                    end: m.end(),
+                })
+            })
+            .take(limit)
+            .collect()
+    }
+
+    pub fn get(&self, seq: u64) -> Option<&HistoryEntry> {
+        let first = self.entries.front()?.seq;
+        self.entries.get(seq.checked_sub(first)? as usize)
+    }
+}
+
+impl Default for SessionHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl HistoryEntry {
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+}
+
+/// 编译搜索模式。hex 为 true 时模式为十六进制字节序列，?? 匹配任意字节，
+/// 括号为捕获组，如 "01 03 (?? ??) 00"
+pub fn compile_pattern(pattern: &str, hex: bool) -> Result<Regex, String> {
+    if !hex {
+        return Regex::new(pattern).map_err(|e| format!("无效的正则: {}", e));
+    }
+
+    let compact: Vec<u8> = pattern.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if compact.is_empty() {
+        return Err("十六进制模式不能为空".to_string());
+    }
+
+    let mut re = String::from("(?s-u)");
+    let mut rest = compact.as_slice();
+    while !rest.is_empty() {
+        rest = match rest {
+            [b @ (b'(' | b')'), tail @ ..] => {
+                re.push(*b as char);
+                tail
+            }
+            [b'?', b'?', tail @ ..] => {
+                re.push('.');
+                tail
+            }
+            [hi, lo, tail @ ..] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                re.push_str("\\x");
+                re.push(*hi as char);
+                re.push(*lo as char);
+                tail
+            }
+            _ => return Err(format!("无效的十六进制模式: {}", pattern)),
+        };
+    }
+    Regex::new(&re).map_err(|e| format!("无效的模式: {}", e))
+}
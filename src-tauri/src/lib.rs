@@ -1,16 +1,73 @@
+pub mod at;
+pub mod capture;
+pub mod checksum;
 pub mod cli;
 pub mod serial;
 pub mod config;
+pub mod decoder;
+pub mod frames;
+pub mod framing;
+pub mod history;
+pub mod logging;
+pub mod modbus;
+pub mod modbus_gateway;
+pub mod modbus_slave;
+pub mod nmea;
+pub mod plot;
+pub mod rawsend;
+pub mod replay;
+pub mod scheduler;
+pub mod script;
+pub mod scripting;
+pub mod simulator;
+pub mod stats;
+pub mod timestamp;
+pub mod transfer;
+pub mod trigger;
+pub mod xmodem;
+pub mod zmodem;
 
+use capture::CaptureRecord;
+use checksum::ChecksumAlgorithm;
+use framing::Framing;
+use rawsend::RawSendOptions;
+use replay::{ReplayOptions, ReplaySink, ReplayTarget};
 use serial::SerialManager;
-use config::AppConfig;
+use config::{AppConfig, CommandPreset, LogConfig};
+use decoder::DecoderInfo;
+use frames::FrameTemplate;
+use history::{HistoryPage, SearchMatch};
+use logging::LogStatus;
+use modbus::{ModbusConfig, ModbusRequest, ModbusResponse, RegisterValue, ValueFormat, WordOrder};
+use modbus_slave::{RegisterMap, RegisterTable, SlaveOptions};
+use scheduler::{ScheduleSpec, ScheduledJob, Scheduler};
+use scripting::{HookInfo, LogFn, ScriptHooks};
+use simulator::{SimOptions, SimPort, SimTable, SimTarget};
+use timestamp::TimestampMode;
+use transfer::{SessionLink, TransferRequest};
+use trigger::TriggerRule;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use parking_lot::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
 pub struct AppState {
     pub serial_manager: Arc<Mutex<SerialManager>>,
     pub config: Arc<Mutex<AppConfig>>,
+    pub scheduler: Arc<Scheduler>,
+    pub script_cancel: Arc<AtomicBool>,
+    pub rhai_cancel: Arc<AtomicBool>,
+    pub script_hooks: Arc<ScriptHooks>,
+    pub transfer_cancel: Arc<AtomicBool>,
+    pub replay_cancel: Arc<AtomicBool>,
+    pub sim_cancel: Arc<AtomicBool>,
+    pub slave_cancel: Arc<AtomicBool>,
+    pub slave_map: Arc<Mutex<RegisterMap>>,
+    pub gateway_cancel: Arc<AtomicBool>,
+    pub at_busy: Arc<AtomicBool>, // AT 命令执行中，read_data 暂不取走数据
+    pub at_cancel: Arc<AtomicBool>,
 }
 
 // ============ Tauri Commands ============
@@ -29,12 +86,23 @@ fn connect_serial(
     stop_bits: u8,
     parity: String,
 ) -> Result<(), String> {
+    let (rx_checksum, framing) = {
+        let config = state.config.lock();
+        (
+            ChecksumAlgorithm::parse(&config.serial.rx_checksum).unwrap_or_default(),
+            Framing::parse(&config.serial.framing).unwrap_or_default(),
+        )
+    };
     let mut manager = state.serial_manager.lock();
-    manager.connect(&port, baud_rate, data_bits, stop_bits, &parity)
+    manager.connect(&port, baud_rate, data_bits, stop_bits, &parity)?;
+    manager.set_rx_checksum(rx_checksum);
+    manager.set_framing(framing);
+    Ok(())
 }
 
 #[tauri::command]
 fn disconnect_serial(state: State<AppState>) -> Result<(), String> {
+    state.scheduler.stop_all();
     let mut manager = state.serial_manager.lock();
     manager.disconnect()
 }
@@ -46,9 +114,64 @@ fn send_data(state: State<AppState>, data: String, hex_mode: bool) -> Result<(),
 }
 
 #[tauri::command]
-fn read_data(state: State<AppState>) -> Result<Vec<serial::DataEntry>, String> {
+fn read_data(app: AppHandle, state: State<AppState>) -> Result<Vec<serial::DataEntry>, String> {
+    // AT 命令的收发记录随 at_command 的结果返回
+    if state.at_busy.load(Ordering::SeqCst) {
+        return Ok(Vec::new());
+    }
     let mut manager = state.serial_manager.lock();
-    manager.read_available()
+    let entries = manager.read_available()?;
+
+    // 对端启动了 sz/rz，由前端提示用户选择文件或目录后调用 start_transfer
+    if let Some(request) = manager.take_zmodem_request() {
+        let _ = app.emit("zmodem-detected", request);
+    }
+
+    let hits = manager.take_trigger_hits();
+    drop(manager);
+    trigger::run_scripts(&hits, &state.serial_manager, &state.config, &rhai_logger(app.clone()));
+    for hit in hits {
+        let _ = app.emit("trigger-fired", hit);
+    }
+    Ok(entries)
+}
+
+#[tauri::command]
+fn set_rx_checksum(state: State<AppState>, algorithm: String) -> Result<(), String> {
+    let algorithm = ChecksumAlgorithm::parse(&algorithm)?;
+    state.serial_manager.lock().set_rx_checksum(algorithm);
+    let mut cfg = state.config.lock();
+    cfg.serial.rx_checksum = algorithm.name().to_string();
+    config::save_config(&cfg)
+}
+
+/// 本次连接（或上次重置）以来的流量统计
+#[tauri::command]
+fn get_stats(state: State<AppState>) -> stats::SessionStats {
+    state.serial_manager.lock().stats()
+}
+
+#[tauri::command]
+fn reset_stats(state: State<AppState>) {
+    state.serial_manager.lock().reset_stats();
+}
+
+#[tauri::command]
+fn set_framing(state: State<AppState>, framing: String) -> Result<(), String> {
+    let framing = Framing::parse(&framing)?;
+    state.serial_manager.lock().set_framing(framing);
+    let mut cfg = state.config.lock();
+    cfg.serial.framing = framing.name().to_string();
+    config::save_config(&cfg)
+}
+
+/// 设置时间戳显示方式 (datetime / time / delta / elapsed)，对之后的收发记录生效
+#[tauri::command]
+fn set_timestamp_mode(state: State<AppState>, mode: String) -> Result<(), String> {
+    let mode = TimestampMode::parse(&mode)?;
+    state.config.lock().display.timestamp_mode = mode.name().to_string();
+    state.serial_manager.lock().set_timestamp_mode(mode);
+    Ok(())
 }
 
 #[tauri::command]
@@ -57,6 +180,489 @@ fn is_connected(state: State<AppState>) -> bool {
     manager.is_connected()
 }
 
+#[tauri::command]
+fn start_schedule(state: State<AppState>, spec: ScheduleSpec) -> Result<u32, String> {
+    state.scheduler.start(state.serial_manager.clone(), spec)
+}
+
+#[tauri::command]
+fn stop_schedule(state: State<AppState>, id: Option<u32>) -> Result<(), String> {
+    match id {
+        Some(id) => state.scheduler.stop(id),
+        None => {
+            state.scheduler.stop_all();
+            Ok(())
+        }
+    }
+}
+
+#[tauri::command]
+fn list_schedules(state: State<AppState>) -> Vec<ScheduledJob> {
+    state.scheduler.list()
+}
+
+/// 后台运行 send/expect 脚本，通过 script-progress / script-finished 事件汇报进度
+#[tauri::command]
+fn run_script(app: AppHandle, state: State<AppState>, path: String) -> Result<(), String> {
+    let script = script::Script::load(&path)?;
+    let manager = state.serial_manager.clone();
+    let cancel = state.script_cancel.clone();
+    cancel.store(false, Ordering::SeqCst);
+
+    std::thread::spawn(move || {
+        let outcome = script::run_script(&script, &manager, &cancel, |report| {
+            let _ = app.emit("script-progress", report);
+        });
+        let _ = app.emit("script-finished", outcome);
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+fn stop_script(state: State<AppState>) {
+    state.script_cancel.store(true, Ordering::SeqCst);
+}
+
+// Rhai 脚本输出通过 rhai-log 事件发送到前端
+fn rhai_logger(app: AppHandle) -> LogFn {
+    Arc::new(move |msg: &str| {
+        let _ = app.emit("rhai-log", msg.to_string());
+    })
+}
+
+/// 后台运行 Rhai 脚本，结束时发送 rhai-finished 事件（失败时携带错误信息）
+#[tauri::command]
+fn run_rhai_script(app: AppHandle, state: State<AppState>, path: String) {
+    let manager = state.serial_manager.clone();
+    let config = state.config.clone();
+    let cancel = state.rhai_cancel.clone();
+    cancel.store(false, Ordering::SeqCst);
+
+    std::thread::spawn(move || {
+        let result = scripting::run_file(&path, manager, config, rhai_logger(app.clone()), cancel);
+        let _ = app.emit("rhai-finished", result.err());
+    });
+}
+
+#[tauri::command]
+fn stop_rhai_script(state: State<AppState>) {
+    state.rhai_cancel.store(true, Ordering::SeqCst);
+}
+
+#[tauri::command]
+fn add_script_hook(app: AppHandle, state: State<AppState>, name: String, path: String) -> Result<(), String> {
+    state.script_hooks.register(
+        &name,
+        &path,
+        state.serial_manager.clone(),
+        state.config.clone(),
+        rhai_logger(app),
+    )
+}
+
+#[tauri::command]
+fn remove_script_hook(state: State<AppState>, name: String) -> Result<(), String> {
+    if state.script_hooks.unregister(&name) {
+        Ok(())
+    } else {
+        Err(format!("接收钩子不存在: {}", name))
+    }
+}
+
+#[tauri::command]
+fn list_script_hooks(state: State<AppState>) -> Vec<HookInfo> {
+    state.script_hooks.list()
+}
+
+/// 后台执行文件传输，通过 transfer-progress / transfer-finished 事件汇报进度
+#[tauri::command]
+fn start_transfer(app: AppHandle, state: State<AppState>, request: TransferRequest) {
+    spawn_transfer(app, &state, request);
+}
+
+/// 分块/按行发送文件，进度与取消与文件传输共用
+#[tauri::command]
+fn send_file(app: AppHandle, state: State<AppState>, path: String, options: RawSendOptions) {
+    spawn_transfer(app, &state, TransferRequest::RawSend { path, options });
+}
+
+fn spawn_transfer(app: AppHandle, state: &AppState, request: TransferRequest) {
+    let manager = state.serial_manager.clone();
+    let cancel = state.transfer_cancel.clone();
+    cancel.store(false, Ordering::SeqCst);
+
+    std::thread::spawn(move || {
+        let result = transfer::run_transfer(&request, &manager, &cancel, &mut |progress| {
+            let _ = app.emit("transfer-progress", progress);
+        });
+        let _ = app.emit("transfer-finished", result.err());
+    });
+}
+
+/// 拒绝对端发起的 ZMODEM 传输
+#[tauri::command(async)]
+fn decline_zmodem(state: State<AppState>) -> Result<(), String> {
+    state.serial_manager.lock().write_raw(&zmodem::ABORT_SEQUENCE)?;
+    // 对端残留的 ZMODEM 帧不再触发提示
+    std::thread::sleep(Duration::from_millis(500));
+    let mut manager = state.serial_manager.lock();
+    manager.poll()?;
+    manager.take_zmodem_request();
+    Ok(())
+}
+
+#[tauri::command]
+fn cancel_transfer(state: State<AppState>) {
+    state.transfer_cancel.store(true, Ordering::SeqCst);
+}
+
+/// 按序号分页查询历史，from_seq 为空时返回最近 limit 条
+#[tauri::command]
+fn history_page(state: State<AppState>, from_seq: Option<u64>, limit: usize) -> HistoryPage {
+    state.serial_manager.lock().history().page_by_seq(from_seq, limit)
+}
+
+/// 按时间范围查询历史（Unix 毫秒）
+#[tauri::command]
+fn history_range(state: State<AppState>, start_ms: i64, end_ms: i64, limit: usize) -> HistoryPage {
+    state.serial_manager.lock().history().page_by_time(start_ms, end_ms, limit)
+}
+
+/// 搜索历史，hex 为 true 时 pattern 为十六进制字节序列（?? 匹配任意字节）
+#[tauri::command]
+fn history_search(
+    state: State<AppState>,
+    pattern: String,
+    hex: bool,
+    direction: Option<String>,
+    limit: usize,
+) -> Result<Vec<SearchMatch>, String> {
+    let regex = history::compile_pattern(&pattern, hex)?;
+    Ok(state
+        .serial_manager
+        .lock()
+        .history()
+        .search(&regex, direction.as_deref(), limit))
+}
+
+#[tauri::command]
+fn clear_history(state: State<AppState>) {
+    state.serial_manager.lock().history_mut().clear();
+}
+
+/// 开始回放，返回回放目标名称（虚拟串口时为上位机应打开的设备）。
+/// 进度通过 replay-progress / replay-finished 事件汇报
+#[tauri::command]
+fn start_replay(
+    app: AppHandle,
+    state: State<AppState>,
+    path: String,
+    target: ReplayTarget,
+    options: ReplayOptions,
+) -> Result<String, String> {
+    let frames = replay::load_frames(&path, &options.direction)?;
+    let mut sink = ReplaySink::open(&target, &state.serial_manager)?;
+    let name = sink.name().to_string();
+
+    let cancel = state.replay_cancel.clone();
+    cancel.store(false, Ordering::SeqCst);
+    std::thread::spawn(move || {
+        let result = replay::run_replay(&frames, &mut sink, &options, &cancel, |progress| {
+            let _ = app.emit("replay-progress", progress);
+        });
+        let _ = app.emit("replay-finished", result.err());
+    });
+    Ok(name)
+}
+
+#[tauri::command]
+fn stop_replay(state: State<AppState>) {
+    state.replay_cancel.store(true, Ordering::SeqCst);
+}
+
+/// Modbus 主站请求，未传入设置时使用配置中的 modbus。等待应答期间不阻塞界面
+#[tauri::command(async)]
+fn modbus_request(
+    state: State<'_, AppState>,
+    unit: u8,
+    request: ModbusRequest,
+    options: Option<ModbusConfig>,
+) -> Result<ModbusResponse, String> {
+    let options = options.unwrap_or_else(|| state.config.lock().modbus.clone());
+    let cancel = AtomicBool::new(false);
+    let mut link = SessionLink::new(&state.serial_manager, &cancel);
+    modbus::transact(&mut link, unit, &request, &options)
+}
+
+/// 设置数值通道解析方式：csv、csv:名称,...、kv、regex:正则，None 停止提取
+#[tauri::command]
+fn set_plot_parser(state: State<AppState>, spec: Option<String>) -> Result<(), String> {
+    let parser = spec.as_deref().map(plot::ChannelParser::parse).transpose()?;
+    state.serial_manager.lock().set_plot_parser(parser);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_plot_parser(state: State<AppState>) -> Option<String> {
+    state.serial_manager.lock().plot().parser_spec()
+}
+
+/// 各通道的时间序列，since 为毫秒时间戳时只返回之后的点
+#[tauri::command]
+fn get_plot_channels(state: State<AppState>, since: Option<i64>) -> Vec<plot::Channel> {
+    state.serial_manager.lock().plot().channels(since)
+}
+
+#[tauri::command]
+fn get_plot_summary(state: State<AppState>) -> Vec<plot::ChannelSummary> {
+    state.serial_manager.lock().plot().summaries()
+}
+
+#[tauri::command]
+fn clear_plot(state: State<AppState>) {
+    state.serial_manager.lock().plot_mut().clear();
+}
+
+/// 导出各通道数据为 CSV，返回数据行数
+#[tauri::command]
+fn export_plot_csv(state: State<AppState>, path: String) -> Result<usize, String> {
+    state.serial_manager.lock().plot().export_csv(&path)
+}
+
+/// 发送 AT 命令并等待结果码，未指定超时时按命令库设置。期间的收发记录在结果的 entries 中
+#[tauri::command(async)]
+fn at_command(state: State<'_, AppState>, command: String, timeout_ms: Option<u64>) -> Result<at::AtResponse, String> {
+    if state.at_busy.swap(true, Ordering::SeqCst) {
+        return Err("上一条 AT 命令尚未完成".to_string());
+    }
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or_else(|| at::timeout_for(&command));
+    state.at_cancel.store(false, Ordering::SeqCst);
+    let result = at::execute(&state.serial_manager, &command, timeout, &state.at_cancel);
+    state.at_busy.store(false, Ordering::SeqCst);
+    result
+}
+
+/// 放弃正在等待结果码的 AT 命令（如 AT+COPS=? 最长等待 180 秒）
+#[tauri::command]
+fn cancel_at_command(state: State<AppState>) {
+    state.at_cancel.store(true, Ordering::SeqCst);
+}
+
+/// AT 命令库，用于补全
+#[tauri::command]
+fn list_at_commands() -> Vec<at::AtCommandInfo> {
+    at::LIBRARY.to_vec()
+}
+
+/// 按格式解释寄存器值
+#[tauri::command]
+fn modbus_decode(registers: Vec<u16>, format: ValueFormat, order: WordOrder) -> Vec<RegisterValue> {
+    modbus::decode_registers(&registers, format, order)
+}
+
+/// 加载寄存器表并启动 Modbus 从站，返回服务端口名称。
+/// 每次交互通过 modbus-slave-event 事件汇报，结束时发送 modbus-slave-finished
+#[tauri::command]
+fn start_modbus_slave(
+    app: AppHandle,
+    state: State<AppState>,
+    path: String,
+    target: SimTarget,
+    options: SlaveOptions,
+) -> Result<String, String> {
+    let map = RegisterMap::load(&path)?;
+    let mut port = SimPort::open(&target)?;
+    let name = port.name().to_string();
+    *state.slave_map.lock() = map;
+
+    let map = state.slave_map.clone();
+    let cancel = state.slave_cancel.clone();
+    cancel.store(false, Ordering::SeqCst);
+    std::thread::spawn(move || {
+        let result = modbus_slave::run_slave(&map, &mut port, &options, &cancel, |event| {
+            let _ = app.emit("modbus-slave-event", event);
+        });
+        let _ = app.emit("modbus-slave-finished", result.err());
+    });
+    Ok(name)
+}
+
+#[tauri::command]
+fn stop_modbus_slave(state: State<AppState>) {
+    state.slave_cancel.store(true, Ordering::SeqCst);
+}
+
+#[tauri::command]
+fn get_modbus_slave_map(state: State<AppState>) -> RegisterMap {
+    state.slave_map.lock().clone()
+}
+
+/// 修改从站寄存器，运行中立即生效
+#[tauri::command]
+fn set_modbus_slave_values(
+    state: State<AppState>,
+    table: RegisterTable,
+    address: u16,
+    values: Vec<u16>,
+) -> Result<(), String> {
+    state.slave_map.lock().set(table, address, &values)
+}
+
+#[tauri::command]
+fn save_modbus_slave_map(state: State<AppState>, path: String) -> Result<(), String> {
+    state.slave_map.lock().save(&path)
+}
+
+/// 启动 Modbus TCP 网关，把请求转发到当前串口，返回实际监听地址。
+/// 转发的帧照常出现在收发记录中，每次请求另有 modbus-gateway-event 事件，结束时发送 modbus-gateway-finished
+#[tauri::command]
+fn start_modbus_gateway(app: AppHandle, state: State<AppState>, addr: String) -> Result<String, String> {
+    if !state.serial_manager.lock().is_connected() {
+        return Err("未连接到串口".to_string());
+    }
+    let listener = modbus_gateway::bind(&addr)?;
+    let local = listener.local_addr().map(|a| a.to_string()).unwrap_or(addr);
+
+    let manager = state.serial_manager.clone();
+    let config = state.config.lock().modbus.clone();
+    let cancel = state.gateway_cancel.clone();
+    cancel.store(false, Ordering::SeqCst);
+    std::thread::spawn(move || {
+        let result = modbus_gateway::run_gateway(&listener, &manager, &config, &cancel, |event| {
+            let _ = app.emit("modbus-gateway-event", event);
+        });
+        let _ = app.emit("modbus-gateway-finished", result.err());
+    });
+    Ok(local)
+}
+
+#[tauri::command]
+fn stop_modbus_gateway(state: State<AppState>) {
+    state.gateway_cancel.store(true, Ordering::SeqCst);
+}
+
+/// 按应答表启动设备模拟器，返回服务端口名称（虚拟串口路径或 TCP 地址）。
+/// 请求与未匹配的数据通过 sim-event 事件汇报，结束时发送 sim-finished
+#[tauri::command]
+fn start_simulator(
+    app: AppHandle,
+    state: State<AppState>,
+    path: String,
+    target: SimTarget,
+    options: SimOptions,
+) -> Result<String, String> {
+    let table = SimTable::load(&path)?;
+    simulator::Simulator::new(&table)?;
+    let mut port = SimPort::open(&target)?;
+    let name = port.name().to_string();
+
+    let cancel = state.sim_cancel.clone();
+    cancel.store(false, Ordering::SeqCst);
+    std::thread::spawn(move || {
+        let result = simulator::run_simulator(&table, &mut port, &options, &cancel, |event| {
+            let _ = app.emit("sim-event", event);
+        });
+        let _ = app.emit("sim-finished", result.err());
+    });
+    Ok(name)
+}
+
+#[tauri::command]
+fn stop_simulator(state: State<AppState>) {
+    state.sim_cancel.store(true, Ordering::SeqCst);
+}
+
+#[tauri::command]
+fn list_decoders() -> Vec<DecoderInfo> {
+    decoder::available()
+}
+
+/// 选择本次会话的协议解码器，如 "modbus_rtu"、"frame:AA55:sum8"，为空时关闭解码
+#[tauri::command]
+fn set_decoder(state: State<AppState>, name: Option<String>) -> Result<(), String> {
+    let decoder = match name.as_deref() {
+        None | Some("") => None,
+        Some(spec) => Some(decoder::create(spec)?),
+    };
+    state.serial_manager.lock().set_decoder(decoder);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_decoder(state: State<AppState>) -> Option<String> {
+    state.serial_manager.lock().decoder_name()
+}
+
+/// 读取配置目录下 frames.toml 中的帧模板
+#[tauri::command]
+fn list_frame_templates() -> Result<Vec<FrameTemplate>, String> {
+    frames::load_templates()
+}
+
+/// 按帧模板与字段值组帧，返回十六进制
+#[tauri::command]
+fn build_frame(name: String, values: HashMap<String, String>) -> Result<String, String> {
+    let frame = frames::find_template(&name)?.build(&values)?;
+    Ok(serial::bytes_to_hex_string(&frame))
+}
+
+#[tauri::command]
+fn send_frame(state: State<AppState>, name: String, values: HashMap<String, String>) -> Result<(), String> {
+    let frame = frames::find_template(&name)?.build(&values)?;
+    state.serial_manager.lock().send_bytes(&frame)
+}
+
+/// 接收数据中 NMEA 语句汇总出的当前定位状态
+#[tauri::command]
+fn get_gps_fix(state: State<AppState>) -> nmea::GpsFix {
+    state.serial_manager.lock().gps_fix().clone()
+}
+
+#[tauri::command]
+fn reset_gps_fix(state: State<AppState>) {
+    state.serial_manager.lock().reset_gps();
+}
+
+/// 发送 NMEA 格式命令（如 PMTK220,1000），自动添加 `$`、校验值与 CRLF
+#[tauri::command]
+fn send_nmea_command(state: State<AppState>, command: String) -> Result<(), String> {
+    let line = nmea::build_command(&command);
+    state.serial_manager.lock().send_bytes(line.as_bytes())
+}
+
+/// 发送 UBX 命令，负载为十六进制，自动添加同步字、长度与校验
+#[tauri::command]
+fn send_ubx_command(state: State<AppState>, class: u8, id: u8, payload: String) -> Result<(), String> {
+    let payload = serial::parse_hex_string(&payload)?;
+    state.serial_manager.lock().send_bytes(&nmea::build_ubx(class, id, &payload))
+}
+
+/// 开始记录会话日志，未传入设置时使用配置中的 logging，返回日志文件路径
+#[tauri::command]
+fn start_logging(state: State<AppState>, options: Option<LogConfig>) -> Result<String, String> {
+    let options = options.unwrap_or_else(|| state.config.lock().logging.clone());
+    state.serial_manager.lock().start_logging(&options)
+}
+
+#[tauri::command]
+fn stop_logging(state: State<AppState>) -> Result<Option<LogStatus>, String> {
+    state.serial_manager.lock().stop_logging().transpose()
+}
+
+#[tauri::command]
+fn logging_status(state: State<AppState>) -> Option<LogStatus> {
+    state.serial_manager.lock().logging_status()
+}
+
+/// 读取 JSON Lines 抓包文件
+#[tauri::command]
+fn read_capture(path: String) -> Result<Vec<CaptureRecord>, String> {
+    capture::read_capture(&path)
+}
+
 #[tauri::command]
 fn get_config(state: State<AppState>) -> AppConfig {
     state.config.lock().clone()
@@ -65,8 +671,113 @@ fn get_config(state: State<AppState>) -> AppConfig {
 #[tauri::command]
 fn save_config(state: State<AppState>, config: AppConfig) -> Result<(), String> {
     let mut cfg = state.config.lock();
-    *cfg = config.clone();
-    config::save_config(&config)
+    // 快捷命令、触发规则、日志与 Modbus 设置由专用命令维护，前端没有这些字段，避免被默认值覆盖
+    let presets = std::mem::take(&mut cfg.presets);
+    let triggers = std::mem::take(&mut cfg.triggers);
+    let logging = std::mem::take(&mut cfg.logging);
+    let modbus = std::mem::take(&mut cfg.modbus);
+    // 接收校验与分帧方式由 set_rx_checksum、set_framing 设置，前端保存的是启动时的旧值
+    let rx_checksum = std::mem::take(&mut cfg.serial.rx_checksum);
+    let framing = std::mem::take(&mut cfg.serial.framing);
+    *cfg = config;
+    cfg.serial.rx_checksum = rx_checksum;
+    cfg.serial.framing = framing;
+    cfg.presets = presets;
+    cfg.triggers = triggers;
+    cfg.logging = logging;
+    cfg.modbus = modbus;
+    let mut manager = state.serial_manager.lock();
+    manager.set_triggers(&cfg)?;
+    let mode = TimestampMode::parse(&cfg.display.timestamp_mode)?;
+    if mode != manager.timestamp_mode() {
+        manager.set_timestamp_mode(mode);
+    }
+    drop(manager);
+    config::save_config(&cfg)
+}
+
+#[tauri::command]
+fn list_triggers(state: State<AppState>) -> Vec<TriggerRule> {
+    state.config.lock().triggers.clone()
+}
+
+/// 添加或修改触发规则，立即生效
+#[tauri::command]
+fn save_trigger(state: State<AppState>, rule: TriggerRule) -> Result<(), String> {
+    let mut cfg = state.config.lock();
+    let mut updated = cfg.clone();
+    updated.upsert_trigger(rule);
+    state.serial_manager.lock().set_triggers(&updated)?;
+    *cfg = updated;
+    config::save_config(&cfg)
+}
+
+#[tauri::command]
+fn delete_trigger(state: State<AppState>, name: String) -> Result<(), String> {
+    let mut cfg = state.config.lock();
+    if !cfg.remove_trigger(&name) {
+        return Err(format!("触发规则不存在: {}", name));
+    }
+    state.serial_manager.lock().set_triggers(&cfg)?;
+    config::save_config(&cfg)
+}
+
+#[tauri::command]
+fn list_presets(state: State<AppState>) -> Vec<CommandPreset> {
+    state.config.lock().presets.clone()
+}
+
+#[tauri::command]
+fn save_preset(state: State<AppState>, preset: CommandPreset) -> Result<(), String> {
+    let mut cfg = state.config.lock();
+    cfg.upsert_preset(preset);
+    config::save_config(&cfg)
+}
+
+#[tauri::command]
+fn delete_preset(state: State<AppState>, name: String) -> Result<(), String> {
+    let mut cfg = state.config.lock();
+    if !cfg.remove_preset(&name) {
+        return Err(format!("快捷命令不存在: {}", name));
+    }
+    config::save_config(&cfg)
+}
+
+#[tauri::command]
+fn run_preset(state: State<AppState>, name: String) -> Result<(), String> {
+    let (bytes, hex) = {
+        let cfg = state.config.lock();
+        let preset = cfg
+            .find_preset(&name)
+            .ok_or_else(|| format!("快捷命令不存在: {}", name))?;
+        (preset.to_bytes()?, preset.hex_mode)
+    };
+    state.serial_manager.lock().send_payload(&bytes, hex)
+}
+
+#[tauri::command]
+fn import_presets(state: State<AppState>, path: String) -> Result<usize, String> {
+    let presets = config::load_presets(&path)?;
+    let count = presets.len();
+    let mut cfg = state.config.lock();
+    for preset in presets {
+        cfg.upsert_preset(preset);
+    }
+    config::save_config(&cfg)?;
+    Ok(count)
+}
+
+#[tauri::command]
+fn export_presets(state: State<AppState>, path: String, group: Option<String>) -> Result<usize, String> {
+    let cfg = state.config.lock();
+    let presets: Vec<CommandPreset> = cfg
+        .presets
+        .iter()
+        .filter(|p| group.is_none() || p.group == group)
+        .cloned()
+        .collect();
+    config::save_presets(&path, &presets)?;
+    Ok(presets.len())
 }
 
 #[tauri::command]
@@ -77,10 +788,27 @@ fn save_log(path: String, content: String) -> Result<(), String> {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let config = config::load_config().unwrap_or_default();
+    let mut manager = SerialManager::new();
+    if let Err(e) = manager.set_triggers(&config) {
+        eprintln!("{}", e);
+    }
+    manager.set_timestamp_mode(TimestampMode::parse(&config.display.timestamp_mode).unwrap_or_default());
     
     let state = AppState {
-        serial_manager: Arc::new(Mutex::new(SerialManager::new())),
+        serial_manager: Arc::new(Mutex::new(manager)),
         config: Arc::new(Mutex::new(config)),
+        scheduler: Arc::new(Scheduler::new()),
+        script_cancel: Arc::new(AtomicBool::new(false)),
+        rhai_cancel: Arc::new(AtomicBool::new(false)),
+        script_hooks: Arc::new(ScriptHooks::new()),
+        transfer_cancel: Arc::new(AtomicBool::new(false)),
+        replay_cancel: Arc::new(AtomicBool::new(false)),
+        sim_cancel: Arc::new(AtomicBool::new(false)),
+        slave_cancel: Arc::new(AtomicBool::new(false)),
+        slave_map: Arc::new(Mutex::new(RegisterMap::default())),
+        gateway_cancel: Arc::new(AtomicBool::new(false)),
+        at_busy: Arc::new(AtomicBool::new(false)),
+        at_cancel: Arc::new(AtomicBool::new(false)),
     };
 
     tauri::Builder::default()
@@ -93,9 +821,77 @@ pub fn run() {
             disconnect_serial,
             send_data,
             read_data,
+            set_rx_checksum,
+            set_framing,
+            set_timestamp_mode,
+            get_stats,
+            reset_stats,
             is_connected,
+            start_schedule,
+            stop_schedule,
+            list_schedules,
+            run_script,
+            stop_script,
+            run_rhai_script,
+            stop_rhai_script,
+            add_script_hook,
+            remove_script_hook,
+            list_script_hooks,
+            start_transfer,
+            send_file,
+            cancel_transfer,
+            decline_zmodem,
+            start_logging,
+            stop_logging,
+            logging_status,
+            read_capture,
+            history_page,
+            history_range,
+            history_search,
+            clear_history,
+            start_replay,
+            stop_replay,
+            modbus_request,
+            modbus_decode,
+            start_modbus_slave,
+            stop_modbus_slave,
+            get_modbus_slave_map,
+            set_modbus_slave_values,
+            save_modbus_slave_map,
+            start_modbus_gateway,
+            list_decoders,
+            set_decoder,
+            get_decoder,
+            list_frame_templates,
+            build_frame,
+            send_frame,
+            get_gps_fix,
+            reset_gps_fix,
+            send_nmea_command,
+            send_ubx_command,
+            at_command,
+            cancel_at_command,
+            list_at_commands,
+            set_plot_parser,
+            get_plot_parser,
+            get_plot_channels,
+            get_plot_summary,
+            clear_plot,
+            export_plot_csv,
+            stop_modbus_gateway,
+            start_simulator,
+            stop_simulator,
             get_config,
             save_config,
+            list_triggers,
+            save_trigger,
+            delete_trigger,
+            list_presets,
+            save_preset,
+            delete_preset,
+            run_preset,
+            import_presets,
+            export_presets,
             save_log,
         ])
         .run(tauri::generate_context!())
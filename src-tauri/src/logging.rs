@@ -0,0 +1,296 @@
+//! 会话日志：收发记录产生时立即追加写入文件，支持按大小/时间轮转与 gzip 压缩。
+//! 格式为文本或 JSON Lines 抓包（见 capture 模块）
+
+use crate::capture::{CaptureEvent, CaptureRecord};
+use crate::config::{self, LogConfig};
+use crate::serial::DataEntry;
+use crate::stats;
+use crate::timestamp::{self, TimestampMode};
+use chrono::{DateTime, Local};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// 日志状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogStatus {
+    pub path: String,
+    pub bytes: u64, // 当前文件已写入字节数
+    pub files: u32, // 本次记录产生的文件数（含轮转）
+    pub last_error: Option<String>,
+}
+
+pub struct SessionLogger {
+    config: LogConfig,
+    port: String,
+    session: String,
+    seq: u64,
+    path: PathBuf,
+    file: BufWriter<File>,
+    written: u64,
+    opened_at: DateTime<Local>,
+    files: u32,
+    last_error: Option<String>,
+    timestamp_mode: TimestampMode, // 文本日志中事件行的时间格式
+}
+
+impl SessionLogger {
+    pub fn start(config: &LogConfig, port: &str, session: &str) -> Result<Self, String> {
+        if !matches!(config.format.as_str(), "text" | "jsonl") {
+            return Err(format!("未知日志格式: {}", config.format));
+        }
+        let now = Local::now();
+        let (path, file) = open_log(config, port, now)?;
+
+        Ok(Self {
+            config: config.clone(),
+            port: port.to_string(),
+            session: session.to_string(),
+            seq: 0,
+            path,
+            file,
+            written: 0,
+            opened_at: now,
+            files: 1,
+            last_error: None,
+            timestamp_mode: TimestampMode::default(),
+        })
+    }
+
+    pub fn status(&self) -> LogStatus {
+        LogStatus {
+            path: self.path.to_string_lossy().to_string(),
+            bytes: self.written,
+            files: self.files,
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    pub fn set_timestamp_mode(&mut self, mode: TimestampMode) {
+        self.timestamp_mode = mode;
+    }
+
+    /// 追加一条收发记录并立即刷新，程序崩溃时也不丢失已写入内容
+    pub fn write_entry(&mut self, entry: &DataEntry) {
+        let line = if self.is_jsonl() {
+            self.json_line(CaptureEvent::from_entry(entry), timestamp::from_micros(entry.time_us))
+        } else {
+            Ok(self.text_entry(entry))
+        };
+        self.write_line(line);
+    }
+
+    /// 追加一条连接、设置或控制线事件
+    pub fn write_event(&mut self, event: CaptureEvent) {
+        let line = if self.is_jsonl() {
+            self.json_line(event, Local::now())
+        } else {
+            Ok(text_event(&event, self.timestamp_mode))
+        };
+        self.write_line(line);
+    }
+
+    fn is_jsonl(&self) -> bool {
+        self.config.format == "jsonl"
+    }
+
+    fn json_line(&mut self, event: CaptureEvent, time: DateTime<Local>) -> Result<String, String> {
+        let record = CaptureRecord::at(&self.session, self.seq, event, time);
+        self.seq += 1;
+        serde_json::to_string(&record).map_err(|e| format!("序列化记录失败: {}", e))
+    }
+
+    fn text_entry(&self, entry: &DataEntry) -> String {
+        let dir = if entry.direction == "tx" { "TX" } else { "RX" };
+        let mut line = format!("[{}] {}: {}", entry.timestamp, dir, escape_control(&entry.data));
+        if self.config.include_hex {
+            line.push_str(" | HEX: ");
+            line.push_str(&entry.hex);
+        }
+        if entry.valid == Some(false) {
+            line.push_str(" [校验错误]");
+        }
+        line
+    }
+
+    fn write_line(&mut self, line: Result<String, String>) {
+        if let Err(e) = line.and_then(|line| self.try_write(&line)) {
+            self.last_error = Some(e);
+        }
+    }
+
+    fn try_write(&mut self, line: &str) -> Result<(), String> {
+        if self.should_rotate() {
+            self.rotate()?;
+        }
+
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|_| self.file.write_all(b"\n"))
+            .and_then(|_| self.file.flush())
+            .map_err(|e| format!("写入日志失败: {}", e))?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn should_rotate(&self) -> bool {
+        if self.written == 0 {
+            return false;
+        }
+        let by_size = self.config.max_size_kb > 0 && self.written >= self.config.max_size_kb * 1024;
+        let by_time = self.config.rotate_minutes > 0
+            && (Local::now() - self.opened_at).num_minutes() >= self.config.rotate_minutes as i64;
+        by_size || by_time
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        let now = Local::now();
+        let (path, file) = open_log(&self.config, &self.port, now)?;
+
+        let _ = self.file.flush();
+        let old = std::mem::replace(&mut self.path, path);
+        self.file = file;
+        self.written = 0;
+        self.opened_at = now;
+        self.files += 1;
+
+        if self.config.gzip {
+            // 压缩可能较慢，放到后台避免阻塞接收
+            thread::spawn(move || {
+                let _ = gzip_file(&old);
+            });
+        }
+        Ok(())
+    }
+
+    /// 结束记录
+    pub fn finish(mut self) -> Result<LogStatus, String> {
+        self.file.flush().map_err(|e| format!("写入日志失败: {}", e))?;
+        Ok(self.status())
+    }
+}
+
+fn open_log(config: &LogConfig, port: &str, now: DateTime<Local>) -> Result<(PathBuf, BufWriter<File>), String> {
+    let dir = if config.dir.is_empty() {
+        config::config_dir().join("logs")
+    } else {
+        PathBuf::from(&config.dir)
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("创建日志目录失败: {}", e))?;
+
+    let name = expand_template(&config.filename, port, now);
+    let path = unique_path(&dir.join(name));
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("创建日志文件 {} 失败: {}", path.display(), e))?;
+
+    Ok((path, BufWriter::new(file)))
+}
+
+/// 展开文件名模板。{port} 只取串口名最后一段，如 /dev/ttyUSB0 → ttyUSB0
+pub fn expand_template(template: &str, port: &str, now: DateTime<Local>) -> String {
+    let port = Path::new(port)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .replace([':', '\\', '/'], "_");
+    let port = if port.is_empty() { "serial".to_string() } else { port };
+
+    template
+        .replace("{port}", &port)
+        .replace("{date}", &now.format("%Y%m%d").to_string())
+        .replace("{time}", &now.format("%H%M%S").to_string())
+}
+
+// 同名文件（或其压缩文件）已存在时（同一秒内轮转）追加序号
+fn unique_path(path: &Path) -> PathBuf {
+    let taken = |p: &Path| p.exists() || gz_path(p).exists();
+    if !taken(path) {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|i| path.with_file_name(format!("{}-{}{}", stem, i, ext)))
+        .find(|p| !taken(p))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// 压缩为 .gz 并删除原文件
+pub fn gzip_file(path: &Path) -> io::Result<PathBuf> {
+    let target = gz_path(path);
+
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&target)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+
+    fs::remove_file(path)?;
+    Ok(target)
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+// 事件不参与 delta / elapsed 计算，只区分是否带日期
+fn text_event(event: &CaptureEvent, mode: TimestampMode) -> String {
+    let mode = if mode == TimestampMode::DateTime { mode } else { TimestampMode::Time };
+    let ts = timestamp::format(mode, Local::now(), None, None);
+    let text = match event {
+        CaptureEvent::Data { direction, hex, .. } => format!("{}: {}", direction.to_uppercase(), hex),
+        CaptureEvent::Connect(s) => format!(
+            "连接 {} {} {}-{}-{}",
+            s.port, s.baud_rate, s.data_bits, s.parity, s.stop_bits
+        ),
+        CaptureEvent::Disconnect => "断开连接".to_string(),
+        CaptureEvent::Settings { key, value } => format!("设置 {} = {}", key, value),
+        CaptureEvent::ControlLine { line, level } => {
+            format!("{} = {}", line.to_uppercase(), if *level { 1 } else { 0 })
+        }
+        CaptureEvent::Marker { text } => format!("标记 {}", text),
+        CaptureEvent::Stats(s) => format!(
+            "统计 连接 {} | RX {} 字节 {} 条 | TX {} 字节 {} 条 | 峰值 RX {}/s TX {}/s | 校验错误 {} | 读取错误 {} | 丢弃 {} 条",
+            stats::format_duration(s.connected_secs),
+            s.rx_bytes,
+            s.rx_frames,
+            s.tx_bytes,
+            s.tx_frames,
+            stats::format_bytes(s.peak_rx_rate),
+            stats::format_bytes(s.peak_tx_rate),
+            s.bad_frames,
+            s.read_errors,
+            s.dropped_entries
+        ),
+    };
+    format!("[{}] -- {}", ts, text)
+}
+
+// 控制字符与反斜杠转义，保证一条记录占一行且能还原原始字节
+fn escape_control(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\r' => out.push_str("\\r"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\x{:02X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -1,3 +1,3 @@
 fn main() {
-    xtools_lib::cli::run_interactive_repl();
+    std::process::exit(xtools_lib::cli::run_cli());
 }
@@ -0,0 +1,650 @@
+//! Modbus RTU/ASCII 主站：组帧与校验、应答解析（含异常码）、超时重试以及寄存器值解码。
+//! 请求解析与组帧同样供从站模拟（modbus_slave.rs）使用
+
+use crate::checksum;
+use crate::serial;
+use crate::transfer::SessionLink;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModbusMode {
+    #[default]
+    Rtu,
+    Ascii,
+}
+
+impl ModbusMode {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "rtu" => Ok(ModbusMode::Rtu),
+            "ascii" => Ok(ModbusMode::Ascii),
+            _ => Err(format!("未知 Modbus 模式: {} (可选 rtu, ascii)", s)),
+        }
+    }
+}
+
+/// 主站设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModbusConfig {
+    pub mode: ModbusMode,
+    pub timeout_ms: u64, // 等待应答首字节的时间
+    pub retries: u32,    // 超时或校验错误时的重试次数，异常应答不重试
+}
+
+impl Default for ModbusConfig {
+    fn default() -> Self {
+        Self {
+            mode: ModbusMode::Rtu,
+            timeout_ms: 1000,
+            retries: 2,
+        }
+    }
+}
+
+/// 主站请求，对应功能码 01/02/03/04/05/06/0F/10
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "function", rename_all = "snake_case")]
+pub enum ModbusRequest {
+    ReadCoils { address: u16, count: u16 },
+    ReadDiscreteInputs { address: u16, count: u16 },
+    ReadHoldingRegisters { address: u16, count: u16 },
+    ReadInputRegisters { address: u16, count: u16 },
+    WriteSingleCoil { address: u16, value: bool },
+    WriteSingleRegister { address: u16, value: u16 },
+    WriteMultipleCoils { address: u16, values: Vec<bool> },
+    WriteMultipleRegisters { address: u16, values: Vec<u16> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ModbusResponse {
+    Bits { values: Vec<bool> },
+    Registers { values: Vec<u16> },
+    /// 写操作的确认，value 为单个写入的值或多个写入的数量
+    Written { address: u16, value: u16 },
+}
+
+// 应答解析失败的原因，异常应答不需要重试
+enum Failure {
+    Exception(u8),
+    Retry(String),
+}
+
+impl ModbusRequest {
+    pub fn function_code(&self) -> u8 {
+        match self {
+            ModbusRequest::ReadCoils { .. } => 0x01,
+            ModbusRequest::ReadDiscreteInputs { .. } => 0x02,
+            ModbusRequest::ReadHoldingRegisters { .. } => 0x03,
+            ModbusRequest::ReadInputRegisters { .. } => 0x04,
+            ModbusRequest::WriteSingleCoil { .. } => 0x05,
+            ModbusRequest::WriteSingleRegister { .. } => 0x06,
+            ModbusRequest::WriteMultipleCoils { .. } => 0x0F,
+            ModbusRequest::WriteMultipleRegisters { .. } => 0x10,
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.function_code() >= 0x05
+    }
+
+    /// 生成协议数据单元（功能码 + 数据），检查数量是否超出协议限制
+    pub fn to_pdu(&self) -> Result<Vec<u8>, String> {
+        let mut pdu = vec![self.function_code()];
+        match self {
+            ModbusRequest::ReadCoils { address, count } | ModbusRequest::ReadDiscreteInputs { address, count } => {
+                check_count(*count, 2000)?;
+                push_u16(&mut pdu, *address);
+                push_u16(&mut pdu, *count);
+            }
+            ModbusRequest::ReadHoldingRegisters { address, count }
+            | ModbusRequest::ReadInputRegisters { address, count } => {
+                check_count(*count, 125)?;
+                push_u16(&mut pdu, *address);
+                push_u16(&mut pdu, *count);
+            }
+            ModbusRequest::WriteSingleCoil { address, value } => {
+                push_u16(&mut pdu, *address);
+                push_u16(&mut pdu, if *value { 0xFF00 } else { 0x0000 });
+            }
+            ModbusRequest::WriteSingleRegister { address, value } => {
+                push_u16(&mut pdu, *address);
+                push_u16(&mut pdu, *value);
+            }
+            ModbusRequest::WriteMultipleCoils { address, values } => {
+                check_count(values.len() as u16, 1968)?;
+                let bytes = pack_bits(values);
+                push_u16(&mut pdu, *address);
+                push_u16(&mut pdu, values.len() as u16);
+                pdu.push(bytes.len() as u8);
+                pdu.extend(bytes);
+            }
+            ModbusRequest::WriteMultipleRegisters { address, values } => {
+                check_count(values.len() as u16, 123)?;
+                push_u16(&mut pdu, *address);
+                push_u16(&mut pdu, values.len() as u16);
+                pdu.push((values.len() * 2) as u8);
+                for v in values {
+                    push_u16(&mut pdu, *v);
+                }
+            }
+        }
+        Ok(pdu)
+    }
+
+    /// 从站侧解析请求 PDU，失败时返回应答的异常码
+    pub fn from_pdu(pdu: &[u8]) -> Result<Self, u8> {
+        let function = *pdu.first().ok_or(0x03u8)?;
+        if !matches!(function, 0x01..=0x06 | 0x0F | 0x10) {
+            return Err(0x01);
+        }
+        if pdu.len() < 5 {
+            return Err(0x03);
+        }
+        let address = u16::from_be_bytes([pdu[1], pdu[2]]);
+        let value = u16::from_be_bytes([pdu[3], pdu[4]]);
+        let valid = |max: u16| if value == 0 || value > max { Err(0x03) } else { Ok(value) };
+
+        let request = match function {
+            0x01 => ModbusRequest::ReadCoils { address, count: valid(2000)? },
+            0x02 => ModbusRequest::ReadDiscreteInputs { address, count: valid(2000)? },
+            0x03 => ModbusRequest::ReadHoldingRegisters { address, count: valid(125)? },
+            0x04 => ModbusRequest::ReadInputRegisters { address, count: valid(125)? },
+            0x05 => match value {
+                0xFF00 => ModbusRequest::WriteSingleCoil { address, value: true },
+                0x0000 => ModbusRequest::WriteSingleCoil { address, value: false },
+                _ => return Err(0x03),
+            },
+            0x06 => ModbusRequest::WriteSingleRegister { address, value },
+            _ => {
+                let count = valid(if function == 0x0F { 1968 } else { 123 })? as usize;
+                let data = pdu.get(6..).filter(|d| d.len() == pdu[5] as usize).ok_or(0x03u8)?;
+                if function == 0x0F {
+                    if data.len() != count.div_ceil(8) {
+                        return Err(0x03);
+                    }
+                    let values = (0..count).map(|i| data[i / 8] & (1 << (i % 8)) != 0).collect();
+                    ModbusRequest::WriteMultipleCoils { address, values }
+                } else {
+                    if data.len() != count * 2 {
+                        return Err(0x03);
+                    }
+                    let values = data.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                    ModbusRequest::WriteMultipleRegisters { address, values }
+                }
+            }
+        };
+        Ok(request)
+    }
+
+    fn parse_response(&self, pdu: &[u8]) -> Result<ModbusResponse, Failure> {
+        let function = self.function_code();
+        match pdu.first() {
+            Some(&f) if f == function | 0x80 => {
+                return Err(Failure::Exception(pdu.get(1).copied().unwrap_or(0)));
+            }
+            Some(&f) if f == function => {}
+            _ => return Err(Failure::Retry("应答功能码不符".to_string())),
+        }
+
+        let malformed = || Failure::Retry("应答长度错误".to_string());
+        match self {
+            ModbusRequest::ReadCoils { count, .. } | ModbusRequest::ReadDiscreteInputs { count, .. } => {
+                let data = pdu.get(2..).filter(|d| d.len() == pdu[1] as usize).ok_or_else(malformed)?;
+                if data.len() * 8 < *count as usize {
+                    return Err(malformed());
+                }
+                let values = (0..*count as usize).map(|i| data[i / 8] & (1 << (i % 8)) != 0).collect();
+                Ok(ModbusResponse::Bits { values })
+            }
+            ModbusRequest::ReadHoldingRegisters { count, .. } | ModbusRequest::ReadInputRegisters { count, .. } => {
+                let data = pdu.get(2..).filter(|d| d.len() == pdu[1] as usize).ok_or_else(malformed)?;
+                if data.len() != *count as usize * 2 {
+                    return Err(malformed());
+                }
+                let values = data.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                Ok(ModbusResponse::Registers { values })
+            }
+            _ => {
+                if pdu.len() != 5 {
+                    return Err(malformed());
+                }
+                Ok(ModbusResponse::Written {
+                    address: u16::from_be_bytes([pdu[1], pdu[2]]),
+                    value: u16::from_be_bytes([pdu[3], pdu[4]]),
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for ModbusRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModbusRequest::ReadCoils { address, count } => write!(f, "读线圈 {} 数量 {}", address, count),
+            ModbusRequest::ReadDiscreteInputs { address, count } => {
+                write!(f, "读离散输入 {} 数量 {}", address, count)
+            }
+            ModbusRequest::ReadHoldingRegisters { address, count } => {
+                write!(f, "读保持寄存器 {} 数量 {}", address, count)
+            }
+            ModbusRequest::ReadInputRegisters { address, count } => {
+                write!(f, "读输入寄存器 {} 数量 {}", address, count)
+            }
+            ModbusRequest::WriteSingleCoil { address, value } => {
+                write!(f, "写线圈 {} = {}", address, *value as u8)
+            }
+            ModbusRequest::WriteSingleRegister { address, value } => {
+                write!(f, "写寄存器 {} = {}", address, value)
+            }
+            ModbusRequest::WriteMultipleCoils { address, values } => {
+                write!(f, "写线圈 {} 数量 {}", address, values.len())
+            }
+            ModbusRequest::WriteMultipleRegisters { address, values } => {
+                write!(f, "写寄存器 {} 数量 {}", address, values.len())
+            }
+        }
+    }
+}
+
+fn check_count(count: u16, max: u16) -> Result<(), String> {
+    if count == 0 || count > max {
+        return Err(format!("数量必须在 1..={} 之间", max));
+    }
+    Ok(())
+}
+
+fn push_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+/// 线圈按位打包，低位在前
+pub fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &b)| if b { acc | (1 << i) } else { acc })
+        })
+        .collect()
+}
+
+pub fn exception_name(code: u8) -> &'static str {
+    match code {
+        0x01 => "非法功能",
+        0x02 => "非法数据地址",
+        0x03 => "非法数据值",
+        0x04 => "从站设备故障",
+        0x05 => "确认",
+        0x06 => "从站设备忙",
+        0x08 => "存储奇偶性差错",
+        0x0A => "网关路径不可用",
+        0x0B => "网关目标设备响应失败",
+        _ => "未知异常",
+    }
+}
+
+/// 组帧：RTU 为 地址 + PDU + CRC（低字节在前），ASCII 为 ':' + 十六进制(地址 + PDU + LRC) + CRLF
+pub fn encode_frame(mode: ModbusMode, unit: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut adu = Vec::with_capacity(pdu.len() + 3);
+    adu.push(unit);
+    adu.extend_from_slice(pdu);
+
+    match mode {
+        ModbusMode::Rtu => {
+            let crc = checksum::crc16_modbus(&adu);
+            adu.extend_from_slice(&crc.to_le_bytes());
+            adu
+        }
+        ModbusMode::Ascii => {
+            adu.push(checksum::lrc(&adu));
+            let mut frame = vec![b':'];
+            for b in adu {
+                frame.extend_from_slice(format!("{:02X}", b).as_bytes());
+            }
+            frame.extend_from_slice(b"\r\n");
+            frame
+        }
+    }
+}
+
+/// 解帧并校验，返回 (从站地址, PDU)
+pub fn decode_frame(mode: ModbusMode, frame: &[u8]) -> Result<(u8, Vec<u8>), String> {
+    let adu = match mode {
+        ModbusMode::Rtu => {
+            if frame.len() < 4 {
+                return Err("帧过短".to_string());
+            }
+            let (body, crc) = frame.split_at(frame.len() - 2);
+            if checksum::crc16_modbus(body).to_le_bytes() != crc {
+                return Err("CRC 校验错误".to_string());
+            }
+            body.to_vec()
+        }
+        ModbusMode::Ascii => {
+            let text = String::from_utf8_lossy(frame);
+            let hex = text
+                .trim()
+                .strip_prefix(':')
+                .ok_or("ASCII 帧缺少起始符 ':'")?;
+            let mut adu = serial::parse_hex_string(hex)?;
+            if adu.len() < 3 {
+                return Err("帧过短".to_string());
+            }
+            let lrc = adu.pop().unwrap_or_default();
+            if checksum::lrc(&adu) != lrc {
+                return Err("LRC 校验错误".to_string());
+            }
+            adu
+        }
+    };
+    Ok((adu[0], adu[1..].to_vec()))
+}
+
+// 应答内字节间的最大间隔
+const INTER_BYTE_TIMEOUT: Duration = Duration::from_millis(100);
+
+// 读取一个 RTU 应答帧，按功能码确定长度
+fn read_rtu(link: &mut SessionLink, timeout: Duration) -> Result<Option<Vec<u8>>, String> {
+    let first = match link.read_byte(timeout)? {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+    let mut frame = vec![first];
+    let function = match link.read_byte(INTER_BYTE_TIMEOUT)? {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+    frame.push(function);
+
+    let remaining = match function {
+        f if f & 0x80 != 0 => 3,
+        0x01..=0x04 => match link.read_byte(INTER_BYTE_TIMEOUT)? {
+            Some(n) => {
+                frame.push(n);
+                n as usize + 2
+            }
+            None => return Ok(None),
+        },
+        0x05 | 0x06 | 0x0F | 0x10 => 6,
+        _ => {
+            // 其他功能码无法确定长度，读到线路空闲为止（网关透传时用到）
+            while let Some(b) = link.read_byte(INTER_BYTE_TIMEOUT)? {
+                frame.push(b);
+                if frame.len() > 256 {
+                    link.purge(INTER_BYTE_TIMEOUT)?;
+                    return Err("RTU 帧过长".to_string());
+                }
+            }
+            return Ok(Some(frame));
+        }
+    };
+
+    match link.read_exact(remaining, INTER_BYTE_TIMEOUT)? {
+        Some(rest) => {
+            frame.extend(rest);
+            Ok(Some(frame))
+        }
+        None => Ok(None),
+    }
+}
+
+// 读取一个 ASCII 应答帧（':' 到 LF）
+fn read_ascii(link: &mut SessionLink, timeout: Duration) -> Result<Option<Vec<u8>>, String> {
+    loop {
+        match link.read_byte(timeout)? {
+            Some(b':') => break,
+            Some(_) => continue,
+            None => return Ok(None),
+        }
+    }
+
+    let mut frame = vec![b':'];
+    while frame.len() < 513 {
+        match link.read_byte(INTER_BYTE_TIMEOUT.max(timeout))? {
+            Some(b'\n') => {
+                frame.push(b'\n');
+                return Ok(Some(frame));
+            }
+            Some(b) => frame.push(b),
+            None => return Ok(None),
+        }
+    }
+    Err("ASCII 帧过长".to_string())
+}
+
+/// 执行一次主站请求。从站地址 0 为广播，只能用于写操作且没有应答
+pub fn transact(
+    link: &mut SessionLink,
+    unit: u8,
+    request: &ModbusRequest,
+    config: &ModbusConfig,
+) -> Result<ModbusResponse, String> {
+    if unit == 0 && !request.is_write() {
+        return Err("广播地址只能用于写操作".to_string());
+    }
+    let response = exchange(link, unit, &request.to_pdu()?, config, |pdu| request.parse_response(pdu))?;
+    Ok(response.unwrap_or(ModbusResponse::Written { address: 0, value: 0 }))
+}
+
+/// 透传一个请求 PDU，返回从站应答的 PDU（可能是异常应答），广播时返回 None
+pub fn transact_pdu(
+    link: &mut SessionLink,
+    unit: u8,
+    pdu: &[u8],
+    config: &ModbusConfig,
+) -> Result<Option<Vec<u8>>, String> {
+    let function = *pdu.first().ok_or("请求为空")?;
+    exchange(link, unit, pdu, config, |reply| match reply.first() {
+        Some(&f) if f & 0x7F == function => Ok(reply.to_vec()),
+        _ => Err(Failure::Retry("应答功能码不符".to_string())),
+    })
+}
+
+// 发送请求并等待应答，超时、帧错误或应答格式不对时重试，异常应答直接返回
+fn exchange<T>(
+    link: &mut SessionLink,
+    unit: u8,
+    pdu: &[u8],
+    config: &ModbusConfig,
+    parse: impl Fn(&[u8]) -> Result<T, Failure>,
+) -> Result<Option<T>, String> {
+    let frame = encode_frame(config.mode, unit, pdu);
+    let timeout = Duration::from_millis(config.timeout_ms.max(1));
+
+    let mut last_error = String::new();
+    for _ in 0..=config.retries {
+        // 丢弃上次超时后残留的半帧
+        link.purge(Duration::ZERO)?;
+        link.send(&frame)?;
+        if unit == 0 {
+            return Ok(None);
+        }
+
+        let reply = match config.mode {
+            ModbusMode::Rtu => read_rtu(link, timeout),
+            ModbusMode::Ascii => read_ascii(link, timeout),
+        };
+        let reply = match reply {
+            Ok(Some(reply)) => reply,
+            Ok(None) => {
+                last_error = "等待应答超时".to_string();
+                continue;
+            }
+            Err(e) => {
+                last_error = e;
+                continue;
+            }
+        };
+
+        let (from, reply_pdu) = match decode_frame(config.mode, &reply) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                last_error = e;
+                continue;
+            }
+        };
+        if from != unit {
+            last_error = format!("应答来自从站 {}，期望 {}", from, unit);
+            continue;
+        }
+
+        match parse(&reply_pdu) {
+            Ok(response) => return Ok(Some(response)),
+            Err(Failure::Exception(code)) => {
+                return Err(format!("从站返回异常 {:02X}: {}", code, exception_name(code)))
+            }
+            Err(Failure::Retry(e)) => last_error = e,
+        }
+    }
+
+    Err(format!("{} (已重试 {} 次)", last_error, config.retries))
+}
+
+/// 寄存器值的解释方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueFormat {
+    #[default]
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+}
+
+/// 多寄存器值的字节顺序，A 为第一个寄存器的高字节
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordOrder {
+    #[default]
+    Abcd, // 大端
+    Cdab, // 字交换
+    Badc, // 字节交换
+    Dcba, // 小端
+}
+
+impl ValueFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "u16" => Ok(ValueFormat::U16),
+            "i16" => Ok(ValueFormat::I16),
+            "u32" => Ok(ValueFormat::U32),
+            "i32" => Ok(ValueFormat::I32),
+            "f32" | "float" => Ok(ValueFormat::F32),
+            _ => Err(format!("未知格式: {} (可选 u16, i16, u32, i32, f32)", s)),
+        }
+    }
+
+    /// 每个值占用的寄存器数
+    pub fn width(&self) -> usize {
+        match self {
+            ValueFormat::U16 | ValueFormat::I16 => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl WordOrder {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "abcd" => Ok(WordOrder::Abcd),
+            "cdab" => Ok(WordOrder::Cdab),
+            "badc" => Ok(WordOrder::Badc),
+            "dcba" => Ok(WordOrder::Dcba),
+            _ => Err(format!("未知字节顺序: {} (可选 abcd, cdab, badc, dcba)", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum RegisterValue {
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+}
+
+impl fmt::Display for RegisterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterValue::U16(v) => write!(f, "{}", v),
+            RegisterValue::I16(v) => write!(f, "{}", v),
+            RegisterValue::U32(v) => write!(f, "{}", v),
+            RegisterValue::I32(v) => write!(f, "{}", v),
+            RegisterValue::F32(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// 按格式解释寄存器，32 位格式每两个寄存器一个值，多余的寄存器忽略
+pub fn decode_registers(registers: &[u16], format: ValueFormat, order: WordOrder) -> Vec<RegisterValue> {
+    let byte_swap = matches!(order, WordOrder::Badc | WordOrder::Dcba);
+
+    if format.width() == 1 {
+        return registers
+            .iter()
+            .map(|&r| if byte_swap { r.swap_bytes() } else { r })
+            .map(|r| match format {
+                ValueFormat::I16 => RegisterValue::I16(r as i16),
+                _ => RegisterValue::U16(r),
+            })
+            .collect();
+    }
+
+    registers
+        .chunks_exact(2)
+        .map(|pair| {
+            let [a, b] = pair[0].to_be_bytes();
+            let [c, d] = pair[1].to_be_bytes();
+            let bytes = match order {
+                WordOrder::Abcd => [a, b, c, d],
+                WordOrder::Cdab => [c, d, a, b],
+                WordOrder::Badc => [b, a, d, c],
+                WordOrder::Dcba => [d, c, b, a],
+            };
+            let raw = u32::from_be_bytes(bytes);
+            match format {
+                ValueFormat::I32 => RegisterValue::I32(raw as i32),
+                ValueFormat::F32 => RegisterValue::F32(f32::from_bits(raw)),
+                _ => RegisterValue::U32(raw),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 读保持寄存器：从站 1，地址 0，数量 10
+    const PDU: [u8; 5] = [0x03, 0x00, 0x00, 0x00, 0x0A];
+
+    #[test]
+    fn rtu_frame() {
+        let frame = encode_frame(ModbusMode::Rtu, 0x01, &PDU);
+        assert_eq!(frame, [0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]);
+        assert_eq!(decode_frame(ModbusMode::Rtu, &frame), Ok((0x01, PDU.to_vec())));
+
+        let mut corrupt = frame.clone();
+        corrupt[7] ^= 1;
+        assert!(decode_frame(ModbusMode::Rtu, &corrupt).is_err());
+    }
+
+    #[test]
+    fn ascii_frame() {
+        let frame = encode_frame(ModbusMode::Ascii, 0x01, &PDU);
+        assert_eq!(frame, b":01030000000AF2\r\n");
+        assert_eq!(decode_frame(ModbusMode::Ascii, &frame), Ok((0x01, PDU.to_vec())));
+
+        assert!(decode_frame(ModbusMode::Ascii, b":01030000000AF3\r\n").is_err());
+        assert!(decode_frame(ModbusMode::Ascii, ":0中\r\n".as_bytes()).is_err());
+    }
+}
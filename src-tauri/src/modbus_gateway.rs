@@ -0,0 +1,185 @@
+//! Modbus TCP ↔ RTU/ASCII 网关：监听 Modbus TCP，把请求转发到当前串口，再按 MBAP 头把应答返回给客户端。
+//!
+//! 多个客户端可同时连接，串口上的请求依次执行。转发的帧照常出现在收发记录、历史与日志中，
+//! 客户端连接、断开以及超时等失败以标记的形式插入。
+
+use crate::modbus::{self, ModbusConfig};
+use crate::serial::SerialManager;
+use crate::transfer::SessionLink;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GatewayEvent {
+    Client { addr: String, connected: bool },
+    /// 一次转发，error 为空表示成功（包括从站返回的异常应答）
+    Request {
+        client: String,
+        transaction: u16,
+        unit: u8,
+        function: u8,
+        error: Option<String>,
+    },
+}
+
+// 客户端读取超时，用于及时响应取消
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 监听 Modbus TCP 端口，返回后可用 local_addr 取得实际地址（端口为 0 时）
+pub fn bind(addr: &str) -> Result<TcpListener, String> {
+    let listener = TcpListener::bind(addr).map_err(|e| format!("监听 {} 失败: {}", addr, e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("监听 {} 失败: {}", addr, e))?;
+    Ok(listener)
+}
+
+/// 运行网关直到 cancel 置位
+pub fn run_gateway(
+    listener: &TcpListener,
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &ModbusConfig,
+    cancel: &AtomicBool,
+    on_event: impl Fn(&GatewayEvent) + Sync,
+) -> Result<(), String> {
+    // 串口同一时间只能有一个请求在等应答
+    let serial = Mutex::new(());
+
+    thread::scope(|scope| {
+        while !cancel.load(Ordering::SeqCst) {
+            let (stream, addr) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(e) => {
+                    // 让客户端线程一并退出
+                    cancel.store(true, Ordering::SeqCst);
+                    return Err(format!("接受连接失败: {}", e));
+                }
+            };
+
+            let (serial, on_event) = (&serial, &on_event);
+            scope.spawn(move || {
+                let addr = addr.to_string();
+                manager.lock().mark(&format!("Modbus TCP 客户端已连接: {}", addr));
+                on_event(&GatewayEvent::Client { addr: addr.clone(), connected: true });
+
+                serve_client(stream, &addr, manager, config, serial, cancel, on_event);
+
+                manager.lock().mark(&format!("Modbus TCP 客户端已断开: {}", addr));
+                on_event(&GatewayEvent::Client { addr, connected: false });
+            });
+        }
+        Ok(())
+    })
+}
+
+// 处理一个客户端的请求，直到断开、协议错误或取消
+fn serve_client(
+    mut stream: TcpStream,
+    addr: &str,
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &ModbusConfig,
+    serial: &Mutex<()>,
+    cancel: &AtomicBool,
+    on_event: &(impl Fn(&GatewayEvent) + Sync),
+) {
+    if stream.set_nonblocking(false).is_err() || stream.set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+        return;
+    }
+
+    loop {
+        // MBAP 头：事务号、协议号（0）、长度（单元号 + PDU）、单元号
+        let mut header = [0u8; 7];
+        if !read_full(&mut stream, &mut header, cancel) {
+            return;
+        }
+        let transaction = u16::from_be_bytes([header[0], header[1]]);
+        let protocol = u16::from_be_bytes([header[2], header[3]]);
+        let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+        let unit = header[6];
+        if protocol != 0 || !(2..=254).contains(&length) {
+            return;
+        }
+        let mut pdu = vec![0u8; length - 1];
+        if !read_full(&mut stream, &mut pdu, cancel) {
+            return;
+        }
+
+        let (reply, error) = forward(manager, config, serial, cancel, unit, &pdu);
+        if let Some(e) = &error {
+            manager
+                .lock()
+                .mark(&format!("Modbus TCP {} 事务 {} 从站 {}: {}", addr, transaction, unit, e));
+        }
+        on_event(&GatewayEvent::Request {
+            client: addr.to_string(),
+            transaction,
+            unit,
+            function: pdu[0],
+            error,
+        });
+
+        // 广播没有应答
+        let Some(reply) = reply else { continue };
+        let mut frame = Vec::with_capacity(reply.len() + 7);
+        frame.extend_from_slice(&header[..4]);
+        frame.extend_from_slice(&(reply.len() as u16 + 1).to_be_bytes());
+        frame.push(unit);
+        frame.extend_from_slice(&reply);
+        if stream.write_all(&frame).is_err() {
+            return;
+        }
+    }
+}
+
+// 在串口上执行请求，返回给客户端的应答 PDU 与失败原因。无法转发时以网关异常码应答
+fn forward(
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &ModbusConfig,
+    serial: &Mutex<()>,
+    cancel: &AtomicBool,
+    unit: u8,
+    pdu: &[u8],
+) -> (Option<Vec<u8>>, Option<String>) {
+    let exception = |code: u8| Some(vec![pdu[0] | 0x80, code]);
+    if unit > 247 {
+        return (exception(0x0A), Some(format!("从站地址 {} 无法转发", unit)));
+    }
+    if !manager.lock().is_connected() {
+        return (exception(0x0A), Some("未连接到串口".to_string()));
+    }
+
+    let _serial = serial.lock();
+    let mut link = SessionLink::new(manager, cancel);
+    match modbus::transact_pdu(&mut link, unit, pdu, config) {
+        Ok(reply) => (reply, None),
+        Err(e) => (exception(0x0B), Some(e)),
+    }
+}
+
+// 读满 buf，客户端断开、出错或取消时返回 false
+fn read_full(stream: &mut TcpStream, buf: &mut [u8], cancel: &AtomicBool) -> bool {
+    let mut filled = 0;
+    while filled < buf.len() {
+        if cancel.load(Ordering::SeqCst) {
+            return false;
+        }
+        match stream.read(&mut buf[filled..]) {
+            Ok(0) => return false,
+            Ok(n) => filled += n,
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted) => {}
+            Err(_) => return false,
+        }
+    }
+    true
+}
@@ -0,0 +1,349 @@
+//! Modbus 从站模拟：按寄存器表应答主站请求，可在串口或虚拟串口上提供服务，运行中可修改寄存器
+//!
+//! 寄存器表为 JSON 文件，只有列出的地址可以访问，其余地址返回异常 02，例如：
+//!
+//! ```json
+//! {
+//!   "unit": 1,
+//!   "coils": {"0": true, "1": false},
+//!   "discrete_inputs": {"0": true},
+//!   "holding_registers": {"0": 1234, "1": 5678, "2": 0},
+//!   "input_registers": {"100": 250, "101": 251}
+//! }
+//! ```
+
+use crate::modbus::{self, ModbusMode, ModbusRequest};
+use crate::serial;
+use crate::simulator::SimPort;
+use chrono::{Local, SecondsFormat};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterTable {
+    Coils,
+    DiscreteInputs,
+    HoldingRegisters,
+    InputRegisters,
+}
+
+impl RegisterTable {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "c" | "coils" => Ok(RegisterTable::Coils),
+            "d" | "discrete_inputs" => Ok(RegisterTable::DiscreteInputs),
+            "h" | "holding_registers" => Ok(RegisterTable::HoldingRegisters),
+            "i" | "input_registers" => Ok(RegisterTable::InputRegisters),
+            _ => Err(format!("未知寄存器类型: {} (可选 c, d, h, i)", s)),
+        }
+    }
+
+    pub fn is_bits(&self) -> bool {
+        matches!(self, RegisterTable::Coils | RegisterTable::DiscreteInputs)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegisterMap {
+    pub unit: u8,
+    pub coils: BTreeMap<u16, bool>,
+    pub discrete_inputs: BTreeMap<u16, bool>,
+    pub holding_registers: BTreeMap<u16, u16>,
+    pub input_registers: BTreeMap<u16, u16>,
+}
+
+impl Default for RegisterMap {
+    fn default() -> Self {
+        Self {
+            unit: 1,
+            coils: BTreeMap::new(),
+            discrete_inputs: BTreeMap::new(),
+            holding_registers: BTreeMap::new(),
+            input_registers: BTreeMap::new(),
+        }
+    }
+}
+
+impl RegisterMap {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("读取寄存器表 {} 失败: {}", path, e))?;
+        let map: RegisterMap =
+            serde_json::from_str(&content).map_err(|e| format!("解析寄存器表 {} 失败: {}", path, e))?;
+        if map.unit == 0 || map.unit > 247 {
+            return Err("从站地址必须在 1..=247 之间".to_string());
+        }
+        Ok(map)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| format!("序列化寄存器表失败: {}", e))?;
+        std::fs::write(path, content).map_err(|e| format!("保存寄存器表 {} 失败: {}", path, e))
+    }
+
+    /// 从 address 起依次设置值，地址不存在时添加。线圈与离散输入非 0 即为 1
+    pub fn set(&mut self, table: RegisterTable, address: u16, values: &[u16]) -> Result<(), String> {
+        if values.is_empty() || address as usize + values.len() > 0x10000 {
+            return Err("地址超出范围".to_string());
+        }
+        for (i, &value) in values.iter().enumerate() {
+            let address = address + i as u16;
+            match table {
+                RegisterTable::Coils => {
+                    self.coils.insert(address, value != 0);
+                }
+                RegisterTable::DiscreteInputs => {
+                    self.discrete_inputs.insert(address, value != 0);
+                }
+                RegisterTable::HoldingRegisters => {
+                    self.holding_registers.insert(address, value);
+                }
+                RegisterTable::InputRegisters => {
+                    self.input_registers.insert(address, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 按地址列出一张表的全部内容，线圈与离散输入以 0/1 表示
+    pub fn entries(&self, table: RegisterTable) -> Vec<(u16, u16)> {
+        match table {
+            RegisterTable::Coils => self.coils.iter().map(|(&a, &v)| (a, v as u16)).collect(),
+            RegisterTable::DiscreteInputs => self.discrete_inputs.iter().map(|(&a, &v)| (a, v as u16)).collect(),
+            RegisterTable::HoldingRegisters => self.holding_registers.iter().map(|(&a, &v)| (a, v)).collect(),
+            RegisterTable::InputRegisters => self.input_registers.iter().map(|(&a, &v)| (a, v)).collect(),
+        }
+    }
+
+    /// 执行请求并返回应答 PDU，失败时返回异常码
+    pub fn execute(&mut self, request: &ModbusRequest) -> Result<Vec<u8>, u8> {
+        let function = request.function_code();
+        let mut pdu = vec![function];
+        match request {
+            ModbusRequest::ReadCoils { address, count } => {
+                let bits = read_range(&self.coils, *address, *count)?;
+                let bytes = modbus::pack_bits(&bits);
+                pdu.push(bytes.len() as u8);
+                pdu.extend(bytes);
+            }
+            ModbusRequest::ReadDiscreteInputs { address, count } => {
+                let bits = read_range(&self.discrete_inputs, *address, *count)?;
+                let bytes = modbus::pack_bits(&bits);
+                pdu.push(bytes.len() as u8);
+                pdu.extend(bytes);
+            }
+            ModbusRequest::ReadHoldingRegisters { address, count } => {
+                let regs = read_range(&self.holding_registers, *address, *count)?;
+                pdu.push((regs.len() * 2) as u8);
+                pdu.extend(regs.iter().flat_map(|r| r.to_be_bytes()));
+            }
+            ModbusRequest::ReadInputRegisters { address, count } => {
+                let regs = read_range(&self.input_registers, *address, *count)?;
+                pdu.push((regs.len() * 2) as u8);
+                pdu.extend(regs.iter().flat_map(|r| r.to_be_bytes()));
+            }
+            ModbusRequest::WriteSingleCoil { address, value } => {
+                write_range(&mut self.coils, *address, &[*value])?;
+                pdu.extend(address.to_be_bytes());
+                pdu.extend(if *value { [0xFF, 0x00] } else { [0x00, 0x00] });
+            }
+            ModbusRequest::WriteSingleRegister { address, value } => {
+                write_range(&mut self.holding_registers, *address, &[*value])?;
+                pdu.extend(address.to_be_bytes());
+                pdu.extend(value.to_be_bytes());
+            }
+            ModbusRequest::WriteMultipleCoils { address, values } => {
+                write_range(&mut self.coils, *address, values)?;
+                pdu.extend(address.to_be_bytes());
+                pdu.extend((values.len() as u16).to_be_bytes());
+            }
+            ModbusRequest::WriteMultipleRegisters { address, values } => {
+                write_range(&mut self.holding_registers, *address, values)?;
+                pdu.extend(address.to_be_bytes());
+                pdu.extend((values.len() as u16).to_be_bytes());
+            }
+        }
+        Ok(pdu)
+    }
+}
+
+// 整段地址都存在才允许访问，否则为异常 02
+fn read_range<T: Copy>(table: &BTreeMap<u16, T>, address: u16, count: u16) -> Result<Vec<T>, u8> {
+    (0..count)
+        .map(|i| {
+            let address = address.checked_add(i).ok_or(0x02u8)?;
+            table.get(&address).copied().ok_or(0x02)
+        })
+        .collect()
+}
+
+fn write_range<T: Copy>(table: &mut BTreeMap<u16, T>, address: u16, values: &[T]) -> Result<(), u8> {
+    read_range(table, address, values.len() as u16)?;
+    for (i, value) in values.iter().enumerate() {
+        table.insert(address + i as u16, *value);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlaveOptions {
+    pub mode: ModbusMode,
+    pub log_path: Option<String>, // 每次交互以 JSON Lines 追加写入此文件
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlaveEvent {
+    /// 一次请求与应答，广播请求的 response 为空
+    Transaction {
+        time: String,
+        unit: u8,
+        summary: String,
+        request: String,
+        response: String,
+        exception: Option<u8>,
+    },
+    /// 校验错误或无法解析的帧
+    BadFrame { time: String, frame: String, error: String },
+}
+
+// RTU 帧之间的静默间隔，超过后缓存的数据视为一帧
+const FRAME_GAP: Duration = Duration::from_millis(50);
+
+/// 运行从站直到 cancel 置位。寄存器表由调用方共享，运行中可随时修改
+pub fn run_slave(
+    map: &Mutex<RegisterMap>,
+    port: &mut SimPort,
+    options: &SlaveOptions,
+    cancel: &AtomicBool,
+    mut on_event: impl FnMut(&SlaveEvent),
+) -> Result<(), String> {
+    let mut buf = [0u8; 1024];
+    let mut pending = Vec::new();
+    let mut last_rx = Instant::now();
+
+    while !cancel.load(Ordering::SeqCst) {
+        let n = port.read(&mut buf, &mut |_| {})?;
+        if n > 0 {
+            last_rx = Instant::now();
+            pending.extend_from_slice(&buf[..n]);
+        }
+
+        let idle = n == 0 && last_rx.elapsed() >= FRAME_GAP;
+        while let Some(frame) = next_frame(options.mode, &mut pending, idle) {
+            let event = match modbus::decode_frame(options.mode, &frame) {
+                Ok((unit, pdu)) => match serve(&mut map.lock(), options.mode, unit, &pdu, &frame) {
+                    Some((response, event)) => {
+                        if !response.is_empty() {
+                            port.write(&response)?;
+                        }
+                        Some(event)
+                    }
+                    None => None,
+                },
+                Err(error) => Some(SlaveEvent::BadFrame {
+                    time: now(),
+                    frame: serial::bytes_to_hex_string(&frame),
+                    error,
+                }),
+            };
+
+            if let Some(event) = event {
+                if let Some(path) = &options.log_path {
+                    append_log(path, &event)?;
+                }
+                on_event(&event);
+            }
+        }
+    }
+    Ok(())
+}
+
+// 从缓存中取出一个完整的帧。RTU 按功能码确定请求长度，无法确定时等线路空闲；
+// ASCII 取 ':' 到 LF 之间的内容
+fn next_frame(mode: ModbusMode, pending: &mut Vec<u8>, idle: bool) -> Option<Vec<u8>> {
+    match mode {
+        ModbusMode::Rtu => {
+            let len = match pending.get(1) {
+                Some(0x01..=0x06) => Some(8),
+                Some(0x0F | 0x10) => pending.get(6).map(|&n| 9 + n as usize),
+                _ => None,
+            };
+            match len {
+                Some(len) if pending.len() >= len => Some(pending.drain(..len).collect()),
+                _ if idle && !pending.is_empty() => Some(std::mem::take(pending)),
+                _ => None,
+            }
+        }
+        ModbusMode::Ascii => {
+            match pending.iter().position(|&b| b == b':') {
+                Some(start) => {
+                    pending.drain(..start);
+                }
+                None => {
+                    pending.clear();
+                    return None;
+                }
+            }
+            let end = pending.iter().position(|&b| b == b'\n')?;
+            Some(pending.drain(..=end).collect())
+        }
+    }
+}
+
+// 处理一个校验通过的请求，返回要发送的应答帧与事件。发给其他从站的请求不处理
+fn serve(map: &mut RegisterMap, mode: ModbusMode, unit: u8, pdu: &[u8], frame: &[u8]) -> Option<(Vec<u8>, SlaveEvent)> {
+    if unit != map.unit && unit != 0 {
+        return None;
+    }
+
+    let request = ModbusRequest::from_pdu(pdu);
+    let summary = match &request {
+        Ok(request) => request.to_string(),
+        Err(_) => format!("功能码 {:02X}", pdu.first().copied().unwrap_or(0)),
+    };
+    // 广播只执行写操作，且不应答
+    if unit == 0 && !matches!(&request, Ok(r) if r.is_write()) {
+        return None;
+    }
+
+    let result = request.and_then(|r| map.execute(&r));
+    let exception = result.as_ref().err().copied();
+    let response = match result {
+        _ if unit == 0 => Vec::new(),
+        Ok(pdu) => modbus::encode_frame(mode, unit, &pdu),
+        Err(code) => modbus::encode_frame(mode, unit, &[pdu[0] | 0x80, code]),
+    };
+
+    let event = SlaveEvent::Transaction {
+        time: now(),
+        unit,
+        summary,
+        request: serial::bytes_to_hex_string(frame),
+        response: serial::bytes_to_hex_string(&response),
+        exception,
+    };
+    Some((response, event))
+}
+
+fn now() -> String {
+    Local::now().to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+fn append_log(path: &str, event: &SlaveEvent) -> Result<(), String> {
+    let line = serde_json::to_string(event).map_err(|e| format!("序列化记录失败: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("打开 {} 失败: {}", path, e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("写入 {} 失败: {}", path, e))
+}
@@ -0,0 +1,418 @@
+//! NMEA 0183 解析：从接收数据中取出语句并校验 `*hh`，解析 GGA、RMC、GSA、GSV、VTG，
+//! 汇总为当前定位状态 [`GpsFix`]。另提供 PMTK 等 NMEA 格式命令与 UBX 二进制命令的组帧。
+
+use chrono::Local;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 一条已校验的 NMEA 语句，如 `$GPGGA,...*47` 的 talker 为 "GP"、kind 为 "GGA"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RawSentence {
+    pub talker: String,
+    pub kind: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Sentence {
+    Gga {
+        time: Option<String>,
+        latitude: Option<f64>,
+        longitude: Option<f64>,
+        quality: u8,
+        satellites: u8,
+        hdop: Option<f32>,
+        altitude: Option<f64>,
+    },
+    Rmc {
+        time: Option<String>,
+        valid: bool,
+        latitude: Option<f64>,
+        longitude: Option<f64>,
+        speed_knots: Option<f64>,
+        course: Option<f64>,
+        date: Option<String>,
+    },
+    Gsa {
+        fix_type: u8,
+        prns: Vec<u16>,
+        pdop: Option<f32>,
+        hdop: Option<f32>,
+        vdop: Option<f32>,
+    },
+    Gsv {
+        system: String,
+        total: u8,
+        number: u8,
+        in_view: u8,
+        satellites: Vec<Satellite>,
+    },
+    Vtg {
+        course: Option<f64>,
+        speed_knots: Option<f64>,
+        speed_kmh: Option<f64>,
+    },
+    /// 校验通过但未解析的语句类型
+    Other(RawSentence),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Satellite {
+    pub system: String, // talker，GP/GL/GA/BD/GB/QZ 等
+    pub prn: u16,
+    pub elevation: Option<u8>,
+    pub azimuth: Option<u16>,
+    pub snr: Option<u8>, // 未跟踪时为空
+}
+
+/// 由最近的语句汇总出的定位状态
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GpsFix {
+    pub time: Option<String>, // UTC，hh:mm:ss.ss
+    pub date: Option<String>, // UTC，yyyy-mm-dd
+    pub valid: bool,          // RMC 状态 A
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>, // 海拔，米
+    pub quality: u8,           // GGA 定位质量
+    pub fix_type: u8,          // GSA：1 无定位，2 二维，3 三维
+    pub satellites_used: u8,
+    pub used_prns: Vec<u16>,
+    pub pdop: Option<f32>,
+    pub hdop: Option<f32>,
+    pub vdop: Option<f32>,
+    pub speed_knots: Option<f64>,
+    pub speed_kmh: Option<f64>,
+    pub course: Option<f64>, // 真北航向，度
+    pub satellites: Vec<Satellite>, // 可见卫星
+    pub sentences: u64,
+    pub checksum_errors: u64,
+    pub updated: Option<String>, // 最近一次更新的本地时间
+}
+
+impl GpsFix {
+    pub fn quality_name(&self) -> &'static str {
+        match self.quality {
+            0 => "无定位",
+            1 => "GPS",
+            2 => "DGPS",
+            3 => "PPS",
+            4 => "RTK 固定解",
+            5 => "RTK 浮点解",
+            6 => "航位推算",
+            7 => "手动输入",
+            8 => "模拟",
+            _ => "未知",
+        }
+    }
+
+    fn apply(&mut self, sentence: &Sentence) {
+        match sentence {
+            Sentence::Gga { time, latitude, longitude, quality, satellites, hdop, altitude } => {
+                self.time = time.clone().or(self.time.take());
+                self.latitude = *latitude;
+                self.longitude = *longitude;
+                self.quality = *quality;
+                self.satellites_used = *satellites;
+                self.hdop = hdop.or(self.hdop);
+                self.altitude = *altitude;
+            }
+            Sentence::Rmc { time, valid, latitude, longitude, speed_knots, course, date } => {
+                self.time = time.clone().or(self.time.take());
+                self.date = date.clone().or(self.date.take());
+                self.valid = *valid;
+                self.latitude = *latitude;
+                self.longitude = *longitude;
+                self.speed_knots = *speed_knots;
+                self.speed_kmh = speed_knots.map(|k| k * 1.852);
+                self.course = *course;
+            }
+            Sentence::Gsa { fix_type, prns, pdop, hdop, vdop } => {
+                self.fix_type = *fix_type;
+                self.used_prns = prns.clone();
+                self.pdop = *pdop;
+                self.hdop = *hdop;
+                self.vdop = *vdop;
+            }
+            Sentence::Vtg { course, speed_knots, speed_kmh } => {
+                self.course = *course;
+                self.speed_knots = *speed_knots;
+                self.speed_kmh = *speed_kmh;
+            }
+            Sentence::Gsv { .. } | Sentence::Other(_) => return,
+        }
+        self.updated = Some(Local::now().format("%H:%M:%S%.3f").to_string());
+    }
+}
+
+// 一行最长字符数，标准为 82，留些余量给厂商语句
+const MAX_LINE: usize = 256;
+
+/// 接收方向的 NMEA 跟踪器：按行取出语句并更新定位状态
+#[derive(Debug, Default)]
+pub struct NmeaTracker {
+    line: Vec<u8>,
+    fix: GpsFix,
+    gsv: HashMap<String, Vec<Satellite>>, // 按 talker 收集中的多条 GSV
+}
+
+impl NmeaTracker {
+    /// 送入接收数据，返回其中解析出的语句
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Sentence> {
+        let mut sentences = Vec::new();
+        for &b in data {
+            match b {
+                b'$' => {
+                    self.line.clear();
+                    self.line.push(b);
+                }
+                b'\r' | b'\n' => {
+                    if self.line.first() == Some(&b'$') {
+                        let line = String::from_utf8_lossy(&self.line).to_string();
+                        if let Some(sentence) = self.process(&line) {
+                            sentences.push(sentence);
+                        }
+                    }
+                    self.line.clear();
+                }
+                _ if !self.line.is_empty() && self.line.len() < MAX_LINE => self.line.push(b),
+                _ => self.line.clear(),
+            }
+        }
+        sentences
+    }
+
+    fn process(&mut self, line: &str) -> Option<Sentence> {
+        let sentence = match parse_sentence(line) {
+            Ok(s) => s,
+            Err(_) => {
+                self.fix.checksum_errors += 1;
+                return None;
+            }
+        };
+        self.fix.sentences += 1;
+
+        if let Sentence::Gsv { system, total, number, satellites, .. } = &sentence {
+            let pending = self.gsv.entry(system.clone()).or_default();
+            if *number <= 1 {
+                pending.clear();
+            }
+            pending.extend(satellites.iter().cloned());
+            if number >= total {
+                let complete = self.gsv.remove(system).unwrap_or_default();
+                self.fix.satellites.retain(|s| &s.system != system);
+                self.fix.satellites.extend(complete);
+            }
+        }
+        self.fix.apply(&sentence);
+        Some(sentence)
+    }
+
+    pub fn fix(&self) -> &GpsFix {
+        &self.fix
+    }
+
+    /// 清除定位状态与未完成的行
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// 计算 `$` 与 `*` 之间内容的异或校验
+pub fn checksum(body: &str) -> u8 {
+    body.bytes().fold(0, |acc, b| acc ^ b)
+}
+
+/// 校验并拆分一行语句（可带 CR/LF），校验值缺失或错误时返回 Err
+pub fn parse_raw(line: &str) -> Result<RawSentence, String> {
+    let line = line.trim();
+    // NMEA 只用 ASCII，字段按字节位置切分前先排除其他字符
+    if !line.is_ascii() {
+        return Err("语句包含非 ASCII 字符".to_string());
+    }
+    let body = line
+        .strip_prefix('$')
+        .or_else(|| line.strip_prefix('!'))
+        .ok_or_else(|| format!("不是 NMEA 语句: {}", line))?;
+    let (body, check) = body
+        .rsplit_once('*')
+        .ok_or_else(|| format!("缺少校验值: {}", line))?;
+    let expected = u8::from_str_radix(check, 16).map_err(|_| format!("校验值无效: {}", check))?;
+    let actual = checksum(body);
+    if actual != expected {
+        return Err(format!("校验错误: 收到 {:02X}，计算 {:02X}", expected, actual));
+    }
+
+    let mut parts = body.split(',');
+    let address = parts.next().unwrap_or_default();
+    // 厂商语句以 P 开头，如 PMTK001、PUBX
+    let (talker, kind) = if address.starts_with('P') || address.len() < 5 {
+        ("", address)
+    } else {
+        address.split_at(2)
+    };
+    Ok(RawSentence {
+        talker: talker.to_string(),
+        kind: kind.to_string(),
+        fields: parts.map(str::to_string).collect(),
+    })
+}
+
+/// 校验并解析一行语句
+pub fn parse_sentence(line: &str) -> Result<Sentence, String> {
+    let raw = parse_raw(line)?;
+    let f = |i: usize| raw.fields.get(i).map(String::as_str).unwrap_or("");
+
+    let sentence = match raw.kind.as_str() {
+        "GGA" => Sentence::Gga {
+            time: parse_time(f(0)),
+            latitude: parse_coordinate(f(1), f(2)),
+            longitude: parse_coordinate(f(3), f(4)),
+            quality: f(5).parse().unwrap_or(0),
+            satellites: f(6).parse().unwrap_or(0),
+            hdop: f(7).parse().ok(),
+            altitude: f(8).parse().ok(),
+        },
+        "RMC" => Sentence::Rmc {
+            time: parse_time(f(0)),
+            valid: f(1) == "A",
+            latitude: parse_coordinate(f(2), f(3)),
+            longitude: parse_coordinate(f(4), f(5)),
+            speed_knots: f(6).parse().ok(),
+            course: f(7).parse().ok(),
+            date: parse_date(f(8)),
+        },
+        "GSA" => Sentence::Gsa {
+            fix_type: f(1).parse().unwrap_or(1),
+            prns: (2..14).filter_map(|i| f(i).parse().ok()).collect(),
+            pdop: f(14).parse().ok(),
+            hdop: f(15).parse().ok(),
+            vdop: f(16).parse().ok(),
+        },
+        "GSV" => Sentence::Gsv {
+            system: raw.talker.clone(),
+            total: f(0).parse().unwrap_or(1),
+            number: f(1).parse().unwrap_or(1),
+            in_view: f(2).parse().unwrap_or(0),
+            // 每颗卫星 4 个字段，NMEA 4.1 起末尾可能多一个信号 ID
+            satellites: raw.fields[3.min(raw.fields.len())..]
+                .chunks(4)
+                .filter(|c| c.len() == 4)
+                .filter_map(|c| {
+                    Some(Satellite {
+                        system: raw.talker.clone(),
+                        prn: c[0].parse().ok()?,
+                        elevation: c[1].parse().ok(),
+                        azimuth: c[2].parse().ok(),
+                        snr: c[3].parse().ok(),
+                    })
+                })
+                .collect(),
+        },
+        "VTG" => Sentence::Vtg {
+            course: f(0).parse().ok(),
+            speed_knots: f(4).parse().ok(),
+            speed_kmh: f(6).parse().ok(),
+        },
+        _ => Sentence::Other(raw),
+    };
+    Ok(sentence)
+}
+
+// ddmm.mmmm + 半球 → 十进制度，南纬、西经为负
+fn parse_coordinate(value: &str, hemisphere: &str) -> Option<f64> {
+    let dot = value.find('.').unwrap_or(value.len());
+    if dot < 3 {
+        return None;
+    }
+    let degrees: f64 = value[..dot - 2].parse().ok()?;
+    let minutes: f64 = value[dot - 2..].parse().ok()?;
+    let decimal = degrees + minutes / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(decimal),
+        "S" | "W" => Some(-decimal),
+        _ => None,
+    }
+}
+
+// hhmmss.ss → hh:mm:ss.ss
+fn parse_time(value: &str) -> Option<String> {
+    if value.len() < 6 || !value[..6].bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}:{}:{}", &value[..2], &value[2..4], &value[4..]))
+}
+
+// ddmmyy → yyyy-mm-dd，两位年份 80 及以上视为 19xx
+fn parse_date(value: &str) -> Option<String> {
+    if value.len() != 6 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let century = if &value[4..6] >= "80" { "19" } else { "20" };
+    Some(format!("{}{}-{}-{}", century, &value[4..6], &value[2..4], &value[..2]))
+}
+
+/// 组成 NMEA 格式命令，自动添加 `$`、`*hh` 与 CRLF。已带的 `$` 与校验值会被替换，
+/// 如 "PMTK220,1000" → "$PMTK220,1000*1F\r\n"
+pub fn build_command(body: &str) -> String {
+    let body = body.trim();
+    let body = body.strip_prefix('$').unwrap_or(body);
+    let body = match body.rsplit_once('*') {
+        Some((b, check)) if check.len() <= 2 => b,
+        _ => body,
+    };
+    format!("${}*{:02X}\r\n", body, checksum(body))
+}
+
+/// 组成 UBX 二进制命令：B5 62、类、ID、长度（小端）、负载、Fletcher-8 校验
+pub fn build_ubx(class: u8, id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0xB5, 0x62, class, id];
+    frame.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    frame.extend_from_slice(payload);
+    let (mut a, mut b) = (0u8, 0u8);
+    for &byte in &frame[2..] {
+        a = a.wrapping_add(byte);
+        b = b.wrapping_add(a);
+    }
+    frame.extend_from_slice(&[a, b]);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_command_adds_checksum() {
+        assert_eq!(build_command("PMTK220,1000"), "$PMTK220,1000*1F\r\n");
+        // 已带的 $ 与校验值被替换
+        assert_eq!(build_command("$PMTK220,1000*00"), "$PMTK220,1000*1F\r\n");
+    }
+
+    // 按收到的字节组成一行，校验值按跟踪器看到的（有损转换后的）文本计算
+    fn line(body: &[u8]) -> Vec<u8> {
+        let check = checksum(&String::from_utf8_lossy(body));
+        let mut line = vec![b'$'];
+        line.extend_from_slice(body);
+        line.extend_from_slice(format!("*{:02X}\r\n", check).as_bytes());
+        line
+    }
+
+    #[test]
+    fn tracker_ignores_non_ascii_lines() {
+        let mut tracker = NmeaTracker::default();
+        for body in [
+            &b"\xFFGGA,1"[..],
+            "\u{FFFD}GGA,1".as_bytes(),
+            "GPGGA,12345\u{e9},4916.45,N,12311.12,W,1,08,0.9,545.4,M,46.9,M,,".as_bytes(),
+            "GPRMC,123519,A,49\u{e9}.45,N,12311.12,W,022.4,084.4,230394,003.1,W".as_bytes(),
+        ] {
+            assert!(tracker.feed(&line(body)).is_empty());
+        }
+        assert_eq!(tracker.fix().checksum_errors, 4);
+
+        let sentences = tracker.feed(&line(b"GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,"));
+        assert_eq!(sentences.len(), 1);
+    }
+}
@@ -0,0 +1,296 @@
+//! 数值通道：从接收的文本行中提取数值（CSV、key=value 或带命名分组的正则），
+//! 每个通道保存为时间序列供界面画图，可导出为 CSV。
+//!
+//! 例如 `t=12.3,v=4.98,i=0.12` 用 kv 解析得到 t、v、i 三个通道。
+
+use chrono::{Local, TimeZone};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+
+/// 每个通道最多保留的点数，超出后丢弃最早的点
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+// 一行最长字节数，超出的部分丢弃
+const MAX_LINE: usize = 1024;
+
+enum ParserKind {
+    /// 逗号、分号或制表符分隔，names 为空时用 ch1、ch2…，遇到全为非数字的行时作为表头
+    Csv { names: Vec<String>, fixed: bool },
+    KeyValue(Regex),
+    /// 命名分组作为通道名，没有命名分组时按序号命名
+    Regex(Regex),
+}
+
+/// 行解析器，按名称创建：`csv`、`csv:t,v,i`、`kv`、`regex:<正则>`
+pub struct ChannelParser {
+    spec: String,
+    kind: ParserKind,
+}
+
+impl ChannelParser {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, args) = spec.split_once(':').unwrap_or((spec, ""));
+        let kind = match name {
+            "csv" => {
+                let names: Vec<String> = args
+                    .split(',')
+                    .map(|n| n.trim().to_string())
+                    .filter(|n| !n.is_empty())
+                    .collect();
+                ParserKind::Csv { fixed: !names.is_empty(), names }
+            }
+            "kv" => ParserKind::KeyValue(
+                Regex::new(r"([A-Za-z_][\w.\[\]]*)\s*[=:]\s*([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)")
+                    .expect("kv 正则有效"),
+            ),
+            "regex" => {
+                let regex = Regex::new(args).map_err(|e| format!("正则无效: {}", e))?;
+                if regex.captures_len() < 2 {
+                    return Err("正则需要至少一个分组，如 regex:T=(?P<temp>[\\d.]+)".to_string());
+                }
+                ParserKind::Regex(regex)
+            }
+            _ => return Err(format!("未知解析方式: {} (可选 csv、csv:名称,...、kv、regex:正则)", spec)),
+        };
+        Ok(Self { spec: spec.to_string(), kind })
+    }
+
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    /// 从一行文本中取出 (通道名, 数值)
+    pub fn extract(&mut self, line: &str) -> Vec<(String, f64)> {
+        match &mut self.kind {
+            ParserKind::Csv { names, fixed } => {
+                let cells: Vec<&str> = line.split([',', ';', '\t']).map(str::trim).collect();
+                let values: Vec<Option<f64>> = cells.iter().map(|c| c.parse().ok()).collect();
+                if !*fixed && values.iter().all(Option::is_none) {
+                    *names = cells.iter().map(|c| c.to_string()).collect();
+                    return Vec::new();
+                }
+                values
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(i, v)| {
+                        let name = names.get(i).cloned().unwrap_or_else(|| format!("ch{}", i + 1));
+                        Some((name, v?))
+                    })
+                    .collect()
+            }
+            ParserKind::KeyValue(regex) => regex
+                .captures_iter(line)
+                .filter_map(|c| Some((c[1].to_string(), c[2].parse().ok()?)))
+                .collect(),
+            ParserKind::Regex(regex) => {
+                let Some(captures) = regex.captures(line) else {
+                    return Vec::new();
+                };
+                let names: Vec<Option<&str>> = regex.capture_names().collect();
+                (1..captures.len())
+                    .filter_map(|i| {
+                        let value = captures.get(i)?.as_str().trim().parse().ok()?;
+                        let name = names[i].map(str::to_string).unwrap_or_else(|| format!("ch{}", i));
+                        Some((name, value))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Sample {
+    pub time: i64, // 毫秒时间戳
+    pub value: f64,
+    #[serde(skip)]
+    line: u64, // 来源行的序号，同一行的数值导出在同一行
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Channel {
+    pub name: String,
+    pub samples: Vec<Sample>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelSummary {
+    pub name: String,
+    pub count: usize,
+    pub last: f64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+/// 按行解析接收数据并保存各通道的时间序列
+pub struct Plotter {
+    parser: Option<ChannelParser>,
+    line: Vec<u8>,
+    channels: BTreeMap<String, VecDeque<Sample>>,
+    capacity: usize,
+    lines: u64,
+}
+
+impl Default for Plotter {
+    fn default() -> Self {
+        Self {
+            parser: None,
+            line: Vec::new(),
+            channels: BTreeMap::new(),
+            capacity: DEFAULT_CAPACITY,
+            lines: 0,
+        }
+    }
+}
+
+impl Plotter {
+    /// 设置解析器，None 停止提取，已有数据保留
+    pub fn set_parser(&mut self, parser: Option<ChannelParser>) {
+        self.parser = parser;
+        self.line.clear();
+    }
+
+    pub fn parser_spec(&self) -> Option<String> {
+        self.parser.as_ref().map(|p| p.spec().to_string())
+    }
+
+    /// 送入接收数据，完整的行按解析器提取数值，time 为毫秒时间戳
+    pub fn feed(&mut self, data: &[u8], time: i64) {
+        let Some(parser) = self.parser.as_mut() else {
+            return;
+        };
+        for &b in data {
+            match b {
+                b'\n' | b'\r' => {
+                    if self.line.is_empty() {
+                        continue;
+                    }
+                    let line = String::from_utf8_lossy(&self.line).to_string();
+                    self.line.clear();
+                    self.lines += 1;
+                    for (name, value) in parser.extract(&line) {
+                        let samples = self.channels.entry(name).or_default();
+                        if samples.len() >= self.capacity {
+                            samples.pop_front();
+                        }
+                        samples.push_back(Sample { time, value, line: self.lines });
+                    }
+                }
+                _ if self.line.len() < MAX_LINE => self.line.push(b),
+                _ => {}
+            }
+        }
+    }
+
+    /// 各通道的数据，since 为毫秒时间戳时只返回之后的点（用于界面增量刷新）
+    pub fn channels(&self, since: Option<i64>) -> Vec<Channel> {
+        self.channels
+            .iter()
+            .map(|(name, samples)| Channel {
+                name: name.clone(),
+                samples: samples
+                    .iter()
+                    .skip_while(|s| since.is_some_and(|t| s.time <= t))
+                    .copied()
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// 最近 count 个点的数值
+    pub fn recent(&self, name: &str, count: usize) -> Vec<f64> {
+        self.channels
+            .get(name)
+            .map(|s| s.iter().skip(s.len().saturating_sub(count)).map(|s| s.value).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn summaries(&self) -> Vec<ChannelSummary> {
+        self.channels
+            .iter()
+            .filter(|(_, samples)| !samples.is_empty())
+            .map(|(name, samples)| {
+                let values = samples.iter().map(|s| s.value);
+                ChannelSummary {
+                    name: name.clone(),
+                    count: samples.len(),
+                    last: samples.back().map(|s| s.value).unwrap_or_default(),
+                    min: values.clone().fold(f64::INFINITY, f64::min),
+                    max: values.clone().fold(f64::NEG_INFINITY, f64::max),
+                    mean: values.sum::<f64>() / samples.len() as f64,
+                }
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.channels.clear();
+        self.line.clear();
+    }
+
+    /// 导出为 CSV：第一列为时间，每个通道一列，同一行提取出的数值在同一行。返回数据行数
+    pub fn export_csv(&self, path: &str) -> Result<usize, String> {
+        let names: Vec<&String> = self.channels.keys().collect();
+        let mut rows: BTreeMap<u64, (i64, Vec<Option<f64>>)> = BTreeMap::new();
+        for (i, samples) in self.channels.values().enumerate() {
+            for sample in samples {
+                let row = rows
+                    .entry(sample.line)
+                    .or_insert_with(|| (sample.time, vec![None; names.len()]));
+                row.1[i] = Some(sample.value);
+            }
+        }
+
+        let mut csv = String::from("time");
+        for name in &names {
+            csv.push(',');
+            csv.push_str(&csv_field(name));
+        }
+        csv.push('\n');
+        for (time, values) in rows.values() {
+            let time = Local
+                .timestamp_millis_opt(*time)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+                .unwrap_or_else(|| time.to_string());
+            csv.push_str(&time);
+            for value in values {
+                csv.push(',');
+                if let Some(v) = value {
+                    csv.push_str(&v.to_string());
+                }
+            }
+            csv.push('\n');
+        }
+
+        std::fs::write(path, csv).map_err(|e| format!("写入 {} 失败: {}", path, e))?;
+        Ok(rows.len())
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// 用方块字符画出数值走势
+pub fn sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|v| {
+            if max > min {
+                BARS[(((v - min) / (max - min)) * 7.0).round() as usize]
+            } else {
+                BARS[3]
+            }
+        })
+        .collect()
+}
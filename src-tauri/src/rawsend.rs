@@ -0,0 +1,168 @@
+//! 原始文件发送：按块或按行写入串口，控制发送节奏，避免设备来不及处理而丢字符
+
+use crate::transfer::{ProgressFn, SessionLink, TransferProgress};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 发送选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RawSendOptions {
+    pub chunk_size: usize, // 每次写入的最大字节数，0 表示不分块
+    pub chunk_delay_ms: u64,
+    pub line_mode: bool, // 按行发送，用于文本文件
+    pub line_delay_ms: u64,
+    pub newline: String, // 行模式下的换行: "crlf", "lf", "cr"，为空时保留文件原有换行
+    pub wait_echo: bool, // 每行发送后等待设备回显该行
+    pub wait_for: Option<String>, // 每行发送后等待匹配的提示符（正则）
+    pub wait_timeout_ms: u64,
+}
+
+impl Default for RawSendOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: 256,
+            chunk_delay_ms: 0,
+            line_mode: false,
+            line_delay_ms: 0,
+            newline: String::new(),
+            wait_echo: false,
+            wait_for: None,
+            wait_timeout_ms: 2000,
+        }
+    }
+}
+
+const MAX_RECEIVED: usize = 4096;
+
+pub fn send_raw(
+    link: &mut SessionLink,
+    path: &str,
+    options: &RawSendOptions,
+    on_progress: ProgressFn,
+) -> Result<(), String> {
+    let content = std::fs::read(path).map_err(|e| format!("读取文件 {} 失败: {}", path, e))?;
+    let prompt = match &options.wait_for {
+        Some(p) if !p.is_empty() => Some(Regex::new(p).map_err(|e| format!("无效的正则: {}", e))?),
+        _ => None,
+    };
+
+    let mut progress = TransferProgress {
+        protocol: "RAW".to_string(),
+        file: path.to_string(),
+        total: Some(content.len() as u64),
+        ..Default::default()
+    };
+    on_progress(&progress);
+
+    if !options.line_mode {
+        write_chunks(link, &content, options, &mut progress, on_progress)?;
+        return Ok(());
+    }
+
+    let mut received = Vec::new();
+    for line in content.split_inclusive(|&b| b == b'\n') {
+        let text = trim_newline(line);
+        let mut bytes = text.to_vec();
+        match options.newline.as_str() {
+            "crlf" => bytes.extend_from_slice(b"\r\n"),
+            "lf" => bytes.push(b'\n'),
+            "cr" => bytes.push(b'\r'),
+            _ => bytes = line.to_vec(),
+        }
+
+        received.clear();
+        let sent = progress.bytes;
+        write_chunks(link, &bytes, options, &mut progress, on_progress)?;
+        // 进度按文件字节计，换行替换不影响百分比
+        progress.bytes = sent + line.len() as u64;
+        on_progress(&progress);
+
+        if options.wait_echo {
+            let echo = String::from_utf8_lossy(text).to_string();
+            if !wait_until(link, &mut received, options.wait_timeout_ms, |t| t.contains(&echo))? {
+                return Err(format!("等待回显超时: {}", echo));
+            }
+        }
+        if let Some(prompt) = &prompt {
+            if !wait_until(link, &mut received, options.wait_timeout_ms, |t| prompt.is_match(t))? {
+                return Err("等待提示符超时".to_string());
+            }
+        }
+        pace(link, options.line_delay_ms)?;
+    }
+
+    Ok(())
+}
+
+fn trim_newline(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn write_chunks(
+    link: &mut SessionLink,
+    data: &[u8],
+    options: &RawSendOptions,
+    progress: &mut TransferProgress,
+    on_progress: ProgressFn,
+) -> Result<(), String> {
+    let size = if options.chunk_size == 0 { data.len().max(1) } else { options.chunk_size };
+
+    for (i, chunk) in data.chunks(size).enumerate() {
+        if link.is_cancelled() {
+            return Err("传输已取消".to_string());
+        }
+        if i > 0 {
+            pace(link, options.chunk_delay_ms)?;
+        }
+
+        link.write(chunk)?;
+        progress.bytes += chunk.len() as u64;
+        progress.blocks += 1;
+        on_progress(progress);
+    }
+    Ok(())
+}
+
+/// 可取消的等待
+fn pace(link: &SessionLink, ms: u64) -> Result<(), String> {
+    let deadline = Instant::now() + Duration::from_millis(ms);
+    while Instant::now() < deadline {
+        if link.is_cancelled() {
+            return Err("传输已取消".to_string());
+        }
+        thread::sleep((deadline - Instant::now()).min(Duration::from_millis(20)));
+    }
+    Ok(())
+}
+
+/// 读取接收数据直到条件满足，超时返回 false
+fn wait_until(
+    link: &mut SessionLink,
+    received: &mut Vec<u8>,
+    timeout_ms: u64,
+    done: impl Fn(&str) -> bool,
+) -> Result<bool, String> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+    loop {
+        if done(&String::from_utf8_lossy(received)) {
+            received.clear();
+            return Ok(true);
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(false);
+        }
+        if let Some(b) = link.read_byte(deadline - now)? {
+            received.push(b);
+            if received.len() > MAX_RECEIVED {
+                received.drain(..received.len() - MAX_RECEIVED);
+            }
+        }
+    }
+}
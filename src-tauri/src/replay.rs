@@ -0,0 +1,359 @@
+//! 会话回放：按原始时间间隔把抓包或文本日志中的数据写入串口或虚拟串口，用于模拟设备
+
+use crate::capture::{CaptureEvent, CaptureReader};
+use crate::serial::{self, SerialManager};
+use chrono::{DateTime, NaiveTime, Timelike};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use flate2::read::GzDecoder;
+use serialport::SerialPort;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 回放的一段数据，offset 为相对第一段的时间
+#[derive(Debug, Clone)]
+pub struct ReplayFrame {
+    pub offset: Duration,
+    pub data: Vec<u8>,
+}
+
+/// 回放目标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplayTarget {
+    /// 当前连接的串口，回放数据作为 TX 记录显示
+    Session,
+    Port { name: String, baud_rate: u32 },
+    /// 新建虚拟串口对，上位机软件打开返回的从端（仅 Unix）
+    Pty,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayOptions {
+    pub direction: String, // 回放哪个方向的数据，默认 "rx"
+    pub speed: f64,        // 2.0 为两倍速，0.5 为半速
+    pub looping: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            direction: "rx".to_string(),
+            speed: 1.0,
+            looping: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayProgress {
+    pub frame: usize,
+    pub frames: usize,
+    pub bytes: u64,
+    pub loops: u32,
+}
+
+/// 读取回放数据，JSON Lines 抓包与文本日志均可
+pub fn load_frames(path: &str, direction: &str) -> Result<Vec<ReplayFrame>, String> {
+    let is_capture = path.ends_with(".jsonl") || path.ends_with(".jsonl.gz") || {
+        let content = read_log(path)?;
+        content.trim_start().starts_with('{')
+    };
+
+    let frames = if is_capture {
+        load_capture(path, direction)?
+    } else {
+        load_text_log(path, direction)?
+    };
+
+    if frames.is_empty() {
+        return Err(format!("文件中没有 {} 数据", direction.to_uppercase()));
+    }
+    Ok(frames)
+}
+
+// 读取日志文件，轮转压缩的 .gz 文件自动解压
+fn read_log(path: &str) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("读取文件 {} 失败: {}", path, e))?;
+    let mut content = String::new();
+    let result = if path.ends_with(".gz") {
+        GzDecoder::new(file).read_to_string(&mut content)
+    } else {
+        file.read_to_string(&mut content)
+    };
+    result.map_err(|e| format!("读取文件 {} 失败: {}", path, e))?;
+    Ok(content)
+}
+
+fn load_capture(path: &str, direction: &str) -> Result<Vec<ReplayFrame>, String> {
+    let mut frames = Vec::new();
+    let mut start = None;
+
+    for record in CaptureReader::open(path)? {
+        let record = record?;
+        let matches = matches!(&record.event, CaptureEvent::Data { direction: d, .. } if d == direction);
+        if !matches {
+            continue;
+        }
+
+        let ts = record.timestamp()?;
+        let start = *start.get_or_insert(ts);
+        let offset = (ts - start).to_std().unwrap_or_default();
+        frames.push(ReplayFrame {
+            offset,
+            data: record.bytes().unwrap_or_default(),
+        });
+    }
+    Ok(frames)
+}
+
+// 文本日志每行形如 "[10:00:00.123] RX: 文本 | HEX: 41 42"，有 HEX 时以 HEX 为准。
+// 时间可以是任一时间戳方式（datetime、time、delta、elapsed）
+fn load_text_log(path: &str, direction: &str) -> Result<Vec<ReplayFrame>, String> {
+    let content = read_log(path)?;
+    let tag = format!("] {}: ", direction.to_uppercase());
+
+    let mut frames = Vec::new();
+    let mut clock = LogClock::default();
+    let mut start: Option<i64> = None;
+
+    for line in content.lines() {
+        let Some(pos) = line.find("] ").filter(|_| line.starts_with('[')) else {
+            continue;
+        };
+        let (time, entry) = (&line[1..pos], &line[pos..]);
+        // delta 按收发记录依次累加，两个方向都要计入；事件行不参与
+        if !entry.starts_with("] RX: ") && !entry.starts_with("] TX: ") {
+            continue;
+        }
+        let Some(position) = clock.advance(time) else {
+            continue;
+        };
+        let Some(data) = entry.strip_prefix(tag.as_str()) else {
+            continue;
+        };
+
+        let bytes = match data.rfind(" | HEX: ") {
+            Some(pos) => serial::parse_hex_string(&data[pos + 8..])?,
+            None => unescape_log_text(data),
+        };
+
+        let start = *start.get_or_insert(position);
+        let offset = Duration::from_micros((position - start).max(0) as u64);
+        frames.push(ReplayFrame { offset, data: bytes });
+    }
+    Ok(frames)
+}
+
+// 把文本日志中的时间换算为微秒位置
+#[derive(Default)]
+struct LogClock {
+    position: i64,
+    prev_time: Option<NaiveTime>,
+    days: i64,
+}
+
+impl LogClock {
+    fn advance(&mut self, text: &str) -> Option<i64> {
+        let position = if let Some(offset) = text.strip_prefix('+') {
+            match offset.split_once(':') {
+                Some(_) => parse_elapsed(offset)?,
+                None => self.position + parse_seconds(offset)?,
+            }
+        } else if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+            time.timestamp_micros()
+        } else {
+            let time = NaiveTime::parse_from_str(text, "%H:%M:%S%.f").ok()?;
+            // 只有时刻，时间倒退视为跨过午夜
+            if self.prev_time.is_some_and(|p| time < p) {
+                self.days += 1;
+            }
+            self.prev_time = Some(time);
+            (self.days * 86_400 + time.num_seconds_from_midnight() as i64) * 1_000_000
+                + (time.nanosecond() / 1_000) as i64
+        };
+        self.position = position;
+        Some(position)
+    }
+}
+
+// "1.000250" → 微秒
+fn parse_seconds(text: &str) -> Option<i64> {
+    let (secs, frac) = text.split_once('.').unwrap_or((text, ""));
+    let frac = format!("{:0<6}", frac.get(..6).unwrap_or(frac));
+    Some(secs.parse::<i64>().ok()? * 1_000_000 + frac.parse::<i64>().ok()?)
+}
+
+// "01:02:03.000250" → 微秒
+fn parse_elapsed(text: &str) -> Option<i64> {
+    let mut parts = text.splitn(3, ':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    Some((hours * 3600 + minutes * 60) * 1_000_000 + parse_seconds(parts.next()?)?)
+}
+
+// 还原日志中转义的控制字符 (\r \n \t \xNN)
+fn unescape_log_text(text: &str) -> Vec<u8> {
+    let text = text.strip_suffix(" [校验错误]").unwrap_or(text);
+    let mut out = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('r') => out.push(b'\r'),
+            Some('n') => out.push(b'\n'),
+            Some('t') => out.push(b'\t'),
+            Some('\\') => out.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(b) => out.push(b),
+                    Err(_) => out.extend_from_slice(format!("\\x{}", hex).as_bytes()),
+                }
+            }
+            Some(other) => {
+                out.push(b'\\');
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => out.push(b'\\'),
+        }
+    }
+    out
+}
+
+/// 回放输出端
+pub struct ReplaySink {
+    name: String,
+    writer: SinkWriter,
+}
+
+enum SinkWriter {
+    Session(Arc<Mutex<SerialManager>>),
+    Port(Box<dyn SerialPort>),
+    #[cfg(unix)]
+    Pty {
+        master: serialport::TTYPort,
+        _slave: serialport::TTYPort, // 保持从端打开，上位机未连接时写入不会出错
+    },
+}
+
+impl ReplaySink {
+    pub fn open(target: &ReplayTarget, manager: &Arc<Mutex<SerialManager>>) -> Result<Self, String> {
+        match target {
+            ReplayTarget::Session => {
+                if !manager.lock().is_connected() {
+                    return Err("串口未连接".to_string());
+                }
+                Ok(Self {
+                    name: "当前串口".to_string(),
+                    writer: SinkWriter::Session(manager.clone()),
+                })
+            }
+            ReplayTarget::Port { name, baud_rate } => {
+                let port = serialport::new(name, *baud_rate)
+                    .timeout(Duration::from_millis(10))
+                    .open()
+                    .map_err(|e| format!("无法打开串口 {}: {}", name, e))?;
+                Ok(Self {
+                    name: name.clone(),
+                    writer: SinkWriter::Port(port),
+                })
+            }
+            #[cfg(unix)]
+            ReplayTarget::Pty => {
+                let (master, slave) =
+                    serialport::TTYPort::pair().map_err(|e| format!("创建虚拟串口失败: {}", e))?;
+                Ok(Self {
+                    name: slave.name().unwrap_or_default(),
+                    writer: SinkWriter::Pty { master, _slave: slave },
+                })
+            }
+            #[cfg(not(unix))]
+            ReplayTarget::Pty => Err("当前平台不支持虚拟串口".to_string()),
+        }
+    }
+
+    /// 目标名称，虚拟串口时为上位机应打开的设备路径
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        match &mut self.writer {
+            SinkWriter::Session(manager) => manager.lock().send_bytes(data),
+            SinkWriter::Port(port) => port.write_all(data).map_err(|e| format!("发送失败: {}", e)),
+            #[cfg(unix)]
+            SinkWriter::Pty { master, .. } => master.write_all(data).map_err(|e| format!("发送失败: {}", e)),
+        }
+    }
+}
+
+/// 按原始时间间隔回放，直到结束或取消
+pub fn run_replay(
+    frames: &[ReplayFrame],
+    sink: &mut ReplaySink,
+    options: &ReplayOptions,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(&ReplayProgress),
+) -> Result<(), String> {
+    let speed = if options.speed > 0.0 { options.speed } else { 1.0 };
+    let mut progress = ReplayProgress {
+        frames: frames.len(),
+        ..Default::default()
+    };
+
+    loop {
+        let start = Instant::now();
+        for (i, frame) in frames.iter().enumerate() {
+            if !wait_until(start + frame.offset.div_f64(speed), cancel) {
+                return Ok(());
+            }
+
+            sink.write(&frame.data)?;
+            progress.frame = i + 1;
+            progress.bytes += frame.data.len() as u64;
+            on_progress(&progress);
+        }
+
+        if !options.looping {
+            return Ok(());
+        }
+        progress.loops += 1;
+        if !wait_until(Instant::now() + loop_gap(frames).div_f64(speed).max(MIN_LOOP_GAP), cancel) {
+            return Ok(());
+        }
+    }
+}
+
+// 循环回放时每轮之间至少间隔的时间，避免只有一段或时间全为 0 时连续写入
+const MIN_LOOP_GAP: Duration = Duration::from_millis(100);
+
+// 最后一段到下一轮第一段的间隔，原始数据中没有，取平均帧间隔
+fn loop_gap(frames: &[ReplayFrame]) -> Duration {
+    match frames.last() {
+        Some(last) if frames.len() > 1 => last.offset.div_f64((frames.len() - 1) as f64),
+        _ => Duration::ZERO,
+    }
+}
+
+// 等待到 due，被取消时返回 false
+fn wait_until(due: Instant, cancel: &AtomicBool) -> bool {
+    while Instant::now() < due {
+        if cancel.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep((due - Instant::now()).min(Duration::from_millis(20)));
+    }
+    !cancel.load(Ordering::SeqCst)
+}
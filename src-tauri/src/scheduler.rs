@@ -0,0 +1,160 @@
+use crate::serial::{self, SerialManager};
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 定时发送任务参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleSpec {
+    pub payload: String,
+    pub hex_mode: bool,
+    pub interval_ms: u64,
+    #[serde(default)]
+    pub count: Option<u64>, // None 表示不限次数
+    #[serde(default)]
+    pub jitter_ms: u64, // 每次间隔随机偏移 ±jitter_ms
+}
+
+/// 定时发送任务状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: u32,
+    pub spec: ScheduleSpec,
+    pub sent: u64,
+    pub running: bool,
+    pub last_error: Option<String>,
+}
+
+struct JobHandle {
+    job: Arc<Mutex<ScheduledJob>>,
+    stop: Arc<AtomicBool>,
+}
+
+pub struct Scheduler {
+    next_id: AtomicU32,
+    jobs: Mutex<Vec<JobHandle>>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU32::new(1),
+            jobs: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 启动一个定时发送任务，返回任务编号
+    pub fn start(&self, manager: Arc<Mutex<SerialManager>>, spec: ScheduleSpec) -> Result<u32, String> {
+        if spec.interval_ms == 0 {
+            return Err("发送间隔必须大于 0".to_string());
+        }
+        if spec.count == Some(0) {
+            return Err("发送次数必须大于 0".to_string());
+        }
+
+        let bytes = if spec.hex_mode {
+            serial::parse_hex_string(&spec.payload)?
+        } else {
+            spec.payload.as_bytes().to_vec()
+        };
+        if bytes.is_empty() {
+            return Err("发送内容为空".to_string());
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let job = Arc::new(Mutex::new(ScheduledJob {
+            id,
+            spec: spec.clone(),
+            sent: 0,
+            running: true,
+            last_error: None,
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let job_thread = job.clone();
+        let stop_thread = stop.clone();
+        thread::spawn(move || {
+            run_job(manager, spec, bytes, job_thread, stop_thread);
+        });
+
+        let mut jobs = self.jobs.lock();
+        jobs.retain(|h| h.job.lock().running);
+        jobs.push(JobHandle { job, stop });
+
+        Ok(id)
+    }
+
+    pub fn stop(&self, id: u32) -> Result<(), String> {
+        let jobs = self.jobs.lock();
+        let handle = jobs
+            .iter()
+            .find(|h| h.job.lock().id == id)
+            .ok_or_else(|| format!("定时任务 {} 不存在", id))?;
+        handle.stop.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn stop_all(&self) {
+        for handle in self.jobs.lock().iter() {
+            handle.stop.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn list(&self) -> Vec<ScheduledJob> {
+        self.jobs.lock().iter().map(|h| h.job.lock().clone()).collect()
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn run_job(
+    manager: Arc<Mutex<SerialManager>>,
+    spec: ScheduleSpec,
+    bytes: Vec<u8>,
+    job: Arc<Mutex<ScheduledJob>>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut rng = rand::thread_rng();
+    let mut next = Instant::now();
+
+    while !stop.load(Ordering::SeqCst) {
+        if Instant::now() >= next {
+            if let Err(e) = manager.lock().send_payload(&bytes, spec.hex_mode) {
+                job.lock().last_error = Some(e);
+                break;
+            }
+
+            let mut j = job.lock();
+            j.sent += 1;
+            if spec.count.is_some_and(|c| j.sent >= c) {
+                break;
+            }
+            drop(j);
+
+            let jitter = if spec.jitter_ms > 0 {
+                rng.gen_range(-(spec.jitter_ms as i64)..=spec.jitter_ms as i64)
+            } else {
+                0
+            };
+            let delay = (spec.interval_ms as i64 + jitter).max(1) as u64;
+            next += Duration::from_millis(delay);
+            // 发送被阻塞太久时不追赶错过的周期
+            if next < Instant::now() {
+                next = Instant::now();
+            }
+        }
+
+        let remaining = next.saturating_duration_since(Instant::now());
+        thread::sleep(remaining.min(Duration::from_millis(20)));
+    }
+
+    job.lock().running = false;
+}
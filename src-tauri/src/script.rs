@@ -0,0 +1,365 @@
+//! send/expect 脚本运行器
+//!
+//! 脚本为纯文本，每行一个步骤，`#` 开头为注释：
+//!
+//! ```text
+//! :login                      # 标签
+//! send root\r                 # 发送文本，支持 \r \n \t \\ \xHH 转义
+//! hex 01 03 00 00 00 02       # 发送十六进制
+//! expect "Password:" 3000     # 等待匹配正则，超时毫秒（默认 5000），超时则脚本结束
+//! sleep 500                   # 延时毫秒
+//! if "Login incorrect" 1000 goto login   # 在超时内匹配则跳转，否则继续
+//! goto login                  # 无条件跳转
+//! fail 登录失败               # 以失败结束
+//! pass                        # 以通过结束
+//! ```
+
+use crate::serial::{self, SerialManager};
+use parking_lot::Mutex;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_EXPECT_TIMEOUT_MS: u64 = 5000;
+const MAX_STEPS: usize = 100_000; // 防止 goto 死循环
+const MAX_BUFFER: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Send { bytes: Vec<u8>, hex: bool },
+    Expect { pattern: Regex, timeout_ms: u64 },
+    Sleep(u64),
+    Goto(String),
+    If { pattern: Regex, timeout_ms: u64, label: String },
+    Pass,
+    Fail(String),
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    number: usize,
+    text: String,
+    step: Step,
+}
+
+#[derive(Debug, Clone)]
+pub struct Script {
+    lines: Vec<Line>,
+    labels: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    TimedOut,
+}
+
+/// 单个步骤的执行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepReport {
+    pub line: usize,
+    pub step: String,
+    pub status: StepStatus,
+    pub message: String,
+    pub elapsed_ms: u64,
+}
+
+/// 脚本最终结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptOutcome {
+    pub status: StepStatus,
+    pub message: String,
+    pub steps: usize,
+}
+
+impl Script {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("读取脚本失败: {}", e))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut lines = Vec::new();
+        let mut labels = HashMap::new();
+
+        for (i, raw) in source.lines().enumerate() {
+            let number = i + 1;
+            let text = raw.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+
+            if let Some(label) = text.strip_prefix(':') {
+                labels.insert(label.trim().to_string(), lines.len());
+                continue;
+            }
+
+            let step = parse_step(text).map_err(|e| format!("第 {} 行: {}", number, e))?;
+            lines.push(Line {
+                number,
+                text: text.to_string(),
+                step,
+            });
+        }
+
+        for line in &lines {
+            if let Step::Goto(label) | Step::If { label, .. } = &line.step {
+                if !labels.contains_key(label) {
+                    return Err(format!("第 {} 行: 未定义的标签 {}", line.number, label));
+                }
+            }
+        }
+
+        Ok(Self { lines, labels })
+    }
+}
+
+fn parse_step(text: &str) -> Result<Step, String> {
+    let (cmd, rest) = match text.split_once(char::is_whitespace) {
+        Some((c, r)) => (c, r.trim()),
+        None => (text, ""),
+    };
+
+    match cmd.to_lowercase().as_str() {
+        "send" => Ok(Step::Send { bytes: unescape(rest)?, hex: false }),
+        "hex" => Ok(Step::Send { bytes: serial::parse_hex_string(rest)?, hex: true }),
+        "expect" => {
+            let (pattern, rest) = split_pattern(rest)?;
+            let timeout_ms = parse_timeout(rest)?.unwrap_or(DEFAULT_EXPECT_TIMEOUT_MS);
+            Ok(Step::Expect { pattern, timeout_ms })
+        }
+        "sleep" => rest
+            .parse::<u64>()
+            .map(Step::Sleep)
+            .map_err(|_| format!("无效的延时: {}", rest)),
+        "goto" if !rest.is_empty() => Ok(Step::Goto(rest.to_string())),
+        "if" => {
+            let (pattern, rest) = split_pattern(rest)?;
+            let (timeout, label) = match rest.rsplit_once("goto") {
+                Some((t, l)) => (t.trim(), l.trim()),
+                None => return Err("用法: if <正则> [超时ms] goto <标签>".to_string()),
+            };
+            if label.is_empty() {
+                return Err("if 缺少跳转标签".to_string());
+            }
+            Ok(Step::If {
+                pattern,
+                timeout_ms: parse_timeout(timeout)?.unwrap_or(0),
+                label: label.to_string(),
+            })
+        }
+        "pass" => Ok(Step::Pass),
+        "fail" => Ok(Step::Fail(rest.to_string())),
+        _ => Err(format!("未知指令: {}", cmd)),
+    }
+}
+
+/// 拆出正则：支持 "带空格的正则" 或单个单词
+fn split_pattern(s: &str) -> Result<(Regex, &str), String> {
+    let (pattern, rest) = if let Some(quoted) = s.strip_prefix('"') {
+        let end = quoted.find('"').ok_or("正则缺少结束引号")?;
+        (&quoted[..end], quoted[end + 1..].trim())
+    } else {
+        match s.split_once(char::is_whitespace) {
+            Some((p, r)) => (p, r.trim()),
+            None => (s, ""),
+        }
+    };
+
+    if pattern.is_empty() {
+        return Err("缺少匹配正则".to_string());
+    }
+
+    let regex = Regex::new(pattern).map_err(|e| format!("无效的正则 {}: {}", pattern, e))?;
+    Ok((regex, rest))
+}
+
+fn parse_timeout(s: &str) -> Result<Option<u64>, String> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+    s.parse::<u64>()
+        .map(Some)
+        .map_err(|_| format!("无效的超时: {}", s))
+}
+
+/// 处理 \r \n \t \0 \\ \xHH 转义
+pub fn unescape(s: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('r') => out.push(b'\r'),
+            Some('n') => out.push(b'\n'),
+            Some('t') => out.push(b'\t'),
+            Some('0') => out.push(0),
+            Some('\\') => out.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .map_err(|_| format!("无效的转义: \\x{}", hex))?;
+                out.push(byte);
+            }
+            Some(other) => return Err(format!("无效的转义: \\{}", other)),
+            None => return Err("转义符位于末尾".to_string()),
+        }
+    }
+
+    Ok(out)
+}
+
+/// 在会话上运行脚本，每完成一步调用一次 on_step
+pub fn run_script(
+    script: &Script,
+    manager: &Arc<Mutex<SerialManager>>,
+    cancel: &AtomicBool,
+    mut on_step: impl FnMut(&StepReport),
+) -> ScriptOutcome {
+    let rx = manager.lock().tap();
+    let mut buffer = String::new();
+    let mut pc = 0;
+    let mut executed = 0;
+
+    let finish = |status, message: String, steps| ScriptOutcome {
+        status,
+        message,
+        steps,
+    };
+
+    while pc < script.lines.len() {
+        if cancel.load(Ordering::SeqCst) {
+            return finish(StepStatus::Failed, "脚本已取消".to_string(), executed);
+        }
+        if executed >= MAX_STEPS {
+            return finish(StepStatus::Failed, "执行步数超过上限，可能存在死循环".to_string(), executed);
+        }
+
+        let line = &script.lines[pc];
+        let started = Instant::now();
+        let mut next = pc + 1;
+
+        let (status, message) = match &line.step {
+            Step::Send { bytes, hex } => match manager.lock().send_payload(bytes, *hex) {
+                Ok(_) => (StepStatus::Passed, format!("已发送 {} 字节", bytes.len())),
+                Err(e) => (StepStatus::Failed, e),
+            },
+            Step::Expect { pattern, timeout_ms } => {
+                match wait_for(manager, &rx, &mut buffer, pattern, *timeout_ms, cancel) {
+                    Some(consumed) => {
+                        let matched = pattern.find(&consumed).map_or("", |m| m.as_str());
+                        (StepStatus::Passed, format!("匹配: {}", matched.trim()))
+                    }
+                    None => (StepStatus::TimedOut, format!("{}ms 内未匹配 {}", timeout_ms, pattern)),
+                }
+            }
+            Step::Sleep(ms) => {
+                sleep_cancellable(*ms, cancel);
+                (StepStatus::Passed, String::new())
+            }
+            Step::Goto(label) => {
+                next = script.labels[label];
+                (StepStatus::Passed, format!("跳转到 {}", label))
+            }
+            Step::If { pattern, timeout_ms, label } => {
+                match wait_for(manager, &rx, &mut buffer, pattern, *timeout_ms, cancel) {
+                    Some(_) => {
+                        next = script.labels[label];
+                        (StepStatus::Passed, format!("条件成立，跳转到 {}", label))
+                    }
+                    None => (StepStatus::Passed, "条件不成立".to_string()),
+                }
+            }
+            Step::Pass => (StepStatus::Passed, "脚本通过".to_string()),
+            Step::Fail(msg) => (StepStatus::Failed, msg.clone()),
+        };
+
+        executed += 1;
+        on_step(&StepReport {
+            line: line.number,
+            step: line.text.clone(),
+            status,
+            message: message.clone(),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        });
+
+        if status != StepStatus::Passed {
+            return finish(status, format!("第 {} 行: {}", line.number, message), executed);
+        }
+        if matches!(line.step, Step::Pass) {
+            break;
+        }
+
+        pc = next;
+    }
+
+    finish(StepStatus::Passed, "脚本执行完成".to_string(), executed)
+}
+
+/// 等待接收缓冲匹配正则，返回并移除截至匹配结束处的数据
+pub(crate) fn wait_for(
+    manager: &Arc<Mutex<SerialManager>>,
+    rx: &Receiver<Vec<u8>>,
+    buffer: &mut String,
+    pattern: &Regex,
+    timeout_ms: u64,
+    cancel: &AtomicBool,
+) -> Option<String> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+    loop {
+        while let Ok(chunk) = rx.try_recv() {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        if buffer.len() > MAX_BUFFER {
+            let mut cut = buffer.len() - MAX_BUFFER / 2;
+            while !buffer.is_char_boundary(cut) {
+                cut += 1;
+            }
+            buffer.drain(..cut);
+        }
+
+        if let Some(m) = pattern.find(buffer) {
+            let end = m.end();
+            return Some(buffer.drain(..end).collect());
+        }
+
+        let now = Instant::now();
+        if now >= deadline || cancel.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        // 无人轮询时（如无界面运行）也要驱动串口读取
+        let _ = manager.lock().poll();
+        match rx.recv_timeout((deadline - now).min(Duration::from_millis(10))) {
+            Ok(chunk) => buffer.push_str(&String::from_utf8_lossy(&chunk)),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+fn sleep_cancellable(ms: u64, cancel: &AtomicBool) {
+    let deadline = Instant::now() + Duration::from_millis(ms);
+    while !cancel.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        thread::sleep((deadline - now).min(Duration::from_millis(20)));
+    }
+}
@@ -0,0 +1,259 @@
+//! Rhai 脚本引擎，用于需要计算的自动化场景
+//!
+//! 脚本可用的函数：
+//!
+//! - `send(text)` / `send_hex("01 03 ...")` / `send_bytes(blob)`
+//! - `read_until(regex, timeout_ms)`：返回截至匹配处的文本，超时返回空字符串
+//! - `wait(ms)`
+//! - `set_dtr(bool)` / `set_rts(bool)`
+//! - `crc16(blob)`（Modbus）、`crc16_ccitt(blob)`、`crc32(blob)`
+//! - `to_hex(blob)` / `from_hex(text)` / `to_text(blob)`
+//! - `log(msg)` 与 `print(msg)`
+//! - `config("serial.baud_rate")`：读取配置项
+//!
+//! 接收钩子脚本需定义 `fn on_receive(text, bytes)`，每收到一段数据调用一次。
+
+use crate::checksum;
+use crate::config::AppConfig;
+use crate::script;
+use crate::serial::{self, SerialManager};
+use parking_lot::Mutex;
+use regex::Regex;
+use rhai::{Blob, Dynamic, Engine, EvalAltResult, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// 脚本日志输出回调
+pub type LogFn = Arc<dyn Fn(&str) + Send + Sync>;
+
+type RhaiResult<T> = Result<T, Box<EvalAltResult>>;
+
+fn to_rhai_err(e: String) -> Box<EvalAltResult> {
+    e.into()
+}
+
+/// 构建绑定到串口会话的脚本引擎
+pub fn build_engine(
+    manager: Arc<Mutex<SerialManager>>,
+    config: Arc<Mutex<AppConfig>>,
+    log: LogFn,
+    cancel: Arc<AtomicBool>,
+) -> Engine {
+    let mut engine = Engine::new();
+
+    // 首次 read_until 时注册接收监听，之后一直保留，避免两次读取之间到达的数据丢失
+    let reader: Mutex<Option<(Receiver<Vec<u8>>, String)>> = Mutex::new(None);
+
+    let m = manager.clone();
+    engine.register_fn("send", move |text: &str| -> RhaiResult<()> {
+        m.lock().send(text, false).map_err(to_rhai_err)
+    });
+
+    let m = manager.clone();
+    engine.register_fn("send_hex", move |hex: &str| -> RhaiResult<()> {
+        m.lock().send(hex, true).map_err(to_rhai_err)
+    });
+
+    let m = manager.clone();
+    engine.register_fn("send_bytes", move |bytes: Blob| -> RhaiResult<()> {
+        m.lock().send_bytes(&bytes).map_err(to_rhai_err)
+    });
+
+    let m = manager.clone();
+    let c = cancel.clone();
+    engine.register_fn("read_until", move |pattern: &str, timeout_ms: i64| -> RhaiResult<String> {
+        let regex = Regex::new(pattern).map_err(|e| to_rhai_err(format!("无效的正则: {}", e)))?;
+        let mut guard = reader.lock();
+        let (rx, buffer) = guard.get_or_insert_with(|| (m.lock().tap(), String::new()));
+        Ok(script::wait_for(&m, rx, buffer, &regex, timeout_ms.max(0) as u64, &c).unwrap_or_default())
+    });
+
+    let c = cancel.clone();
+    engine.register_fn("wait", move |ms: i64| {
+        let deadline = Instant::now() + Duration::from_millis(ms.max(0) as u64);
+        while !c.load(Ordering::SeqCst) && Instant::now() < deadline {
+            thread::sleep((deadline - Instant::now()).min(Duration::from_millis(20)));
+        }
+    });
+
+    let m = manager.clone();
+    engine.register_fn("set_dtr", move |level: bool| -> RhaiResult<()> {
+        m.lock().set_dtr(level).map_err(to_rhai_err)
+    });
+
+    let m = manager;
+    engine.register_fn("set_rts", move |level: bool| -> RhaiResult<()> {
+        m.lock().set_rts(level).map_err(to_rhai_err)
+    });
+
+    engine.register_fn("crc16", |data: Blob| checksum::crc16_modbus(&data) as i64);
+    engine.register_fn("crc16_ccitt", |data: Blob| checksum::crc16_ccitt(&data) as i64);
+    engine.register_fn("crc32", |data: Blob| checksum::crc32(&data) as i64);
+    engine.register_fn("to_hex", |data: Blob| serial::bytes_to_hex_string(&data));
+    engine.register_fn("to_text", |data: Blob| String::from_utf8_lossy(&data).to_string());
+    engine.register_fn("from_hex", |text: &str| -> RhaiResult<Blob> {
+        serial::parse_hex_string(text).map_err(to_rhai_err)
+    });
+
+    engine.register_fn("config", move |path: &str| -> RhaiResult<Dynamic> {
+        let value = serde_json::to_value(&*config.lock()).map_err(|e| to_rhai_err(e.to_string()))?;
+        let pointer = format!("/{}", path.replace('.', "/"));
+        match value.pointer(&pointer) {
+            Some(v) => rhai::serde::to_dynamic(v),
+            None => Ok(Dynamic::UNIT),
+        }
+    });
+
+    let l = log.clone();
+    engine.register_fn("log", move |msg: &str| l(msg));
+    let l = log.clone();
+    engine.on_print(move |msg| l(msg));
+    engine.on_debug(move |msg, _, _| log(msg));
+
+    engine.on_progress(move |_| {
+        if cancel.load(Ordering::SeqCst) {
+            Some(Dynamic::from("脚本已取消"))
+        } else {
+            None
+        }
+    });
+
+    engine
+}
+
+/// 运行一次 Rhai 脚本文件
+pub fn run_file(
+    path: &str,
+    manager: Arc<Mutex<SerialManager>>,
+    config: Arc<Mutex<AppConfig>>,
+    log: LogFn,
+    cancel: Arc<AtomicBool>,
+) -> Result<(), String> {
+    let engine = build_engine(manager, config, log, cancel);
+    engine
+        .run_file(path.into())
+        .map_err(|e| format!("脚本错误: {}", e))
+}
+
+/// 已注册的接收钩子
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookInfo {
+    pub name: String,
+    pub path: String,
+    pub calls: u64,
+    pub last_error: Option<String>,
+}
+
+struct Hook {
+    info: Arc<Mutex<HookInfo>>,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// 接收钩子：每收到一段数据就调用脚本中的 on_receive
+pub struct ScriptHooks {
+    hooks: Mutex<HashMap<String, Hook>>,
+}
+
+impl ScriptHooks {
+    pub fn new() -> Self {
+        Self {
+            hooks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn register(
+        &self,
+        name: &str,
+        path: &str,
+        manager: Arc<Mutex<SerialManager>>,
+        config: Arc<Mutex<AppConfig>>,
+        log: LogFn,
+    ) -> Result<(), String> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let engine = build_engine(manager.clone(), config, log.clone(), stop.clone());
+        let ast = engine
+            .compile_file(path.into())
+            .map_err(|e| format!("脚本错误: {}", e))?;
+        if !ast.iter_functions().any(|f| f.name == "on_receive" && f.params.len() == 2) {
+            return Err("钩子脚本需要定义 fn on_receive(text, bytes)".to_string());
+        }
+
+        let info = Arc::new(Mutex::new(HookInfo {
+            name: name.to_string(),
+            path: path.to_string(),
+            calls: 0,
+            last_error: None,
+        }));
+        let rx = manager.lock().tap();
+
+        let info_thread = info.clone();
+        let stop_thread = stop.clone();
+        let handle = thread::spawn(move || {
+            run_hook(engine, ast, rx, info_thread, stop_thread, log);
+        });
+
+        self.unregister(name);
+        self.hooks
+            .lock()
+            .insert(name.to_string(), Hook { info, stop, handle });
+        Ok(())
+    }
+
+    pub fn unregister(&self, name: &str) -> bool {
+        let hook = self.hooks.lock().remove(name);
+        match hook {
+            Some(hook) => {
+                hook.stop.store(true, Ordering::SeqCst);
+                let _ = hook.handle.join();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn list(&self) -> Vec<HookInfo> {
+        self.hooks.lock().values().map(|h| h.info.lock().clone()).collect()
+    }
+}
+
+impl Default for ScriptHooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn run_hook(
+    engine: Engine,
+    ast: AST,
+    rx: Receiver<Vec<u8>>,
+    info: Arc<Mutex<HookInfo>>,
+    stop: Arc<AtomicBool>,
+    log: LogFn,
+) {
+    let mut scope = Scope::new();
+
+    while !stop.load(Ordering::SeqCst) {
+        let chunk = match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(chunk) => chunk,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let text = String::from_utf8_lossy(&chunk).to_string();
+        let result = engine.call_fn::<Dynamic>(&mut scope, &ast, "on_receive", (text, chunk));
+
+        let mut info = info.lock();
+        info.calls += 1;
+        if let Err(e) = result {
+            let msg = format!("钩子 {} 出错: {}", info.name, e);
+            log(&msg);
+            info.last_error = Some(msg);
+        }
+    }
+}
@@ -1,8 +1,23 @@
+use crate::capture::{CaptureEvent, PortSettings};
+use crate::checksum::ChecksumAlgorithm;
+use crate::config::{AppConfig, LogConfig};
+use crate::decoder::{Decoded, Decoder, Direction};
+use crate::framing::{Deframer, Framing};
+use crate::history::SessionHistory;
+use crate::logging::{LogStatus, SessionLogger};
+use crate::nmea::{GpsFix, NmeaTracker};
+use crate::plot::{ChannelParser, Plotter};
+use crate::stats::{SessionStats, TrafficStats};
+use crate::timestamp::{TimestampMode, Timestamper};
+use crate::trigger::{TriggerAction, TriggerEngine, TriggerHit};
+use crate::zmodem::{ZmodemDetector, ZmodemRequest};
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::collections::VecDeque;
 use std::io::{Read, Write};
-use std::time::Duration;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortInfo {
@@ -12,24 +27,85 @@ pub struct PortInfo {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataEntry {
-    pub timestamp: String,
+    pub timestamp: String, // 按当前时间戳方式显示的时间
+    #[serde(default)]
+    pub time_us: i64, // 微秒时间戳
     pub data: String,
     pub hex: String,
-    pub direction: String, // "rx" or "tx"
+    pub direction: String, // "rx", "tx" 或 "marker"（触发规则插入的标记）
+    #[serde(default)]
+    pub valid: Option<bool>, // 启用接收校验时的校验结果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<String>, // 触发规则设置的高亮颜色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<Decoded>, // 选择了协议解码器时的解码结果
 }
 
 pub struct SerialManager {
     port: Option<Box<dyn SerialPort>>,
     port_name: String,
     buffer: Vec<u8>,
+    pending: VecDeque<DataEntry>, // 尚未被 read_available 取走的收发记录
+    taps: Vec<Sender<Vec<u8>>>,
+    record_entries: bool, // 为 false 时接收数据只分发给监听者，不生成记录（文件传输期间）
+    rx_checksum: ChecksumAlgorithm,
+    framing: Framing,
+    deframer: Deframer, // 接收方向未完成的帧
+    gap_frame: Option<(DateTime<Local>, Vec<u8>)>, // 未分帧时按空闲间隔累积、待校验的接收数据
+    stats: TrafficStats,
+    timestamps: Timestamper,
+    zmodem: ZmodemDetector,
+    zmodem_request: Option<ZmodemRequest>, // 接收流中检测到的 ZMODEM 启动请求，等待界面处理
+    logger: Option<SessionLogger>,
+    session_id: String, // 每次连接生成，写入抓包记录
+    settings: Option<PortSettings>,
+    history: SessionHistory,
+    triggers: TriggerEngine,
+    trigger_hits: VecDeque<TriggerHit>, // 等待界面取走的命中（显示、运行脚本）
+    trigger_log: LogConfig, // 触发规则开始记录时使用的日志设置
+    dtr_restore: Option<(Instant, bool)>,
+    decoder: Option<Box<dyn Decoder>>,
+    nmea: NmeaTracker, // 接收数据中的 NMEA 语句汇总出的定位状态
+    plot: Plotter,
 }
 
+// 未被取走的触发命中最多保留条数
+const MAX_TRIGGER_HITS: usize = 256;
+
+// 未被取走的收发记录最多保留条数，超出后丢弃最早的记录并计入统计
+const MAX_PENDING: usize = 10_000;
+
+// 按空闲间隔累积的接收数据超过该长度时直接作为一帧
+const MAX_GAP_FRAME: usize = 64 * 1024;
+
 impl SerialManager {
     pub fn new() -> Self {
         Self {
             port: None,
             port_name: String::new(),
             buffer: Vec::with_capacity(4096),
+            pending: VecDeque::new(),
+            taps: Vec::new(),
+            record_entries: true,
+            rx_checksum: ChecksumAlgorithm::None,
+            framing: Framing::None,
+            deframer: Deframer::default(),
+            gap_frame: None,
+            stats: TrafficStats::default(),
+            timestamps: Timestamper::default(),
+            zmodem: ZmodemDetector::default(),
+            zmodem_request: None,
+            logger: None,
+            session_id: String::new(),
+            settings: None,
+            history: SessionHistory::default(),
+            triggers: TriggerEngine::default(),
+            trigger_hits: VecDeque::new(),
+            trigger_log: LogConfig::default(),
+            dtr_restore: None,
+            decoder: None,
+            nmea: NmeaTracker::default(),
+            plot: Plotter::default(),
         }
     }
 
@@ -45,6 +121,14 @@ impl SerialManager {
             self.disconnect()?;
         }
 
+        let settings = PortSettings {
+            port: port_name.to_string(),
+            baud_rate,
+            data_bits,
+            stop_bits,
+            parity: parity.to_lowercase(),
+        };
+
         let data_bits = match data_bits {
             5 => DataBits::Five,
             6 => DataBits::Six,
@@ -77,15 +161,32 @@ impl SerialManager {
 
         self.port = Some(port);
         self.port_name = port_name.to_string();
+        self.session_id = format!("{:08x}", rand::random::<u32>());
+        self.settings = Some(settings);
         self.buffer.clear();
+        self.deframer.reset();
+        self.gap_frame = None;
+        self.nmea.reset();
+        self.stats.start();
+        self.timestamps.start(Local::now());
+        self.triggers.reset();
 
         Ok(())
     }
 
     pub fn disconnect(&mut self) -> Result<(), String> {
+        self.log_event(CaptureEvent::Disconnect);
+        let _ = self.stop_logging();
         self.port = None;
+        self.settings = None;
         self.port_name.clear();
         self.buffer.clear();
+        self.pending.clear();
+        self.gap_frame = None;
+        self.zmodem_request = None;
+        self.trigger_hits.clear();
+        self.dtr_restore = None;
+        self.stats.stop();
         Ok(())
     }
 
@@ -93,52 +194,449 @@ impl SerialManager {
         self.port.is_some()
     }
 
-    pub fn send(&mut self, data: &str, hex_mode: bool) -> Result<(), String> {
+    pub fn rx_checksum(&self) -> ChecksumAlgorithm {
+        self.rx_checksum
+    }
+
+    pub fn set_rx_checksum(&mut self, algorithm: ChecksumAlgorithm) {
+        self.rx_checksum = algorithm;
+        self.log_event(CaptureEvent::Settings {
+            key: "rx_checksum".to_string(),
+            value: algorithm.name().to_string(),
+        });
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// 设置分帧方式：接收数据按帧去除转义后逐帧记录，十六进制发送时自动转义
+    pub fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+        self.deframer.reset();
+        self.log_event(CaptureEvent::Settings {
+            key: "framing".to_string(),
+            value: framing.name().to_string(),
+        });
+    }
+
+    pub fn timestamp_mode(&self) -> TimestampMode {
+        self.timestamps.mode()
+    }
+
+    /// 设置时间戳显示方式，对之后的收发记录生效
+    pub fn set_timestamp_mode(&mut self, mode: TimestampMode) {
+        self.timestamps.set_mode(mode);
+        if let Some(logger) = self.logger.as_mut() {
+            logger.set_timestamp_mode(mode);
+        }
+        self.log_event(CaptureEvent::Settings {
+            key: "timestamp".to_string(),
+            value: mode.name().to_string(),
+        });
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// 本次连接（或上次重置）以来的流量统计
+    pub fn stats(&self) -> SessionStats {
+        self.stats.snapshot()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    pub fn set_dtr(&mut self, level: bool) -> Result<(), String> {
+        let port = self.port.as_mut().ok_or("串口未连接")?;
+        port.write_data_terminal_ready(level)
+            .map_err(|e| format!("设置 DTR 失败: {}", e))?;
+        self.log_event(CaptureEvent::ControlLine {
+            line: "dtr".to_string(),
+            level,
+        });
+        Ok(())
+    }
+
+    pub fn set_rts(&mut self, level: bool) -> Result<(), String> {
         let port = self.port.as_mut().ok_or("串口未连接")?;
+        port.write_request_to_send(level)
+            .map_err(|e| format!("设置 RTS 失败: {}", e))?;
+        self.log_event(CaptureEvent::ControlLine {
+            line: "rts".to_string(),
+            level,
+        });
+        Ok(())
+    }
+
+    /// 暂停/恢复生成收发记录，文件传输时避免二进制数据刷屏
+    pub fn set_record_entries(&mut self, enabled: bool) {
+        self.record_entries = enabled;
+    }
+
+    /// 开始将收发记录写入日志文件，返回文件路径
+    pub fn start_logging(&mut self, config: &LogConfig) -> Result<String, String> {
+        if self.port.is_none() {
+            return Err("串口未连接".to_string());
+        }
+        let _ = self.stop_logging();
+
+        let mut logger = SessionLogger::start(config, &self.port_name, &self.session_id)?;
+        logger.set_timestamp_mode(self.timestamps.mode());
+        // 先记录当前串口参数，使日志可以独立解读
+        if let Some(settings) = &self.settings {
+            logger.write_event(CaptureEvent::Connect(settings.clone()));
+        }
+        if self.rx_checksum != ChecksumAlgorithm::None {
+            logger.write_event(CaptureEvent::Settings {
+                key: "rx_checksum".to_string(),
+                value: self.rx_checksum.name().to_string(),
+            });
+        }
+
+        let path = logger.status().path;
+        self.logger = Some(logger);
+        Ok(path)
+    }
+
+    /// 停止记录，结尾写入本次会话的流量统计
+    pub fn stop_logging(&mut self) -> Option<Result<LogStatus, String>> {
+        let stats = self.stats.snapshot();
+        self.logger.take().map(|mut logger| {
+            logger.write_event(CaptureEvent::Stats(stats));
+            logger.finish()
+        })
+    }
+
+    pub fn logging_status(&self) -> Option<LogStatus> {
+        self.logger.as_ref().map(|l| l.status())
+    }
+
+    fn log_event(&mut self, event: CaptureEvent) {
+        if let Some(logger) = self.logger.as_mut() {
+            logger.write_event(event);
+        }
+    }
+
+    pub fn history(&self) -> &SessionHistory {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut SessionHistory {
+        &mut self.history
+    }
+
+    // 新的收发记录：写入日志与历史，并放入待取队列
+    fn record(&mut self, mut entry: DataEntry, raw: &[u8]) {
+        if let Some(decoder) = &self.decoder {
+            let direction = if entry.direction == "tx" { Direction::Tx } else { Direction::Rx };
+            entry.decoded = decoder.decode(raw, direction).map(|fields| Decoded {
+                decoder: decoder.name(),
+                fields,
+            });
+        }
+        if let Some(logger) = self.logger.as_mut() {
+            logger.write_entry(&entry);
+        }
+        self.history.push(&entry, raw, entry.time_us / 1000);
+        self.push_pending(entry);
+    }
+
+    fn push_pending(&mut self, entry: DataEntry) {
+        if self.pending.len() >= MAX_PENDING {
+            if let Some(dropped) = self.pending.pop_front() {
+                self.stats.dropped(dropped.hex.len().div_ceil(3));
+            }
+        }
+        self.pending.push_back(entry);
+    }
+
+    /// 设置协议解码器，之后的收发记录附带解码结果，None 关闭解码
+    pub fn set_decoder(&mut self, decoder: Option<Box<dyn Decoder>>) {
+        self.decoder = decoder;
+    }
+
+    pub fn decoder_name(&self) -> Option<String> {
+        self.decoder.as_ref().map(|d| d.name())
+    }
+
+    /// 接收数据中 NMEA 语句汇总出的当前定位状态
+    pub fn gps_fix(&self) -> &GpsFix {
+        self.nmea.fix()
+    }
+
+    pub fn reset_gps(&mut self) {
+        self.nmea.reset();
+    }
+
+    /// 设置数值通道解析器，None 停止提取
+    pub fn set_plot_parser(&mut self, parser: Option<ChannelParser>) {
+        self.plot.set_parser(parser);
+    }
+
+    /// 从接收数据中提取的数值通道
+    pub fn plot(&self) -> &Plotter {
+        &self.plot
+    }
 
-        let bytes = if hex_mode {
-            parse_hex_string(data)?
-        } else {
-            data.as_bytes().to_vec()
+    pub fn plot_mut(&mut self) -> &mut Plotter {
+        &mut self.plot
+    }
+
+    /// 加载配置中的触发规则，规则无效时保留原有规则
+    pub fn set_triggers(&mut self, config: &AppConfig) -> Result<(), String> {
+        self.triggers = TriggerEngine::new(&config.triggers)?;
+        self.trigger_log = config.logging.clone();
+        Ok(())
+    }
+
+    /// 取走触发规则的命中记录
+    pub fn take_trigger_hits(&mut self) -> Vec<TriggerHit> {
+        self.trigger_hits.drain(..).collect()
+    }
+
+    /// 插入一条标记，显示在收发记录中并写入日志
+    pub fn mark(&mut self, text: &str) {
+        self.log_event(CaptureEvent::Marker { text: text.to_string() });
+        let now = Local::now();
+        let entry = DataEntry {
+            timestamp: self.timestamps.stamp(now),
+            time_us: now.timestamp_micros(),
+            data: text.to_string(),
+            hex: String::new(),
+            direction: "marker".to_string(),
+            valid: None,
+            highlight: None,
+            decoded: None,
         };
+        self.history.push(&entry, text.as_bytes(), now.timestamp_millis());
+        self.push_pending(entry);
+    }
+
+    // 执行命中规则的动作（脚本除外，见 trigger::run_scripts）
+    fn run_trigger(&mut self, hit: &TriggerHit) {
+        for action in &hit.actions {
+            let result = match action {
+                TriggerAction::Reply { hex, .. } => match action.reply_bytes() {
+                    Some(Ok(bytes)) => self.send_payload(&bytes, *hex),
+                    Some(Err(e)) => Err(e),
+                    None => Ok(()),
+                },
+                TriggerAction::Marker { text } => {
+                    self.mark(text);
+                    Ok(())
+                }
+                TriggerAction::StartLog if self.logger.is_none() => {
+                    let config = self.trigger_log.clone();
+                    self.start_logging(&config).map(|_| ())
+                }
+                TriggerAction::StopLog => match self.stop_logging() {
+                    Some(result) => result.map(|_| ()),
+                    None => Ok(()),
+                },
+                TriggerAction::PulseDtr { level, ms } => {
+                    let restore = Instant::now() + Duration::from_millis(*ms);
+                    self.dtr_restore = Some((restore, !level));
+                    self.set_dtr(*level)
+                }
+                // 高亮在记录生成时处理，脚本由界面运行
+                TriggerAction::Highlight { .. } | TriggerAction::StartLog | TriggerAction::RunScript { .. } => Ok(()),
+            };
+            if let Err(e) = result {
+                self.mark(&format!("触发规则 {} 执行失败: {}", hit.rule, e));
+            }
+        }
+
+        if self.trigger_hits.len() >= MAX_TRIGGER_HITS {
+            self.trigger_hits.pop_front();
+        }
+        self.trigger_hits.push_back(hit.clone());
+    }
+
+    /// 取走检测到的 ZMODEM 启动请求
+    pub fn take_zmodem_request(&mut self) -> Option<ZmodemRequest> {
+        self.zmodem_request.take()
+    }
+
+    /// 直接写入串口，不生成 TX 记录
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let port = self.port.as_mut().ok_or("串口未连接")?;
+        port.write_all(bytes)
+            .map_err(|e| format!("发送失败: {}", e))?;
+        port.flush()
+            .map_err(|e| format!("发送失败: {}", e))?;
+        self.stats.tx(bytes.len());
+        Ok(())
+    }
+
+    /// 发送文本或十六进制数据。设置了分帧方式时，十六进制数据作为一帧内容自动转义
+    pub fn send(&mut self, data: &str, hex_mode: bool) -> Result<(), String> {
+        if !hex_mode {
+            return self.send_bytes(data.as_bytes());
+        }
+
+        self.send_payload(&parse_hex_string(data)?, true)
+    }
+
+    /// 发送已解析的数据。hex 为真时作为十六进制负载，与 send 一样经过分帧层
+    pub fn send_payload(&mut self, payload: &[u8], hex: bool) -> Result<(), String> {
+        if !hex {
+            return self.send_bytes(payload);
+        }
+        let wire = self.framing.encode(payload);
+        self.transmit(&wire, payload)
+    }
+
+    /// 发送原始字节（不经分帧），并记录一条 TX 数据，随下一次 read_available 返回
+    pub fn send_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.transmit(bytes, bytes)
+    }
+
+    // 写入 wire，记录中显示 payload（分帧时为转义前的内容，与接收记录一致）
+    fn transmit(&mut self, wire: &[u8], bytes: &[u8]) -> Result<(), String> {
+        let port = self.port.as_mut().ok_or("串口未连接")?;
 
-        port.write_all(&bytes)
+        port.write_all(wire)
             .map_err(|e| format!("发送失败: {}", e))?;
+        self.stats.tx(wire.len());
+        self.stats.tx_frame();
+
+        let now: DateTime<Local> = Local::now();
+        let timestamp = self.timestamps.stamp(now);
+        self.record(DataEntry {
+            timestamp,
+            time_us: now.timestamp_micros(),
+            data: String::from_utf8_lossy(bytes).to_string(),
+            hex: bytes_to_hex_string(bytes),
+            direction: "tx".to_string(),
+            valid: None,
+            highlight: None,
+            decoded: None,
+        }, bytes);
 
         Ok(())
     }
 
-    pub fn read_available(&mut self) -> Result<Vec<DataEntry>, String> {
-        let port = match self.port.as_mut() {
-            Some(p) => p,
-            None => return Ok(vec![]),
-        };
+    /// 注册接收数据监听，返回的通道会收到之后读到的每一段原始数据。
+    /// 丢弃接收端即自动注销
+    pub fn tap(&mut self) -> Receiver<Vec<u8>> {
+        let (tx, rx) = mpsc::channel();
+        self.taps.push(tx);
+        rx
+    }
 
+    /// 从串口读取当前可用数据，放入待取队列并分发给监听者
+    pub fn poll(&mut self) -> Result<(), String> {
         let mut temp_buf = [0u8; 1024];
-        let mut entries = Vec::new();
+
+        if let Some((due, level)) = self.dtr_restore {
+            if Instant::now() >= due {
+                self.dtr_restore = None;
+                self.set_dtr(level)?;
+            }
+        }
 
         loop {
+            let port = match self.port.as_mut() {
+                Some(p) => p,
+                None => return Ok(()),
+            };
+
             match port.read(&mut temp_buf) {
                 Ok(0) => break,
                 Ok(n) => {
                     let now: DateTime<Local> = Local::now();
-                    let timestamp = now.format("%H:%M:%S%.3f").to_string();
                     let data_slice = &temp_buf[..n];
-                    
-                    entries.push(DataEntry {
-                        timestamp,
-                        data: String::from_utf8_lossy(data_slice).to_string(),
-                        hex: bytes_to_hex_string(data_slice),
-                        direction: "rx".to_string(),
-                    });
+                    self.stats.rx(n);
+
+                    self.taps.retain(|tap| tap.send(data_slice.to_vec()).is_ok());
+                    if !self.record_entries {
+                        continue;
+                    }
+
+                    if let Some(request) = self.zmodem.feed(data_slice) {
+                        self.zmodem_request = Some(request);
+                    }
+
+                    // 启用接收校验但未分帧时，以空闲间隔（读取超时）切分，跨多次读取的帧整体校验
+                    if self.framing == Framing::None && self.rx_checksum != ChecksumAlgorithm::None {
+                        let (_, frame) = self.gap_frame.get_or_insert_with(|| (now, Vec::new()));
+                        frame.extend_from_slice(data_slice);
+                        if frame.len() >= MAX_GAP_FRAME {
+                            self.flush_gap_frame();
+                        }
+                        continue;
+                    }
+
+                    // 未设置分帧时每次读到的数据为一帧
+                    for frame in self.deframer.feed(self.framing, data_slice) {
+                        self.receive_frame(&frame.data, frame.valid, now);
+                    }
                 }
                 Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => break,
                 Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
-                Err(e) => return Err(format!("读取错误: {}", e)),
+                Err(e) => {
+                    self.stats.read_error();
+                    return Err(format!("读取错误: {}", e));
+                }
             }
         }
 
-        Ok(entries)
+        self.flush_gap_frame();
+        Ok(())
+    }
+
+    fn flush_gap_frame(&mut self) {
+        if let Some((time, data)) = self.gap_frame.take() {
+            self.receive_frame(&data, true, time);
+        }
+    }
+
+    // 一帧接收数据：校验、送入各解析器与触发规则，生成 RX 记录
+    fn receive_frame(&mut self, data: &[u8], well_formed: bool, now: DateTime<Local>) {
+        let valid = if well_formed { self.rx_checksum.verify(data) } else { Some(false) };
+        self.stats.rx_frame(valid);
+
+        self.nmea.feed(data);
+        self.plot.feed(data, now.timestamp_millis());
+        let hits = self.triggers.feed(data);
+        let highlight = hits
+            .iter()
+            .flat_map(|h| &h.actions)
+            .find_map(|a| match a {
+                TriggerAction::Highlight { color } => Some(color.clone()),
+                _ => None,
+            });
+
+        let timestamp = self.timestamps.stamp(now);
+        self.record(DataEntry {
+            timestamp,
+            time_us: now.timestamp_micros(),
+            data: String::from_utf8_lossy(data).to_string(),
+            hex: bytes_to_hex_string(data),
+            direction: "rx".to_string(),
+            valid,
+            highlight,
+            decoded: None,
+        }, data);
+
+        for hit in &hits {
+            self.run_trigger(hit);
+        }
+    }
+
+    /// 读取串口并取走所有待显示的收发记录
+    pub fn read_available(&mut self) -> Result<Vec<DataEntry>, String> {
+        self.poll()?;
+        Ok(self.pending.drain(..).collect())
+    }
+}
+
+impl Default for SerialManager {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -166,23 +664,33 @@ pub fn list_available_ports() -> Result<Vec<PortInfo>, String> {
         .collect())
 }
 
-fn parse_hex_string(s: &str) -> Result<Vec<u8>, String> {
+pub fn parse_hex_string(s: &str) -> Result<Vec<u8>, String> {
     let s = s.replace(" ", "").replace("\n", "").replace("\r", "");
     
-    if s.len() % 2 != 0 {
+    // 先逐字符检查，非 ASCII 字符不能按字节切片
+    if let Some(c) = s.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("无效的十六进制字符: {}", c));
+    }
+    if !s.len().is_multiple_of(2) {
         return Err("十六进制字符串长度必须为偶数".to_string());
     }
 
-    (0..s.len())
-        .step_by(2)
-        .map(|i| {
-            u8::from_str_radix(&s[i..i + 2], 16)
-                .map_err(|_| format!("无效的十六进制字符: {}", &s[i..i + 2]))
-        })
-        .collect()
+    Ok(s.as_bytes()
+        .chunks(2)
+        .map(|pair| (hex_digit(pair[0]) << 4) | hex_digit(pair[1]))
+        .collect())
+}
+
+// 已确认是十六进制数字
+fn hex_digit(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        _ => c - b'A' + 10,
+    }
 }
 
-fn bytes_to_hex_string(bytes: &[u8]) -> String {
+pub fn bytes_to_hex_string(bytes: &[u8]) -> String {
     bytes
         .iter()
         .map(|b| format!("{:02X}", b))
@@ -0,0 +1,475 @@
+//! 设备模拟器：按应答表回应上位机的请求，可在串口、虚拟串口或 TCP 端口上提供服务
+//!
+//! 应答表为 JSON 文件，例如：
+//!
+//! ```json
+//! {
+//!   "initial_state": "idle",
+//!   "rules": [
+//!     {"pattern": "AT\r", "responses": [{"data": "\r\nOK\r\n"}]},
+//!     {"pattern": "AT\\+CPIN=(\\d+)\r", "kind": "regex", "state": "idle", "next_state": "ready",
+//!      "responses": [{"data": "\r\nOK\r\n", "delay_ms": 200}, {"data": "\r\n+CPIN: READY $1\r\n", "delay_ms": 500}]},
+//!     {"pattern": "01 03 ?? ?? 00 01 ?? ??", "kind": "hex", "responses": [{"data": "01 03 02 00 2A 38 5B", "hex": true}]}
+//!   ]
+//! }
+//! ```
+//!
+//! 文本应答可用 $1、${name} 引用捕获组；十六进制应答中 $1 这样的单独一项替换为捕获到的字节。
+
+use crate::history;
+use crate::serial;
+use chrono::{Local, SecondsFormat};
+use regex::bytes::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimTable {
+    #[serde(default)]
+    pub initial_state: String,
+    #[serde(default = "default_idle_ms")]
+    pub idle_ms: u64, // 收到数据后空闲这么久仍未匹配，视为无法识别的请求
+    pub rules: Vec<SimRule>,
+}
+
+fn default_idle_ms() -> u64 {
+    200
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternKind {
+    #[default]
+    Text,
+    Hex, // 十六进制字节序列，?? 匹配任意字节
+    Regex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimRule {
+    #[serde(default)]
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub kind: PatternKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>, // 仅在此状态下匹配，为空时任何状态
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_state: Option<String>,
+    #[serde(default)]
+    pub responses: Vec<SimResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimResponse {
+    pub data: String,
+    #[serde(default)]
+    pub hex: bool,
+    #[serde(default)]
+    pub delay_ms: u64, // 相对上一条应答（或请求）的延时
+}
+
+impl SimTable {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("读取应答表 {} 失败: {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("解析应答表失败: {}", e))
+    }
+}
+
+/// 模拟器运行选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimOptions {
+    /// 无法匹配的请求以规则草稿的形式追加到此文件（JSON Lines），补全应答后可直接粘贴到应答表
+    pub unmatched_path: Option<String>,
+}
+
+/// 模拟器事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SimEvent {
+    Client { addr: String, connected: bool },
+    Matched { rule: String, request: String, responses: usize, state: String },
+    Unmatched { request: String, state: String },
+}
+
+/// 模拟器服务端口
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimTarget {
+    Port { name: String, baud_rate: u32 },
+    /// 新建虚拟串口对，上位机打开返回的从端（仅 Unix）
+    Pty,
+    /// 监听 TCP 地址，如 "127.0.0.1:5000"，同时只服务一个客户端
+    Tcp { addr: String },
+}
+
+pub struct SimPort {
+    name: String,
+    io: SimIo,
+}
+
+enum SimIo {
+    Serial(Box<dyn SerialPort>),
+    #[cfg(unix)]
+    Pty {
+        master: serialport::TTYPort,
+        _slave: serialport::TTYPort,
+    },
+    Tcp {
+        listener: TcpListener,
+        client: Option<TcpStream>,
+    },
+}
+
+const READ_TIMEOUT: Duration = Duration::from_millis(10);
+
+impl SimPort {
+    pub fn open(target: &SimTarget) -> Result<Self, String> {
+        match target {
+            SimTarget::Port { name, baud_rate } => {
+                let port = serialport::new(name, *baud_rate)
+                    .timeout(READ_TIMEOUT)
+                    .open()
+                    .map_err(|e| format!("无法打开串口 {}: {}", name, e))?;
+                Ok(Self {
+                    name: name.clone(),
+                    io: SimIo::Serial(port),
+                })
+            }
+            #[cfg(unix)]
+            SimTarget::Pty => {
+                let (mut master, slave) =
+                    serialport::TTYPort::pair().map_err(|e| format!("创建虚拟串口失败: {}", e))?;
+                master
+                    .set_timeout(READ_TIMEOUT)
+                    .map_err(|e| format!("创建虚拟串口失败: {}", e))?;
+                Ok(Self {
+                    name: slave.name().unwrap_or_default(),
+                    io: SimIo::Pty { master, _slave: slave },
+                })
+            }
+            #[cfg(not(unix))]
+            SimTarget::Pty => Err("当前平台不支持虚拟串口".to_string()),
+            SimTarget::Tcp { addr } => {
+                let listener = TcpListener::bind(addr).map_err(|e| format!("监听 {} 失败: {}", addr, e))?;
+                listener
+                    .set_nonblocking(true)
+                    .map_err(|e| format!("监听 {} 失败: {}", addr, e))?;
+                let name = listener
+                    .local_addr()
+                    .map(|a| format!("tcp://{}", a))
+                    .unwrap_or_else(|_| addr.clone());
+                Ok(Self {
+                    name,
+                    io: SimIo::Tcp { listener, client: None },
+                })
+            }
+        }
+    }
+
+    /// 服务端口名称，虚拟串口时为上位机应打开的设备路径
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 读取数据，超时返回 0。TCP 模式下顺带处理客户端连接与断开
+    pub fn read(&mut self, buf: &mut [u8], on_event: &mut dyn FnMut(&SimEvent)) -> Result<usize, String> {
+        let result = match &mut self.io {
+            SimIo::Serial(port) => port.read(buf),
+            #[cfg(unix)]
+            SimIo::Pty { master, .. } => master.read(buf),
+            SimIo::Tcp { listener, client } => {
+                let stream = match client {
+                    Some(stream) => stream,
+                    None => match listener.accept() {
+                        Ok((stream, addr)) => {
+                            stream
+                                .set_nonblocking(false)
+                                .and_then(|_| stream.set_read_timeout(Some(READ_TIMEOUT)))
+                                .map_err(|e| format!("接受连接失败: {}", e))?;
+                            on_event(&SimEvent::Client {
+                                addr: addr.to_string(),
+                                connected: true,
+                            });
+                            client.insert(stream)
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                            thread::sleep(READ_TIMEOUT);
+                            return Ok(0);
+                        }
+                        Err(e) => return Err(format!("接受连接失败: {}", e)),
+                    },
+                };
+                match stream.read(buf) {
+                    Ok(n) if n > 0 => return Ok(n),
+                    Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => return Ok(0),
+                    // 读到结尾或出错均视为客户端断开，继续等待下一个连接
+                    _ => {
+                        let addr = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+                        *client = None;
+                        on_event(&SimEvent::Client { addr, connected: false });
+                        return Ok(0);
+                    }
+                }
+            }
+        };
+
+        match result {
+            Ok(n) => Ok(n),
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => Ok(0),
+            Err(e) => Err(format!("读取错误: {}", e)),
+        }
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        let result = match &mut self.io {
+            SimIo::Serial(port) => port.write_all(data),
+            #[cfg(unix)]
+            SimIo::Pty { master, .. } => master.write_all(data),
+            // 客户端已断开时丢弃应答
+            SimIo::Tcp { client, .. } => match client {
+                Some(stream) => stream.write_all(data),
+                None => Ok(()),
+            },
+        };
+        result.map_err(|e| format!("发送失败: {}", e))
+    }
+}
+
+struct CompiledRule {
+    rule: SimRule,
+    regex: Regex,
+}
+
+/// 应答表匹配状态机，与服务端口无关
+pub struct Simulator {
+    rules: Vec<CompiledRule>,
+    state: String,
+    buffer: Vec<u8>,
+}
+
+/// 一次成功匹配
+pub struct SimMatch {
+    pub rule: String,
+    pub request: Vec<u8>,
+    pub responses: Vec<(Duration, Vec<u8>)>,
+}
+
+// 待匹配数据上限，超过后作为无法识别的请求丢弃
+const MAX_BUFFER: usize = 4096;
+
+impl Simulator {
+    pub fn new(table: &SimTable) -> Result<Self, String> {
+        let rules = table
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                let name = if rule.name.is_empty() { format!("#{}", i + 1) } else { rule.name.clone() };
+                let regex = match rule.kind {
+                    PatternKind::Text => history::compile_pattern(&regex::escape(&rule.pattern), false),
+                    PatternKind::Hex => history::compile_pattern(&rule.pattern, true),
+                    PatternKind::Regex => history::compile_pattern(&rule.pattern, false),
+                }
+                .map_err(|e| format!("规则 {}: {}", name, e))?;
+                Ok(CompiledRule {
+                    rule: SimRule { name, ..rule.clone() },
+                    regex,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            rules,
+            state: table.initial_state.clone(),
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// 追加收到的数据，返回所有完整匹配的请求。返回的第二项为匹配位置之前无法识别的数据
+    pub fn feed(&mut self, data: &[u8]) -> Result<(Vec<SimMatch>, Vec<Vec<u8>>), String> {
+        self.buffer.extend_from_slice(data);
+        let mut matches = Vec::new();
+        let mut unmatched = Vec::new();
+
+        while let Some((index, start, end)) = self.find_next() {
+            if start > 0 {
+                unmatched.push(self.buffer[..start].to_vec());
+            }
+            let request = self.buffer[start..end].to_vec();
+            let compiled = &self.rules[index];
+            // 在整个缓冲区上从匹配位置取分组，使 ^、\b 等断言与查找时一致
+            let Some(caps) = compiled.regex.captures_at(&self.buffer, start) else {
+                break;
+            };
+
+            let responses = compiled
+                .rule
+                .responses
+                .iter()
+                .map(|r| Ok((Duration::from_millis(r.delay_ms), render(r, &caps)?)))
+                .collect::<Result<Vec<_>, String>>()
+                .map_err(|e| format!("规则 {} 的应答无效: {}", compiled.rule.name, e))?;
+
+            matches.push(SimMatch {
+                rule: compiled.rule.name.clone(),
+                request: request.clone(),
+                responses,
+            });
+            if let Some(next) = &compiled.rule.next_state {
+                self.state = next.clone();
+            }
+            self.buffer.drain(..end);
+        }
+
+        if self.buffer.len() > MAX_BUFFER {
+            unmatched.push(std::mem::take(&mut self.buffer));
+        }
+        Ok((matches, unmatched))
+    }
+
+    /// 取走尚未匹配的数据（空闲超时时调用）
+    pub fn take_pending(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+
+    // 当前状态下最早出现的匹配，位置相同时取表中靠前的规则
+    fn find_next(&self) -> Option<(usize, usize, usize)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| match c.rule.state.as_deref() {
+                None | Some("") => true,
+                Some(state) => state == self.state,
+            })
+            .filter_map(|(i, c)| {
+                c.regex
+                    .find_iter(&self.buffer)
+                    .find(|m| m.end() > m.start())
+                    .map(|m| (i, m.start(), m.end()))
+            })
+            .min_by_key(|&(i, start, _)| (start, i))
+    }
+}
+
+fn render(response: &SimResponse, caps: &Captures) -> Result<Vec<u8>, String> {
+    if !response.hex {
+        let mut out = Vec::new();
+        caps.expand(response.data.as_bytes(), &mut out);
+        return Ok(out);
+    }
+
+    let mut out = Vec::new();
+    for item in response.data.split_whitespace() {
+        match item.strip_prefix('$') {
+            Some(group) => {
+                let group = group.trim_start_matches('{').trim_end_matches('}');
+                let m = match group.parse::<usize>() {
+                    Ok(i) => caps.get(i),
+                    Err(_) => caps.name(group),
+                };
+                out.extend_from_slice(m.map(|m| m.as_bytes()).unwrap_or_default());
+            }
+            None => out.extend(serial::parse_hex_string(item)?),
+        }
+    }
+    Ok(out)
+}
+
+/// 运行模拟器直到取消
+pub fn run_simulator(
+    table: &SimTable,
+    port: &mut SimPort,
+    options: &SimOptions,
+    cancel: &AtomicBool,
+    mut on_event: impl FnMut(&SimEvent),
+) -> Result<(), String> {
+    let mut sim = Simulator::new(table)?;
+    let idle = Duration::from_millis(table.idle_ms.max(1));
+    let mut buf = [0u8; 1024];
+    let mut last_rx = Instant::now();
+
+    while !cancel.load(Ordering::SeqCst) {
+        let n = port.read(&mut buf, &mut on_event)?;
+        let unmatched = if n > 0 {
+            last_rx = Instant::now();
+            let (matches, unmatched) = sim.feed(&buf[..n])?;
+            for m in matches {
+                for (delay, data) in &m.responses {
+                    if !sleep_cancellable(*delay, cancel) {
+                        return Ok(());
+                    }
+                    port.write(data)?;
+                }
+                on_event(&SimEvent::Matched {
+                    rule: m.rule,
+                    request: serial::bytes_to_hex_string(&m.request),
+                    responses: m.responses.len(),
+                    state: sim.state().to_string(),
+                });
+            }
+            unmatched
+        } else if last_rx.elapsed() >= idle {
+            vec![sim.take_pending()]
+        } else {
+            Vec::new()
+        };
+
+        for request in unmatched {
+            // 单独的换行多为上一条请求的结尾，不视为新请求
+            if request.is_empty() || request.iter().all(|b| matches!(b, b'\r' | b'\n')) {
+                continue;
+            }
+            if let Some(path) = &options.unmatched_path {
+                record_unmatched(path, &request, sim.state())?;
+            }
+            on_event(&SimEvent::Unmatched {
+                request: serial::bytes_to_hex_string(&request),
+                state: sim.state().to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn sleep_cancellable(delay: Duration, cancel: &AtomicBool) -> bool {
+    let deadline = Instant::now() + delay;
+    while Instant::now() < deadline {
+        if cancel.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep((deadline - Instant::now()).min(Duration::from_millis(20)));
+    }
+    !cancel.load(Ordering::SeqCst)
+}
+
+// 追加一条规则草稿，补全 responses 后即可放入应答表
+fn record_unmatched(path: &str, request: &[u8], state: &str) -> Result<(), String> {
+    let rule = SimRule {
+        name: format!("unmatched {}", Local::now().to_rfc3339_opts(SecondsFormat::Millis, false)),
+        pattern: serial::bytes_to_hex_string(request),
+        kind: PatternKind::Hex,
+        state: (!state.is_empty()).then(|| state.to_string()),
+        next_state: None,
+        responses: Vec::new(),
+    };
+    let line = serde_json::to_string(&rule).map_err(|e| format!("序列化记录失败: {}", e))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("打开 {} 失败: {}", path, e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("写入 {} 失败: {}", path, e))
+}
@@ -0,0 +1,231 @@
+//! 会话流量统计：收发字节与帧数、当前与峰值吞吐量、连接时长、读取错误、丢弃数据与校验失败。
+//! 连接时清零，也可手动重置；停止记录日志时作为结尾写入日志。
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// 统计快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_frames: u64, // 接收记录数（分帧时为帧数）
+    pub tx_frames: u64,
+    pub bad_frames: u64, // 接收校验或分帧格式错误
+    pub read_errors: u64,
+    pub dropped_entries: u64, // 待取队列已满时丢弃的收发记录
+    pub dropped_bytes: u64,
+    pub rx_rate: f64, // 最近一秒，字节/秒
+    pub tx_rate: f64,
+    pub peak_rx_rate: f64,
+    pub peak_tx_rate: f64,
+    pub connected_secs: u64,
+    pub since: String, // 统计开始时间（连接或重置）
+}
+
+// 按整秒窗口统计吞吐量：窗口满一秒才结算，期间空闲的秒按 0 计
+#[derive(Debug)]
+struct RateMeter {
+    window_start: Instant,
+    window_bytes: u64,
+    rate: f64, // 最近一个完整窗口
+    peak: f64,
+}
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+impl RateMeter {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            window_bytes: 0,
+            rate: 0.0,
+            peak: 0.0,
+        }
+    }
+
+    fn add(&mut self, bytes: usize, now: Instant) {
+        let windows = self.elapsed_windows(now);
+        if windows > 0 {
+            let finished = self.window_rate();
+            self.peak = self.peak.max(finished);
+            self.rate = if windows == 1 { finished } else { 0.0 };
+            self.window_start += RATE_WINDOW * windows;
+            self.window_bytes = 0;
+        }
+        self.window_bytes += bytes as u64;
+    }
+
+    // 已结束但还没有新数据结算的窗口同样计入
+    fn current(&self, now: Instant) -> f64 {
+        match self.elapsed_windows(now) {
+            0 => self.rate,
+            1 => self.window_rate(),
+            _ => 0.0,
+        }
+    }
+
+    fn peak(&self, now: Instant) -> f64 {
+        if self.elapsed_windows(now) > 0 {
+            self.peak.max(self.window_rate())
+        } else {
+            self.peak
+        }
+    }
+
+    // 当前窗口开始后完整经过的窗口数
+    fn elapsed_windows(&self, now: Instant) -> u32 {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        (elapsed.as_nanos() / RATE_WINDOW.as_nanos()) as u32
+    }
+
+    fn window_rate(&self) -> f64 {
+        self.window_bytes as f64 / RATE_WINDOW.as_secs_f64()
+    }
+}
+
+/// 累计中的统计
+#[derive(Debug)]
+pub struct TrafficStats {
+    counters: SessionStats,
+    rx: RateMeter,
+    tx: RateMeter,
+    connected_at: Option<Instant>,
+    connected_for: Duration, // 断开后保留的连接时长
+}
+
+impl Default for TrafficStats {
+    fn default() -> Self {
+        Self {
+            counters: SessionStats {
+                since: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                ..SessionStats::default()
+            },
+            rx: RateMeter::new(),
+            tx: RateMeter::new(),
+            connected_at: None,
+            connected_for: Duration::ZERO,
+        }
+    }
+}
+
+impl TrafficStats {
+    /// 连接时调用：清零并开始计时
+    pub fn start(&mut self) {
+        *self = Self {
+            connected_at: Some(Instant::now()),
+            ..Self::default()
+        };
+    }
+
+    /// 断开时调用：停止计时，统计保留到下次连接
+    pub fn stop(&mut self) {
+        if let Some(at) = self.connected_at.take() {
+            self.connected_for = at.elapsed();
+        }
+    }
+
+    /// 清零计数与峰值，连接时长不变
+    pub fn reset(&mut self) {
+        *self = Self {
+            connected_at: self.connected_at,
+            connected_for: self.connected_for,
+            ..Self::default()
+        };
+    }
+
+    pub fn rx(&mut self, bytes: usize) {
+        self.counters.rx_bytes += bytes as u64;
+        self.rx.add(bytes, Instant::now());
+    }
+
+    pub fn tx(&mut self, bytes: usize) {
+        self.counters.tx_bytes += bytes as u64;
+        self.tx.add(bytes, Instant::now());
+    }
+
+    /// 一条接收记录，valid 为校验结果
+    pub fn rx_frame(&mut self, valid: Option<bool>) {
+        self.counters.rx_frames += 1;
+        if valid == Some(false) {
+            self.counters.bad_frames += 1;
+        }
+    }
+
+    pub fn tx_frame(&mut self) {
+        self.counters.tx_frames += 1;
+    }
+
+    pub fn read_error(&mut self) {
+        self.counters.read_errors += 1;
+    }
+
+    pub fn dropped(&mut self, bytes: usize) {
+        self.counters.dropped_entries += 1;
+        self.counters.dropped_bytes += bytes as u64;
+    }
+
+    pub fn snapshot(&self) -> SessionStats {
+        let now = Instant::now();
+        let connected = self.connected_at.map(|at| now - at).unwrap_or(self.connected_for);
+        SessionStats {
+            rx_rate: self.rx.current(now),
+            tx_rate: self.tx.current(now),
+            peak_rx_rate: self.rx.peak(now),
+            peak_tx_rate: self.tx.peak(now),
+            connected_secs: connected.as_secs(),
+            ..self.counters.clone()
+        }
+    }
+}
+
+/// 字节数显示为 B/KB/MB
+pub fn format_bytes(bytes: f64) -> String {
+    if bytes < 1024.0 {
+        format!("{:.0} B", bytes)
+    } else if bytes < 1024.0 * 1024.0 {
+        format!("{:.1} KB", bytes / 1024.0)
+    } else {
+        format!("{:.2} MB", bytes / 1024.0 / 1024.0)
+    }
+}
+
+/// 秒数显示为 时:分:秒
+pub fn format_duration(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_gap_does_not_dilute_peak() {
+        let mut meter = RateMeter::new();
+        let t0 = meter.window_start;
+        let at = |ms: u64| t0 + Duration::from_millis(ms);
+
+        meter.add(1000, at(100));
+        meter.add(500, at(900));
+        assert_eq!(meter.current(at(1500)), 1500.0);
+
+        // 空闲 10 秒后再收到数据
+        meter.add(10, at(11_200));
+        assert_eq!(meter.peak(at(11_300)), 1500.0);
+        assert_eq!(meter.current(at(11_300)), 0.0);
+        assert_eq!(meter.current(at(12_100)), 10.0);
+        assert_eq!(meter.current(at(13_100)), 0.0);
+    }
+
+    #[test]
+    fn steady_rate() {
+        let mut meter = RateMeter::new();
+        let t0 = meter.window_start;
+        for i in 0..50 {
+            meter.add(100, t0 + Duration::from_millis(i * 100));
+        }
+        assert_eq!(meter.current(t0 + Duration::from_millis(5050)), 1000.0);
+        assert_eq!(meter.peak(t0 + Duration::from_millis(5050)), 1000.0);
+    }
+}
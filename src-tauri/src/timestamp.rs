@@ -0,0 +1,115 @@
+//! 时间戳显示方式：完整日期时间、仅时刻、与上一条记录的间隔、连接以来的时长，均精确到微秒。
+//!
+//! 每条收发记录保存微秒时间戳，显示用的字符串按当前方式生成，界面、命令行与文本日志一致。
+
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// 时间戳显示方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampMode {
+    DateTime, // 2024-05-01T10:00:00.123456+08:00
+    #[default]
+    Time, // 10:00:00.123456
+    Delta,   // +0.001234，与上一条记录间隔的秒数
+    Elapsed, // +00:01:02.123456，连接以来的时长
+}
+
+impl TimestampMode {
+    pub const ALL: [TimestampMode; 4] = [
+        TimestampMode::DateTime,
+        TimestampMode::Time,
+        TimestampMode::Delta,
+        TimestampMode::Elapsed,
+    ];
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "datetime" | "date" | "iso" => Ok(TimestampMode::DateTime),
+            "" | "time" => Ok(TimestampMode::Time),
+            "delta" | "diff" => Ok(TimestampMode::Delta),
+            "elapsed" => Ok(TimestampMode::Elapsed),
+            _ => Err(format!("未知时间戳方式: {} (可选 datetime、time、delta、elapsed)", s)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimestampMode::DateTime => "datetime",
+            TimestampMode::Time => "time",
+            TimestampMode::Delta => "delta",
+            TimestampMode::Elapsed => "elapsed",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            TimestampMode::DateTime => "完整日期时间",
+            TimestampMode::Time => "仅时刻",
+            TimestampMode::Delta => "与上一条的间隔",
+            TimestampMode::Elapsed => "连接以来的时长",
+        }
+    }
+}
+
+/// 按当前方式为依次产生的记录生成时间戳
+#[derive(Debug, Default)]
+pub struct Timestamper {
+    mode: TimestampMode,
+    origin: Option<DateTime<Local>>,   // 连接时刻
+    previous: Option<DateTime<Local>>, // 上一条记录的时刻
+}
+
+impl Timestamper {
+    pub fn mode(&self) -> TimestampMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TimestampMode) {
+        self.mode = mode;
+    }
+
+    /// 连接时调用，作为 elapsed 的起点
+    pub fn start(&mut self, at: DateTime<Local>) {
+        self.origin = Some(at);
+        self.previous = None;
+    }
+
+    /// 生成一条记录的时间戳，并作为下一条 delta 的参照
+    pub fn stamp(&mut self, time: DateTime<Local>) -> String {
+        let text = format(self.mode, time, self.previous, self.origin);
+        self.previous = Some(time);
+        text
+    }
+}
+
+/// 按方式格式化时间。previous 为空时 delta 为 0，origin 为空时 elapsed 为 0
+pub fn format(
+    mode: TimestampMode,
+    time: DateTime<Local>,
+    previous: Option<DateTime<Local>>,
+    origin: Option<DateTime<Local>>,
+) -> String {
+    let micros_since = |from: Option<DateTime<Local>>| {
+        from.and_then(|f| (time - f).num_microseconds()).unwrap_or(0).max(0)
+    };
+    match mode {
+        TimestampMode::DateTime => time.format("%Y-%m-%dT%H:%M:%S%.6f%:z").to_string(),
+        TimestampMode::Time => time.format("%H:%M:%S%.6f").to_string(),
+        TimestampMode::Delta => {
+            let us = micros_since(previous);
+            format!("+{}.{:06}", us / 1_000_000, us % 1_000_000)
+        }
+        TimestampMode::Elapsed => {
+            let us = micros_since(origin);
+            let secs = us / 1_000_000;
+            format!("+{:02}:{:02}:{:02}.{:06}", secs / 3600, secs / 60 % 60, secs % 60, us % 1_000_000)
+        }
+    }
+}
+
+/// 微秒时间戳转为本地时间
+pub fn from_micros(us: i64) -> DateTime<Local> {
+    Local.timestamp_micros(us).single().unwrap_or_else(Local::now)
+}
@@ -0,0 +1,151 @@
+//! 文件传输协议的公共部分：基于串口会话的字节链路、进度汇报与传输请求分发
+
+use crate::rawsend::{self, RawSendOptions};
+use crate::serial::SerialManager;
+use crate::xmodem;
+use crate::zmodem;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 传输进度
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferProgress {
+    pub protocol: String,
+    pub file: String,
+    pub bytes: u64,
+    pub total: Option<u64>,
+    pub blocks: u32,
+    pub retries: u32,
+}
+
+pub type ProgressFn<'a> = &'a mut dyn FnMut(&TransferProgress);
+
+/// 传输请求，REPL 与 GUI 共用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TransferRequest {
+    XmodemSend { path: String, one_k: bool },
+    XmodemReceive { path: String, crc: bool },
+    YmodemSend { paths: Vec<String> },
+    YmodemReceive { dir: String },
+    ZmodemSend { paths: Vec<String> },
+    ZmodemReceive { dir: String },
+    RawSend {
+        path: String,
+        #[serde(default)]
+        options: RawSendOptions,
+    },
+}
+
+/// 执行传输。协议传输期间暂停生成收发记录，原始发送时保留设备回显
+pub fn run_transfer(
+    request: &TransferRequest,
+    manager: &Arc<Mutex<SerialManager>>,
+    cancel: &AtomicBool,
+    progress: ProgressFn,
+) -> Result<(), String> {
+    let mut link = SessionLink::new(manager, cancel);
+    let quiet = !matches!(request, TransferRequest::RawSend { .. });
+    if quiet {
+        manager.lock().set_record_entries(false);
+    }
+
+    let result = match request {
+        TransferRequest::XmodemSend { path, one_k } => xmodem::send_xmodem(&mut link, path, *one_k, progress),
+        TransferRequest::XmodemReceive { path, crc } => xmodem::receive_xmodem(&mut link, path, *crc, progress),
+        TransferRequest::YmodemSend { paths } => xmodem::send_ymodem(&mut link, paths, progress),
+        TransferRequest::YmodemReceive { dir } => xmodem::receive_ymodem(&mut link, dir, progress),
+        TransferRequest::ZmodemSend { paths } => zmodem::send_zmodem(&mut link, paths, progress),
+        TransferRequest::ZmodemReceive { dir } => zmodem::receive_zmodem(&mut link, dir, progress),
+        TransferRequest::RawSend { path, options } => rawsend::send_raw(&mut link, path, options, progress),
+    };
+
+    if quiet {
+        manager.lock().set_record_entries(true);
+    }
+    result
+}
+
+/// 串口会话上的字节链路
+pub struct SessionLink<'a> {
+    manager: &'a Arc<Mutex<SerialManager>>,
+    rx: Receiver<Vec<u8>>,
+    buffer: VecDeque<u8>,
+    cancel: &'a AtomicBool,
+}
+
+impl<'a> SessionLink<'a> {
+    pub fn new(manager: &'a Arc<Mutex<SerialManager>>, cancel: &'a AtomicBool) -> Self {
+        let rx = manager.lock().tap();
+        Self {
+            manager,
+            rx,
+            buffer: VecDeque::new(),
+            cancel,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.manager.lock().write_raw(data)
+    }
+
+    /// 发送并生成 TX 记录，用于需要显示在收发记录中的请求（如 Modbus）
+    pub fn send(&mut self, data: &[u8]) -> Result<(), String> {
+        self.manager.lock().send_bytes(data)
+    }
+
+    /// 读取一个字节，超时返回 None
+    pub fn read_byte(&mut self, timeout: Duration) -> Result<Option<u8>, String> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(b) = self.buffer.pop_front() {
+                return Ok(Some(b));
+            }
+            if self.is_cancelled() {
+                return Err("传输已取消".to_string());
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+
+            // 无人轮询时也要驱动串口读取
+            self.manager.lock().poll()?;
+            match self.rx.recv_timeout((deadline - now).min(Duration::from_millis(10))) {
+                Ok(chunk) => self.buffer.extend(chunk),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Err("串口已断开".to_string()),
+            }
+        }
+    }
+
+    /// 读取 n 个字节，每个字节等待不超过 timeout
+    pub fn read_exact(&mut self, n: usize, timeout: Duration) -> Result<Option<Vec<u8>>, String> {
+        let mut out = Vec::with_capacity(n);
+        while out.len() < n {
+            match self.read_byte(timeout)? {
+                Some(b) => out.push(b),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(out))
+    }
+
+    /// 丢弃输入直到线路安静 quiet 时长
+    pub fn purge(&mut self, quiet: Duration) -> Result<(), String> {
+        self.buffer.clear();
+        while self.read_byte(quiet)?.is_some() {}
+        Ok(())
+    }
+}
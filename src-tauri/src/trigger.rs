@@ -0,0 +1,223 @@
+//! 触发规则：接收数据匹配正则或字节模式时执行动作（自动回复、插入标记、高亮、启停日志、DTR 脉冲、运行脚本）
+//!
+//! 规则保存在配置的 triggers 中，例如：
+//!
+//! ```json
+//! {"name":"login","pattern":"login:\\s*$","actions":[{"action":"reply","data":"root","newline":"crlf"}]}
+//! {"name":"panic","pattern":"Kernel panic","actions":[{"action":"highlight","color":"red"},{"action":"start_log"}]}
+//! {"name":"modbus","pattern":"01 03 ?? ??","hex":true,"actions":[{"action":"marker","text":"读保持寄存器"}]}
+//! ```
+
+use crate::config::AppConfig;
+use crate::history;
+use crate::scripting::{self, LogFn};
+use crate::serial::{self, SerialManager};
+use parking_lot::Mutex;
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::thread;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerRule {
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub pattern: String,
+    #[serde(default)]
+    pub hex: bool, // pattern 为十六进制字节序列，?? 匹配任意字节
+    pub actions: Vec<TriggerAction>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TriggerAction {
+    /// 发送回复，文本回复可用 $1、${name} 引用捕获组
+    Reply {
+        data: String,
+        #[serde(default)]
+        hex: bool,
+        #[serde(default)]
+        newline: String, // "none", "crlf", "lf", "cr"
+    },
+    /// 在收发记录中插入一条标记
+    Marker { text: String },
+    /// 高亮触发的这条接收记录
+    Highlight { color: String },
+    StartLog,
+    StopLog,
+    /// 将 DTR 置为 level，ms 毫秒后恢复
+    PulseDtr {
+        #[serde(default)]
+        level: bool,
+        #[serde(default = "default_pulse_ms")]
+        ms: u64,
+    },
+    /// 后台运行 Rhai 脚本
+    RunScript { path: String },
+}
+
+fn default_pulse_ms() -> u64 {
+    100
+}
+
+impl TriggerAction {
+    /// 回复动作实际发送的字节
+    pub fn reply_bytes(&self) -> Option<Result<Vec<u8>, String>> {
+        let TriggerAction::Reply { data, hex, newline } = self else {
+            return None;
+        };
+        let bytes = if *hex {
+            serial::parse_hex_string(data)
+        } else {
+            let mut bytes = data.as_bytes().to_vec();
+            match newline.as_str() {
+                "crlf" => bytes.extend_from_slice(b"\r\n"),
+                "lf" => bytes.push(b'\n'),
+                "cr" => bytes.push(b'\r'),
+                _ => {}
+            }
+            Ok(bytes)
+        };
+        Some(bytes)
+    }
+}
+
+/// 一次规则命中，Reply 的 data 已展开捕获组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerHit {
+    pub rule: String,
+    pub matched: String,
+    pub actions: Vec<TriggerAction>,
+}
+
+// 保留的接收数据上限，匹配可跨越多次读取，但不超过这个长度
+const MAX_BUFFER: usize = 4096;
+
+struct CompiledRule {
+    rule: TriggerRule,
+    regex: Regex,
+    scanned: usize, // buffer 中此位置之前的内容已匹配过
+}
+
+/// 在接收流上执行规则，由 SerialManager 在接收路径中调用
+#[derive(Default)]
+pub struct TriggerEngine {
+    rules: Vec<CompiledRule>,
+    buffer: Vec<u8>,
+}
+
+impl TriggerEngine {
+    pub fn new(rules: &[TriggerRule]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .filter(|r| r.enabled)
+            .map(|r| {
+                let regex = history::compile_pattern(&r.pattern, r.hex)
+                    .map_err(|e| format!("触发规则 {}: {}", r.name, e))?;
+                Ok(CompiledRule {
+                    rule: r.clone(),
+                    regex,
+                    scanned: 0,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            rules,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 清空缓存的接收数据（重新连接时）
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        for rule in &mut self.rules {
+            rule.scanned = 0;
+        }
+    }
+
+    /// 追加接收数据并返回新的命中，同一段数据对每条规则只触发一次
+    pub fn feed(&mut self, data: &[u8]) -> Vec<TriggerHit> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        self.buffer.extend_from_slice(data);
+
+        let mut hits = Vec::new();
+        for compiled in &mut self.rules {
+            let haystack = &self.buffer[compiled.scanned..];
+            let mut consumed = 0;
+            for caps in compiled.regex.captures_iter(haystack) {
+                let m = caps.get(0).expect("第 0 组总是存在");
+                if m.end() == m.start() {
+                    continue;
+                }
+                consumed = m.end();
+                hits.push(TriggerHit {
+                    rule: compiled.rule.name.clone(),
+                    matched: String::from_utf8_lossy(m.as_bytes()).to_string(),
+                    actions: compiled.rule.actions.iter().map(|a| expand(a, &caps)).collect(),
+                });
+            }
+            compiled.scanned += consumed;
+        }
+
+        if self.buffer.len() > MAX_BUFFER {
+            let excess = self.buffer.len() - MAX_BUFFER;
+            self.buffer.drain(..excess);
+            for rule in &mut self.rules {
+                rule.scanned = rule.scanned.saturating_sub(excess);
+            }
+        }
+        hits
+    }
+}
+
+fn expand(action: &TriggerAction, caps: &regex::bytes::Captures) -> TriggerAction {
+    match action {
+        TriggerAction::Reply { data, hex: false, newline } => {
+            let mut out = Vec::new();
+            caps.expand(data.as_bytes(), &mut out);
+            TriggerAction::Reply {
+                data: String::from_utf8_lossy(&out).to_string(),
+                hex: false,
+                newline: newline.clone(),
+            }
+        }
+        other => other.clone(),
+    }
+}
+
+/// 运行命中中的脚本动作。脚本需要完整的会话与配置，因此不在接收路径中执行，
+/// 由取走命中记录的界面调用
+pub fn run_scripts(
+    hits: &[TriggerHit],
+    manager: &Arc<Mutex<SerialManager>>,
+    config: &Arc<Mutex<AppConfig>>,
+    log: &LogFn,
+) {
+    for hit in hits {
+        for action in &hit.actions {
+            if let TriggerAction::RunScript { path } = action {
+                let (path, manager, config, log) = (path.clone(), manager.clone(), config.clone(), log.clone());
+                let name = hit.rule.clone();
+                thread::spawn(move || {
+                    let cancel = Arc::new(AtomicBool::new(false));
+                    if let Err(e) = scripting::run_file(&path, manager, config, log.clone(), cancel) {
+                        log(&format!("触发规则 {} 的脚本出错: {}", name, e));
+                    }
+                });
+            }
+        }
+    }
+}
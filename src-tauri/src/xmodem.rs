@@ -0,0 +1,409 @@
+//! XMODEM（校验和 / CRC / 1K）与 YMODEM 批量传输
+
+use crate::checksum::crc16_xmodem;
+use crate::transfer::{ProgressFn, SessionLink, TransferProgress};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const SOH: u8 = 0x01;
+const STX: u8 = 0x02;
+const EOT: u8 = 0x04;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+const CAN: u8 = 0x18;
+const CRC_REQ: u8 = b'C';
+const PAD: u8 = 0x1A;
+
+const MAX_RETRIES: u32 = 10;
+const START_TIMEOUT: Duration = Duration::from_secs(60);
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+const BYTE_TIMEOUT: Duration = Duration::from_secs(1);
+const BLOCK_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckMode {
+    Checksum,
+    Crc,
+}
+
+impl CheckMode {
+    fn len(self) -> usize {
+        match self {
+            CheckMode::Checksum => 1,
+            CheckMode::Crc => 2,
+        }
+    }
+
+    fn compute(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            CheckMode::Checksum => vec![data.iter().fold(0u8, |acc, b| acc.wrapping_add(*b))],
+            CheckMode::Crc => crc16_xmodem(data).to_be_bytes().to_vec(),
+        }
+    }
+}
+
+fn cancel_remote(link: &mut SessionLink) {
+    let _ = link.write(&[CAN; 8]);
+}
+
+// ============ 发送 ============
+
+/// 等待接收方发出 'C' 或 NAK，确定校验方式
+fn wait_for_start(link: &mut SessionLink) -> Result<CheckMode, String> {
+    let mut cans = 0;
+    loop {
+        match link.read_byte(START_TIMEOUT)? {
+            Some(CRC_REQ) => return Ok(CheckMode::Crc),
+            Some(NAK) => return Ok(CheckMode::Checksum),
+            Some(CAN) => {
+                cans += 1;
+                if cans >= 2 {
+                    return Err("接收方取消了传输".to_string());
+                }
+            }
+            Some(_) => cans = 0,
+            None => return Err("等待接收方启动超时".to_string()),
+        }
+    }
+}
+
+/// 发送一个数据块并等待 ACK
+fn send_block(
+    link: &mut SessionLink,
+    number: u8,
+    data: &[u8],
+    size: usize,
+    mode: CheckMode,
+    progress: &mut TransferProgress,
+    on_progress: &mut ProgressFn,
+) -> Result<(), String> {
+    let mut payload = data.to_vec();
+    payload.resize(size, PAD);
+
+    let mut packet = Vec::with_capacity(size + 5);
+    packet.push(if size == 1024 { STX } else { SOH });
+    packet.push(number);
+    packet.push(!number);
+    packet.extend_from_slice(&payload);
+    packet.extend(mode.compute(&payload));
+
+    for attempt in 0..=MAX_RETRIES {
+        if attempt > 0 {
+            progress.retries += 1;
+            on_progress(progress);
+        }
+
+        link.write(&packet)?;
+
+        let mut cans = 0;
+        loop {
+            match link.read_byte(ACK_TIMEOUT) {
+                Ok(Some(ACK)) => return Ok(()),
+                Ok(Some(NAK)) | Ok(None) => break,
+                Ok(Some(CAN)) => {
+                    cans += 1;
+                    if cans >= 2 {
+                        return Err("接收方取消了传输".to_string());
+                    }
+                }
+                // 'C' 在 YMODEM 中紧随块 0 的 ACK 出现，此处忽略
+                Ok(Some(_)) => {}
+                Err(e) => {
+                    cancel_remote(link);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    cancel_remote(link);
+    Err(format!("数据块 {} 重试次数过多", number))
+}
+
+/// 发送 EOT 直到收到 ACK。YMODEM 接收方通常先回 NAK 再回 ACK
+fn send_eot(link: &mut SessionLink) -> Result<(), String> {
+    for _ in 0..MAX_RETRIES {
+        link.write(&[EOT])?;
+        if link.read_byte(ACK_TIMEOUT)? == Some(ACK) {
+            return Ok(());
+        }
+    }
+    Err("结束传输时未收到确认".to_string())
+}
+
+fn send_file_blocks(
+    link: &mut SessionLink,
+    data: &[u8],
+    block_size: usize,
+    mode: CheckMode,
+    progress: &mut TransferProgress,
+    on_progress: &mut ProgressFn,
+) -> Result<(), String> {
+    let mut number: u8 = 1;
+    for chunk in data.chunks(block_size) {
+        // 最后一块较小时用 128 字节块，减少填充
+        let size = if chunk.len() <= 128 { 128 } else { block_size };
+        send_block(link, number, chunk, size, mode, progress, on_progress)?;
+
+        number = number.wrapping_add(1);
+        progress.blocks += 1;
+        progress.bytes += chunk.len() as u64;
+        on_progress(progress);
+    }
+    Ok(())
+}
+
+pub fn send_xmodem(link: &mut SessionLink, path: &str, one_k: bool, mut on_progress: ProgressFn) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let mut progress = TransferProgress {
+        protocol: if one_k { "XMODEM-1K" } else { "XMODEM" }.to_string(),
+        file: path.to_string(),
+        total: Some(data.len() as u64),
+        ..Default::default()
+    };
+
+    let mode = wait_for_start(link)?;
+    // XMODEM-1K 需要 CRC 模式
+    let block_size = if one_k && mode == CheckMode::Crc { 1024 } else { 128 };
+
+    send_file_blocks(link, &data, block_size, mode, &mut progress, &mut on_progress)?;
+    send_eot(link)
+}
+
+/// YMODEM 块 0：文件名\0大小 [修改时间]，调用方以 0 填充
+fn header_block(path: &Path, size: u64) -> Vec<u8> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mtime = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut block = name.into_bytes();
+    block.push(0);
+    block.extend(format!("{} {:o}", size, mtime).into_bytes());
+    block
+}
+
+pub fn send_ymodem(link: &mut SessionLink, paths: &[String], mut on_progress: ProgressFn) -> Result<(), String> {
+    for path in paths {
+        let data = fs::read(path).map_err(|e| format!("读取文件 {} 失败: {}", path, e))?;
+        let mut progress = TransferProgress {
+            protocol: "YMODEM".to_string(),
+            file: path.clone(),
+            total: Some(data.len() as u64),
+            ..Default::default()
+        };
+
+        wait_for_start(link)?;
+        let mut header = header_block(Path::new(path), data.len() as u64);
+        let size = if header.len() > 128 { 1024 } else { 128 };
+        header.resize(size, 0);
+        send_block(link, 0, &header, size, CheckMode::Crc, &mut progress, &mut on_progress)?;
+
+        wait_for_start(link)?;
+        send_file_blocks(link, &data, 1024, CheckMode::Crc, &mut progress, &mut on_progress)?;
+        send_eot(link)?;
+    }
+
+    // 空的块 0 表示批量传输结束
+    wait_for_start(link)?;
+    let mut progress = TransferProgress {
+        protocol: "YMODEM".to_string(),
+        ..Default::default()
+    };
+    send_block(link, 0, &[0; 128], 128, CheckMode::Crc, &mut progress, &mut on_progress)
+}
+
+// ============ 接收 ============
+
+enum Received {
+    Block(u8, Vec<u8>),
+    Eot,
+}
+
+/// 请求启动并接收第一个数据块。XMODEM 的 CRC 请求三次无响应后退回校验和模式
+fn start_receive(link: &mut SessionLink, crc: bool, ymodem: bool) -> Result<(Received, CheckMode), String> {
+    let mut mode = if crc { CheckMode::Crc } else { CheckMode::Checksum };
+
+    for attempt in 0..MAX_RETRIES {
+        if !ymodem && mode == CheckMode::Crc && attempt == 3 {
+            mode = CheckMode::Checksum;
+        }
+        link.write(&[if mode == CheckMode::Crc { CRC_REQ } else { NAK }])?;
+
+        if let Some(received) = read_block(link, mode, BLOCK_TIMEOUT)? {
+            return Ok((received, mode));
+        }
+    }
+
+    Err("发送方无响应".to_string())
+}
+
+/// 读取一个数据块。超时或校验失败返回 None（调用方负责 NAK）
+fn read_block(link: &mut SessionLink, mode: CheckMode, timeout: Duration) -> Result<Option<Received>, String> {
+    let size = match link.read_byte(timeout)? {
+        Some(SOH) => 128,
+        Some(STX) => 1024,
+        Some(EOT) => return Ok(Some(Received::Eot)),
+        Some(CAN) => {
+            if link.read_byte(BYTE_TIMEOUT)? == Some(CAN) {
+                return Err("发送方取消了传输".to_string());
+            }
+            return Ok(None);
+        }
+        _ => return Ok(None),
+    };
+
+    let body = match link.read_exact(2 + size + mode.len(), BYTE_TIMEOUT)? {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+
+    let (number, complement) = (body[0], body[1]);
+    let data = &body[2..2 + size];
+    let check = &body[2 + size..];
+
+    if number != !complement || mode.compute(data) != check {
+        link.purge(BYTE_TIMEOUT)?;
+        return Ok(None);
+    }
+
+    Ok(Some(Received::Block(number, data.to_vec())))
+}
+
+/// 接收数据块直到 EOT。ymodem 为 true 时首个 EOT 回 NAK
+fn receive_blocks(
+    link: &mut SessionLink,
+    first: Option<Received>,
+    mode: CheckMode,
+    ymodem: bool,
+    progress: &mut TransferProgress,
+    on_progress: &mut ProgressFn,
+) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    let mut expected: u8 = 1;
+    let mut errors = 0;
+    let mut eot_seen = false;
+    let mut next = first;
+
+    loop {
+        let received = match next.take() {
+            Some(r) => Some(r),
+            None => read_block(link, mode, ACK_TIMEOUT)?,
+        };
+
+        match received {
+            Some(Received::Block(number, block)) if number == expected => {
+                data.extend_from_slice(&block);
+                expected = expected.wrapping_add(1);
+                errors = 0;
+                link.write(&[ACK])?;
+
+                progress.blocks += 1;
+                progress.bytes = data.len() as u64;
+                on_progress(progress);
+            }
+            // 重复块（ACK 丢失），确认后丢弃
+            Some(Received::Block(number, _)) if number == expected.wrapping_sub(1) => {
+                link.write(&[ACK])?;
+            }
+            Some(Received::Block(number, _)) => {
+                cancel_remote(link);
+                return Err(format!("数据块序号错误: 期望 {}，收到 {}", expected, number));
+            }
+            Some(Received::Eot) => {
+                if ymodem && !eot_seen {
+                    eot_seen = true;
+                    link.write(&[NAK])?;
+                    continue;
+                }
+                link.write(&[ACK])?;
+                return Ok(data);
+            }
+            None => {
+                errors += 1;
+                progress.retries += 1;
+                on_progress(progress);
+                if errors > MAX_RETRIES {
+                    cancel_remote(link);
+                    return Err("接收错误次数过多".to_string());
+                }
+                link.write(&[NAK])?;
+            }
+        }
+    }
+}
+
+pub fn receive_xmodem(link: &mut SessionLink, path: &str, crc: bool, mut on_progress: ProgressFn) -> Result<(), String> {
+    let mut progress = TransferProgress {
+        protocol: "XMODEM".to_string(),
+        file: path.to_string(),
+        ..Default::default()
+    };
+
+    let (first, mode) = start_receive(link, crc, false)?;
+    let mut data = receive_blocks(link, Some(first), mode, false, &mut progress, &mut on_progress)?;
+
+    // XMODEM 不携带文件长度，去掉末尾填充
+    while data.last() == Some(&PAD) {
+        data.pop();
+    }
+
+    fs::write(path, data).map_err(|e| format!("保存文件失败: {}", e))
+}
+
+pub fn receive_ymodem(link: &mut SessionLink, dir: &str, mut on_progress: ProgressFn) -> Result<(), String> {
+    loop {
+        let (header, _) = start_receive(link, true, true)?;
+        let header = match header {
+            Received::Block(0, block) => block,
+            _ => {
+                cancel_remote(link);
+                return Err("未收到 YMODEM 文件头".to_string());
+            }
+        };
+        link.write(&[ACK])?;
+
+        // 空文件名表示批量传输结束
+        let name_end = header.iter().position(|&b| b == 0).unwrap_or(header.len());
+        if name_end == 0 {
+            return Ok(());
+        }
+        let name = String::from_utf8_lossy(&header[..name_end]).to_string();
+        let size = header
+            .get(name_end + 1..)
+            .unwrap_or_default()
+            .split(|&b| b == b' ' || b == 0)
+            .next()
+            .and_then(|s| std::str::from_utf8(s).ok())
+            .and_then(|s| s.parse::<u64>().ok());
+
+        // 只保留文件名部分，防止写到目标目录之外
+        let file_name = Path::new(&name)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| format!("无效的文件名: {}", name))?;
+        let target = Path::new(dir).join(&file_name);
+
+        let mut progress = TransferProgress {
+            protocol: "YMODEM".to_string(),
+            file: target.to_string_lossy().to_string(),
+            total: size,
+            ..Default::default()
+        };
+
+        let (first, mode) = start_receive(link, true, true)?;
+        let mut data = receive_blocks(link, Some(first), mode, true, &mut progress, &mut on_progress)?;
+        if let Some(size) = size {
+            data.truncate(size as usize);
+        }
+
+        fs::write(&target, data).map_err(|e| format!("保存文件 {} 失败: {}", file_name, e))?;
+    }
+}
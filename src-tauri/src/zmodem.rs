@@ -0,0 +1,830 @@
+//! ZMODEM 文件传输，支持断点续传与接收流中的自动启动检测
+
+use crate::checksum::{crc16_xmodem, crc32};
+use crate::transfer::{ProgressFn, SessionLink, TransferProgress};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const ZPAD: u8 = b'*';
+const ZDLE: u8 = 0x18;
+const ZBIN: u8 = b'A';
+const ZHEX: u8 = b'B';
+const ZBIN32: u8 = b'C';
+const XON: u8 = 0x11;
+const XOFF: u8 = 0x13;
+
+// 帧类型
+const ZRQINIT: u8 = 0;
+const ZRINIT: u8 = 1;
+const ZSINIT: u8 = 2;
+const ZACK: u8 = 3;
+const ZFILE: u8 = 4;
+const ZSKIP: u8 = 5;
+const ZNAK: u8 = 6;
+const ZABORT: u8 = 7;
+const ZFIN: u8 = 8;
+const ZRPOS: u8 = 9;
+const ZDATA: u8 = 10;
+const ZEOF: u8 = 11;
+const ZFERR: u8 = 12;
+const ZCRC: u8 = 13;
+const ZCHALLENGE: u8 = 14;
+const ZCOMPL: u8 = 15;
+const ZCAN: u8 = 16;
+const ZCOMMAND: u8 = 18;
+
+// 数据子包结束标记
+const ZCRCE: u8 = b'h';
+const ZCRCG: u8 = b'i';
+const ZCRCQ: u8 = b'j';
+const ZCRCW: u8 = b'k';
+const ZRUB0: u8 = b'l';
+const ZRUB1: u8 = b'm';
+
+// ZRINIT 能力标志 (ZF0)
+const CANFDX: u8 = 0x01;
+const CANOVIO: u8 = 0x02;
+const CANFC32: u8 = 0x20;
+
+// ZFILE 转换选项 (ZF0)：断点续传
+const ZCRESUM: u8 = 3;
+
+const SUBPACKET_SIZE: usize = 1024;
+const WINDOW: usize = 8; // 每发送 WINDOW 个子包等待一次确认
+const MAX_SUBPACKET: usize = 8192;
+const MAX_RETRIES: u32 = 10;
+const HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+const BYTE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 接收流中检测到的 ZMODEM 启动请求
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZmodemRequest {
+    /// 对端运行了 sz，等待本端接收
+    Receive,
+    /// 对端运行了 rz，等待本端发送
+    Send,
+}
+
+/// 在接收流中查找 ZRQINIT / ZRINIT 十六进制帧头
+#[derive(Debug, Default)]
+pub struct ZmodemDetector {
+    window: Vec<u8>,
+}
+
+impl ZmodemDetector {
+    const ZRQINIT_HDR: &'static [u8] = b"**\x18B00";
+    const ZRINIT_HDR: &'static [u8] = b"**\x18B01";
+
+    pub fn feed(&mut self, data: &[u8]) -> Option<ZmodemRequest> {
+        self.window.extend_from_slice(data);
+
+        let found = if contains(&self.window, Self::ZRQINIT_HDR) {
+            Some(ZmodemRequest::Receive)
+        } else if contains(&self.window, Self::ZRINIT_HDR) {
+            Some(ZmodemRequest::Send)
+        } else {
+            None
+        };
+
+        if found.is_some() {
+            self.window.clear();
+        } else {
+            // 只保留可能跨数据段的帧头前缀
+            let keep = Self::ZRQINIT_HDR.len() - 1;
+            if self.window.len() > keep {
+                self.window.drain(..self.window.len() - keep);
+            }
+        }
+        found
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+// ============ 帧头 ============
+
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    kind: u8,
+    data: [u8; 4],
+    crc32: bool, // 帧头为 ZBIN32 时，随后的数据子包使用 CRC-32
+}
+
+impl Header {
+    fn pos(&self) -> u64 {
+        u32::from_le_bytes(self.data) as u64
+    }
+
+    fn zf0(&self) -> u8 {
+        self.data[3]
+    }
+}
+
+fn pos_data(pos: u64) -> [u8; 4] {
+    (pos as u32).to_le_bytes()
+}
+
+fn needs_escape(b: u8) -> bool {
+    matches!(b, ZDLE | 0x10 | 0x90 | XON | 0x91 | XOFF | 0x93)
+}
+
+fn escape_into(out: &mut Vec<u8>, data: &[u8]) {
+    for &b in data {
+        if needs_escape(b) {
+            out.push(ZDLE);
+            out.push(b ^ 0x40);
+        } else {
+            out.push(b);
+        }
+    }
+}
+
+fn hex_header(kind: u8, data: [u8; 4]) -> Vec<u8> {
+    let mut raw = vec![kind];
+    raw.extend_from_slice(&data);
+    raw.extend_from_slice(&crc16_xmodem(&raw).to_be_bytes());
+
+    let mut out = vec![ZPAD, ZPAD, ZDLE, ZHEX];
+    for b in raw {
+        out.extend(format!("{:02x}", b).into_bytes());
+    }
+    out.extend_from_slice(b"\r\n");
+    if kind != ZFIN && kind != ZACK {
+        out.push(XON);
+    }
+    out
+}
+
+fn bin_header(kind: u8, data: [u8; 4], use_crc32: bool) -> Vec<u8> {
+    let mut raw = vec![kind];
+    raw.extend_from_slice(&data);
+
+    let mut out = vec![ZPAD, ZDLE];
+    if use_crc32 {
+        out.push(ZBIN32);
+        raw.extend_from_slice(&crc32(&raw).to_le_bytes());
+    } else {
+        out.push(ZBIN);
+        raw.extend_from_slice(&crc16_xmodem(&raw).to_be_bytes());
+    }
+    escape_into(&mut out, &raw);
+    out
+}
+
+fn subpacket(data: &[u8], end: u8, use_crc32: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 16);
+    escape_into(&mut out, data);
+    out.push(ZDLE);
+    out.push(end);
+
+    let mut covered = data.to_vec();
+    covered.push(end);
+    if use_crc32 {
+        escape_into(&mut out, &crc32(&covered).to_le_bytes());
+    } else {
+        escape_into(&mut out, &crc16_xmodem(&covered).to_be_bytes());
+    }
+    out
+}
+
+enum Escaped {
+    Byte(u8),
+    FrameEnd(u8),
+}
+
+/// 读取一个 ZDLE 转义后的字节，忽略流控字符
+fn read_escaped(link: &mut SessionLink) -> Result<Option<Escaped>, String> {
+    let mut cans = 0;
+    loop {
+        let b = match link.read_byte(BYTE_TIMEOUT)? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        if matches!(b, XON | XOFF | 0x91 | 0x93) {
+            continue;
+        }
+        if b != ZDLE {
+            return Ok(Some(Escaped::Byte(b)));
+        }
+
+        loop {
+            let c = match link.read_byte(BYTE_TIMEOUT)? {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            match c {
+                ZDLE => {
+                    cans += 1;
+                    if cans >= 4 {
+                        return Err("对方取消了传输".to_string());
+                    }
+                }
+                XON | XOFF | 0x91 | 0x93 => {}
+                ZCRCE | ZCRCG | ZCRCQ | ZCRCW => return Ok(Some(Escaped::FrameEnd(c))),
+                ZRUB0 => return Ok(Some(Escaped::Byte(0x7F))),
+                ZRUB1 => return Ok(Some(Escaped::Byte(0xFF))),
+                _ if c & 0x60 == 0x40 => return Ok(Some(Escaped::Byte(c ^ 0x40))),
+                _ => return Ok(None),
+            }
+        }
+    }
+}
+
+fn read_escaped_bytes(link: &mut SessionLink, n: usize) -> Result<Option<Vec<u8>>, String> {
+    let mut out = Vec::with_capacity(n);
+    while out.len() < n {
+        match read_escaped(link)? {
+            Some(Escaped::Byte(b)) => out.push(b),
+            _ => return Ok(None),
+        }
+    }
+    Ok(Some(out))
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// 等待下一个有效帧头，跳过其间的杂乱数据，超时返回 None
+fn read_header(link: &mut SessionLink, timeout: Duration) -> Result<Option<Header>, String> {
+    let deadline = Instant::now() + timeout;
+    let mut cans = 0;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        match link.read_byte(remaining)? {
+            Some(ZPAD) => {
+                cans = 0;
+                if let Some(header) = parse_header(link)? {
+                    return Ok(Some(header));
+                }
+            }
+            Some(ZDLE) => {
+                cans += 1;
+                if cans >= 5 {
+                    return Err("对方取消了传输".to_string());
+                }
+            }
+            Some(_) => cans = 0,
+            None => return Ok(None),
+        }
+    }
+}
+
+/// 解析 ZPAD 之后的帧头，格式或校验错误返回 None
+fn parse_header(link: &mut SessionLink) -> Result<Option<Header>, String> {
+    let mut b = ZPAD;
+    while b == ZPAD {
+        b = match link.read_byte(BYTE_TIMEOUT)? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+    }
+    if b != ZDLE {
+        return Ok(None);
+    }
+
+    let format = match link.read_byte(BYTE_TIMEOUT)? {
+        Some(f) => f,
+        None => return Ok(None),
+    };
+
+    let (raw, crc32_mode) = match format {
+        ZHEX => {
+            let mut raw = Vec::with_capacity(7);
+            for _ in 0..7 {
+                let pair = match link.read_exact(2, BYTE_TIMEOUT)? {
+                    Some(p) => p,
+                    None => return Ok(None),
+                };
+                match (hex_value(pair[0] & 0x7F), hex_value(pair[1] & 0x7F)) {
+                    (Some(h), Some(l)) => raw.push((h << 4) | l),
+                    _ => return Ok(None),
+                }
+            }
+            if crc16_xmodem(&raw[..5]).to_be_bytes() != raw[5..] {
+                return Ok(None);
+            }
+            (raw, false)
+        }
+        ZBIN => {
+            let raw = match read_escaped_bytes(link, 7)? {
+                Some(r) => r,
+                None => return Ok(None),
+            };
+            if crc16_xmodem(&raw[..5]).to_be_bytes() != raw[5..] {
+                return Ok(None);
+            }
+            (raw, false)
+        }
+        ZBIN32 => {
+            let raw = match read_escaped_bytes(link, 9)? {
+                Some(r) => r,
+                None => return Ok(None),
+            };
+            if crc32(&raw[..5]).to_le_bytes() != raw[5..] {
+                return Ok(None);
+            }
+            (raw, true)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(Header {
+        kind: raw[0],
+        data: [raw[1], raw[2], raw[3], raw[4]],
+        crc32: crc32_mode,
+    }))
+}
+
+/// 读取一个数据子包，CRC 错误或超时返回 None
+fn read_subpacket(link: &mut SessionLink, use_crc32: bool) -> Result<Option<(Vec<u8>, u8)>, String> {
+    let mut data = Vec::with_capacity(SUBPACKET_SIZE);
+
+    let end = loop {
+        match read_escaped(link)? {
+            Some(Escaped::Byte(b)) => {
+                data.push(b);
+                if data.len() > MAX_SUBPACKET {
+                    return Ok(None);
+                }
+            }
+            Some(Escaped::FrameEnd(end)) => break end,
+            None => return Ok(None),
+        }
+    };
+
+    let crc_len = if use_crc32 { 4 } else { 2 };
+    let crc = match read_escaped_bytes(link, crc_len)? {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+
+    let mut covered = data.clone();
+    covered.push(end);
+    let ok = if use_crc32 {
+        crc32(&covered).to_le_bytes() == crc[..]
+    } else {
+        crc16_xmodem(&covered).to_be_bytes() == crc[..]
+    };
+
+    Ok(if ok { Some((data, end)) } else { None })
+}
+
+/// 取消序列：8 个 CAN 后跟 8 个退格，对端收到后中止传输
+pub const ABORT_SEQUENCE: [u8; 16] = [
+    ZDLE, ZDLE, ZDLE, ZDLE, ZDLE, ZDLE, ZDLE, ZDLE,
+    0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08,
+];
+
+fn abort(link: &mut SessionLink) {
+    let _ = link.write(&ABORT_SEQUENCE);
+}
+
+fn guard<T>(link: &mut SessionLink, result: Result<T, String>) -> Result<T, String> {
+    if result.is_err() {
+        abort(link);
+    }
+    result
+}
+
+// ============ 接收 ============
+
+pub fn receive_zmodem(link: &mut SessionLink, dir: &str, on_progress: ProgressFn) -> Result<(), String> {
+    let result = receive_session(link, dir, on_progress);
+    guard(link, result)
+}
+
+fn send_zrinit(link: &mut SessionLink) -> Result<(), String> {
+    link.write(&hex_header(ZRINIT, [0, 0, 0, CANFDX | CANOVIO | CANFC32]))
+}
+
+fn receive_session(link: &mut SessionLink, dir: &str, mut on_progress: ProgressFn) -> Result<(), String> {
+    send_zrinit(link)?;
+    let mut timeouts = 0;
+
+    loop {
+        let header = match read_header(link, HEADER_TIMEOUT)? {
+            Some(h) => h,
+            None => {
+                timeouts += 1;
+                if timeouts > MAX_RETRIES {
+                    return Err("等待发送方超时".to_string());
+                }
+                send_zrinit(link)?;
+                continue;
+            }
+        };
+        timeouts = 0;
+
+        match header.kind {
+            ZRQINIT => send_zrinit(link)?,
+            ZSINIT => {
+                read_subpacket(link, header.crc32)?;
+                link.write(&hex_header(ZACK, [0; 4]))?;
+            }
+            ZFILE => {
+                let info = match read_subpacket(link, header.crc32)? {
+                    Some((info, _)) => info,
+                    None => {
+                        link.write(&hex_header(ZNAK, [0; 4]))?;
+                        continue;
+                    }
+                };
+                let resume = header.zf0() == ZCRESUM;
+                receive_file(link, dir, &info, resume, &mut on_progress)?;
+                send_zrinit(link)?;
+            }
+            ZFIN => {
+                link.write(&hex_header(ZFIN, [0; 4]))?;
+                // 发送方最后发送 "OO"
+                let _ = link.read_exact(2, Duration::from_secs(1));
+                return Ok(());
+            }
+            ZCOMMAND => {
+                // 不执行对方请求的命令，以非零状态回复 ZCOMPL
+                read_subpacket(link, header.crc32)?;
+                link.write(&hex_header(ZCOMPL, [1, 0, 0, 0]))?;
+            }
+            ZCAN | ZABORT => return Err("发送方中止了传输".to_string()),
+            _ => send_zrinit(link)?,
+        }
+    }
+}
+
+/// 接收一个文件。发送方请求续传 (ZCRESUM) 且本地同名文件较短时从其末尾续传，
+/// 其他情况下本地已有同名文件则另存为 name.1、name.2…，不覆盖已有文件
+fn receive_file(
+    link: &mut SessionLink,
+    dir: &str,
+    info: &[u8],
+    resume: bool,
+    on_progress: &mut ProgressFn,
+) -> Result<(), String> {
+    let name_end = info.iter().position(|&b| b == 0).unwrap_or(info.len());
+    let name = String::from_utf8_lossy(&info[..name_end]).to_string();
+    let size = info
+        .get(name_end + 1..)
+        .unwrap_or_default()
+        .split(|&b| b == b' ' || b == 0)
+        .next()
+        .and_then(|s| std::str::from_utf8(s).ok())
+        .and_then(|s| s.parse::<u64>().ok());
+
+    // 只保留文件名部分，防止写到目标目录之外
+    let file_name = match Path::new(&name).file_name() {
+        Some(n) => n.to_string_lossy().to_string(),
+        None => {
+            link.write(&hex_header(ZSKIP, [0; 4]))?;
+            return Ok(());
+        }
+    };
+    let mut target = Path::new(dir).join(&file_name);
+
+    let existing = fs::metadata(&target).ok().map(|m| m.len());
+    let offset = match (existing, size) {
+        (None, _) => 0,
+        (Some(existing), Some(size)) if resume && existing == size && size > 0 => {
+            // 已完整存在，跳过
+            link.write(&hex_header(ZSKIP, [0; 4]))?;
+            return Ok(());
+        }
+        (Some(existing), Some(size)) if resume && existing < size => existing,
+        (Some(_), _) => {
+            target = unused_path(&target);
+            0
+        }
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(offset == 0)
+        .open(&target)
+        .map_err(|e| format!("创建文件 {} 失败: {}", target.display(), e))?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("写入文件失败: {}", e))?;
+
+    let mut progress = TransferProgress {
+        protocol: "ZMODEM".to_string(),
+        file: target.to_string_lossy().to_string(),
+        bytes: offset,
+        total: size,
+        ..Default::default()
+    };
+    on_progress(&progress);
+
+    let mut pos = offset;
+    link.write(&hex_header(ZRPOS, pos_data(pos)))?;
+
+    let mut errors = 0;
+    loop {
+        let header = match read_header(link, HEADER_TIMEOUT)? {
+            Some(h) => h,
+            None => {
+                errors += 1;
+                if errors > MAX_RETRIES {
+                    return Err("接收超时".to_string());
+                }
+                link.write(&hex_header(ZRPOS, pos_data(pos)))?;
+                continue;
+            }
+        };
+
+        match header.kind {
+            ZDATA => {
+                if header.pos() != pos {
+                    link.write(&hex_header(ZRPOS, pos_data(pos)))?;
+                    continue;
+                }
+
+                loop {
+                    match read_subpacket(link, header.crc32)? {
+                        Some((data, end)) => {
+                            file.write_all(&data)
+                                .map_err(|e| format!("写入文件失败: {}", e))?;
+                            pos += data.len() as u64;
+                            errors = 0;
+
+                            progress.bytes = pos;
+                            progress.blocks += 1;
+                            on_progress(&progress);
+
+                            match end {
+                                ZCRCW => {
+                                    link.write(&hex_header(ZACK, pos_data(pos)))?;
+                                    break;
+                                }
+                                ZCRCQ => link.write(&hex_header(ZACK, pos_data(pos)))?,
+                                ZCRCE => break,
+                                _ => {}
+                            }
+                        }
+                        None => {
+                            errors += 1;
+                            progress.retries += 1;
+                            on_progress(&progress);
+                            if errors > MAX_RETRIES {
+                                return Err("接收错误次数过多".to_string());
+                            }
+                            link.write(&hex_header(ZRPOS, pos_data(pos)))?;
+                            break;
+                        }
+                    }
+                }
+            }
+            // 过期的 ZEOF（位置不符）直接忽略
+            ZEOF if header.pos() == pos => {
+                file.flush().map_err(|e| format!("写入文件失败: {}", e))?;
+                return Ok(());
+            }
+            ZFILE => {
+                // ZRPOS 丢失，发送方重发了文件头
+                read_subpacket(link, header.crc32)?;
+                link.write(&hex_header(ZRPOS, pos_data(pos)))?;
+            }
+            ZNAK => link.write(&hex_header(ZRPOS, pos_data(pos)))?,
+            ZCAN | ZABORT | ZFIN | ZFERR => return Err("发送方中止了传输".to_string()),
+            _ => {}
+        }
+    }
+}
+
+// 在文件名后追加 .1、.2… 直到不与已有文件重名
+fn unused_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    (1..)
+        .map(|i| path.with_file_name(format!("{}.{}", name, i)))
+        .find(|p| !p.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+// ============ 发送 ============
+
+pub fn send_zmodem(link: &mut SessionLink, paths: &[String], on_progress: ProgressFn) -> Result<(), String> {
+    let result = send_session(link, paths, on_progress);
+    guard(link, result)
+}
+
+fn send_session(link: &mut SessionLink, paths: &[String], mut on_progress: ProgressFn) -> Result<(), String> {
+    // 与 lrzsz 一致，先发送 "rz\r" 以便在 shell 中启动对方的接收程序
+    link.write(b"rz\r")?;
+    let use_crc32 = wait_for_zrinit(link)?;
+
+    for path in paths {
+        send_file(link, path, use_crc32, &mut on_progress)?;
+    }
+
+    for _ in 0..3 {
+        link.write(&hex_header(ZFIN, [0; 4]))?;
+        if let Some(h) = read_header(link, HEADER_TIMEOUT)? {
+            if h.kind == ZFIN {
+                break;
+            }
+        }
+    }
+    link.write(b"OO")
+}
+
+/// 发送 ZRQINIT 直到收到 ZRINIT，返回对方是否支持 CRC-32
+fn wait_for_zrinit(link: &mut SessionLink) -> Result<bool, String> {
+    for _ in 0..MAX_RETRIES {
+        link.write(&hex_header(ZRQINIT, [0; 4]))?;
+
+        match read_header(link, Duration::from_secs(5))? {
+            Some(h) if h.kind == ZRINIT => return Ok(h.zf0() & CANFC32 != 0),
+            Some(h) if h.kind == ZCHALLENGE => link.write(&hex_header(ZACK, h.data))?,
+            Some(h) if h.kind == ZCAN || h.kind == ZABORT => {
+                return Err("接收方中止了传输".to_string())
+            }
+            _ => {}
+        }
+    }
+    Err("等待接收方超时".to_string())
+}
+
+fn send_file(link: &mut SessionLink, path: &str, use_crc32: bool, on_progress: &mut ProgressFn) -> Result<(), String> {
+    let mut file = File::open(path).map_err(|e| format!("打开文件 {} 失败: {}", path, e))?;
+    let meta = file.metadata().map_err(|e| format!("读取文件信息失败: {}", e))?;
+    let len = meta.len();
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+
+    let mut info = name.into_bytes();
+    info.push(0);
+    info.extend(format!("{} {:o} 100644 0 1 {}", len, mtime, len).into_bytes());
+    info.push(0);
+
+    let mut progress = TransferProgress {
+        protocol: "ZMODEM".to_string(),
+        file: path.to_string(),
+        total: Some(len),
+        ..Default::default()
+    };
+
+    // 发送文件头，等待对方给出起始位置
+    let mut start = None;
+    for _ in 0..MAX_RETRIES {
+        let mut frame = bin_header(ZFILE, [0, 0, 0, ZCRESUM], use_crc32);
+        frame.extend(subpacket(&info, ZCRCW, use_crc32));
+        link.write(&frame)?;
+
+        match read_header(link, HEADER_TIMEOUT)? {
+            Some(h) if h.kind == ZRPOS => {
+                start = Some(h.pos());
+                break;
+            }
+            Some(h) if h.kind == ZSKIP => return Ok(()),
+            Some(h) if h.kind == ZCRC => {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content)
+                    .map_err(|e| format!("读取文件失败: {}", e))?;
+                link.write(&hex_header(ZCRC, crc32(&content).to_le_bytes()))?;
+            }
+            Some(h) if h.kind == ZCAN || h.kind == ZABORT || h.kind == ZFERR => {
+                return Err("接收方中止了传输".to_string())
+            }
+            _ => {}
+        }
+    }
+    let mut pos = start.ok_or("接收方未响应文件头")?;
+
+    let mut retries = 0;
+    loop {
+        if pos > len {
+            pos = len;
+        }
+        file.seek(SeekFrom::Start(pos))
+            .map_err(|e| format!("读取文件失败: {}", e))?;
+        progress.bytes = pos;
+        on_progress(&progress);
+
+        match stream_data(link, &mut file, pos, len, use_crc32, &mut progress, on_progress)? {
+            StreamResult::Done => {}
+            StreamResult::Reposition(p) => {
+                retries += 1;
+                progress.retries += 1;
+                if retries > MAX_RETRIES {
+                    return Err("重传次数过多".to_string());
+                }
+                pos = p;
+                continue;
+            }
+        }
+
+        // 数据发送完毕，发送 ZEOF 并等待下一个 ZRINIT
+        let mut eof_acked = false;
+        for _ in 0..MAX_RETRIES {
+            link.write(&bin_header(ZEOF, pos_data(len), use_crc32))?;
+            match read_header(link, HEADER_TIMEOUT)? {
+                Some(h) if h.kind == ZRINIT => {
+                    eof_acked = true;
+                    break;
+                }
+                Some(h) if h.kind == ZRPOS => {
+                    pos = h.pos();
+                    break;
+                }
+                Some(h) if h.kind == ZSKIP => return Ok(()),
+                Some(h) if h.kind == ZCAN || h.kind == ZABORT || h.kind == ZFERR => {
+                    return Err("接收方中止了传输".to_string())
+                }
+                _ => {}
+            }
+        }
+
+        if eof_acked {
+            return Ok(());
+        }
+        retries += 1;
+        progress.retries += 1;
+        if retries > MAX_RETRIES {
+            return Err("重传次数过多".to_string());
+        }
+    }
+}
+
+enum StreamResult {
+    Done,
+    Reposition(u64),
+}
+
+/// 从 pos 开始发送 ZDATA 与数据子包，每 WINDOW 个子包等待一次 ZACK
+fn stream_data(
+    link: &mut SessionLink,
+    file: &mut File,
+    mut pos: u64,
+    len: u64,
+    use_crc32: bool,
+    progress: &mut TransferProgress,
+    on_progress: &mut ProgressFn,
+) -> Result<StreamResult, String> {
+    if pos >= len {
+        return Ok(StreamResult::Done);
+    }
+
+    link.write(&bin_header(ZDATA, pos_data(pos), use_crc32))?;
+
+    let mut buf = vec![0u8; SUBPACKET_SIZE];
+    let mut count = 0;
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("读取文件失败: {}", e))?;
+        let last = pos + n as u64 >= len || n == 0;
+        count += 1;
+
+        let end = if last {
+            ZCRCE
+        } else if count % WINDOW == 0 {
+            ZCRCW
+        } else {
+            ZCRCG
+        };
+        link.write(&subpacket(&buf[..n], end, use_crc32))?;
+        pos += n as u64;
+
+        progress.bytes = pos;
+        progress.blocks += 1;
+        on_progress(progress);
+
+        if last {
+            return Ok(StreamResult::Done);
+        }
+
+        if end == ZCRCW {
+            match read_header(link, HEADER_TIMEOUT)? {
+                Some(h) if h.kind == ZACK => {
+                    // ZCRCW 之后需要新的 ZDATA 帧头
+                    link.write(&bin_header(ZDATA, pos_data(pos), use_crc32))?;
+                }
+                Some(h) if h.kind == ZRPOS => return Ok(StreamResult::Reposition(h.pos())),
+                Some(h) if h.kind == ZCAN || h.kind == ZABORT || h.kind == ZFERR => {
+                    return Err("接收方中止了传输".to_string())
+                }
+                // 超时或其他帧：从当前位置重新开始
+                _ => return Ok(StreamResult::Reposition(pos)),
+            }
+        }
+    }
+}